@@ -0,0 +1,17 @@
+use std::process::Command;
+
+fn main() {
+    // record the commit being built so the proxy can report it
+    if let Ok(output) = Command::new("git")
+        .args(&["rev-parse", "--short", "HEAD"])
+        .output()
+    {
+        if output.status.success() {
+            println!(
+                "cargo:rustc-env=SPLINTER_GIT_COMMIT={}",
+                String::from_utf8_lossy(&output.stdout).trim()
+            );
+        }
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+}
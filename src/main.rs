@@ -19,7 +19,7 @@ use crate::proxy::{
     SplinterProxy,
 };
 
-const CONFIG_FILENAME: &str = "./config.ron";
+pub const CONFIG_FILENAME: &str = "./config.ron";
 
 fn main() -> anyhow::Result<()> {
     splinter_logging::init()?;
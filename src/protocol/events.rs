@@ -7,11 +7,19 @@ use crate::protocol::current::{
     protocol::{
         HasPacketId,
         HasPacketKind,
+        Id,
         PacketErr,
         RawPacket,
     },
 };
 
+/// A packet that owns all of its data, so that it can be queued
+pub enum OwnedPacket {
+    Deserialized(PacketLatest),
+    /// A packet that was never deserialized, as its id and body
+    Raw(Id, Vec<u8>),
+}
+
 /// A packet that is lazily deserialized when the deserialized packet is accessed
 pub struct LazyDeserializedPacket<'a> {
     raw_packet: Option<RawPacketLatest<'a>>,
@@ -59,6 +67,23 @@ impl<'a> LazyDeserializedPacket<'a> {
             self.raw_packet
         }
     }
+    /// Takes ownership of the packet's data. A raw packet is copied rather than deserialized
+    pub fn into_owned(self) -> Result<OwnedPacket, PacketErr> {
+        if self.is_deserialized() {
+            Ok(OwnedPacket::Deserialized(self.into_packet()?))
+        } else {
+            let raw_packet = self.raw_packet.unwrap();
+            Ok(OwnedPacket::Raw(raw_packet.id(), raw_packet.data().to_vec()))
+        }
+    }
+    /// Checks if deserializing this packet was attempted and failed
+    pub fn failed_to_deserialize(&self) -> bool {
+        matches!(self.de_packet, Some(Err(_)))
+    }
+    /// Gets the raw packet this was created from, regardless of whether it was deserialized
+    pub fn into_original_raw_packet(self) -> Option<RawPacketLatest<'a>> {
+        self.raw_packet
+    }
     /// Checks if this packet has an already deserialized version
     pub fn is_deserialized(&self) -> bool {
         self.de_packet.is_some()
@@ -1,15 +1,18 @@
 use std::{
-    collections::HashSet,
-    net::SocketAddr,
-    sync::{atomic::AtomicBool, Arc},
+    net::{SocketAddr, TcpStream},
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
+use async_dup::Arc as AsyncArc;
 use craftio_rs::CraftIo;
 use futures_lite::future;
-use smol::lock::Mutex;
+use smol::{Async, Timer};
 
-use super::{v_cur::send_position_set, AsyncCraftConnection, AsyncCraftWriter, Tags};
+use super::{
+    describe_version, v_cur::send_position_set, AsyncCraftConnection, AsyncCraftWriter, Tags,
+};
 use crate::{
     protocol::{
         current::{
@@ -20,12 +23,16 @@ use crate::{
         v_cur,
     },
     proxy::{
+        chat::send_welcome_messages,
         client::{ClientSettings, SplinterClient},
         mapping::uuid_from_name,
         server::SplinterServerConnection,
         SplinterProxy,
     },
-    systems::{playersave::DEFAULT_SPAWN_POSITION, zoning::world_to_chunk_position},
+    systems::{
+        routing::{route_login, Route},
+        zoning::DEFAULT_DIMENSION,
+    },
 };
 
 pub struct ClientBuilder<'a> {
@@ -33,27 +40,51 @@ pub struct ClientBuilder<'a> {
     pub name: Option<String>,
     pub uuid: Option<UUID4>,
     pub client_addr: SocketAddr,
+    /// Protocol version the client connected with
+    pub client_protocol: i32,
     pub client_writer: AsyncCraftWriter,
+    /// Stream the client is connected by, for controlling how packets are sent on it
+    pub client_stream: AsyncArc<Async<TcpStream>>,
     pub server_conn: Option<SplinterServerConnection>,
     pub settings: Option<ClientSettings>,
     pub position: Option<Vec3<f64>>,
+    /// Whether tags have been sent to the client yet. Tags are only sent while logging in, so
+    /// the built client doesn't keep this
+    pub tags_sent: bool,
+    /// Brand the client identified itself with, if it sent one
+    pub brand: Option<String>,
+    /// World the client joined in
+    pub dimension: Option<String>,
+    /// When the server had the client join the game
+    pub joined_at: Option<Instant>,
+    /// Entity id the client was told its player has
+    pub proxy_eid: Option<i32>,
 }
 
 impl<'a> ClientBuilder<'a> {
     pub fn new(
         proxy: &'a Arc<SplinterProxy>,
         client_addr: SocketAddr,
+        client_protocol: i32,
         client_writer: AsyncCraftWriter,
+        client_stream: AsyncArc<Async<TcpStream>>,
     ) -> Self {
         Self {
             proxy,
             name: None,
             uuid: None,
             client_addr,
+            client_protocol,
             server_conn: None,
             client_writer,
+            client_stream,
             settings: None,
             position: None,
+            tags_sent: false,
+            brand: None,
+            dimension: None,
+            joined_at: None,
+            proxy_eid: None,
         }
     }
     pub async fn login_start(&mut self, name: impl AsRef<str>) -> anyhow::Result<()> {
@@ -66,20 +97,50 @@ impl<'a> ClientBuilder<'a> {
         );
         let player_data_lock = self.proxy.player_data.lock().await;
         let plinfo = player_data_lock.players.get(self.uuid.as_ref().unwrap());
-        let spawn_pos = if let Some(plinfo) = plinfo {
+        let (spawn_dimension, spawn_x, spawn_z) = if let Some(plinfo) = plinfo {
             self.position = Some((plinfo.x, plinfo.y, plinfo.z).into());
-            (plinfo.x, plinfo.z)
+            (
+                plinfo.dimension.as_deref().unwrap_or(DEFAULT_DIMENSION),
+                plinfo.x,
+                plinfo.z,
+            )
         } else {
-            self.position = Some(DEFAULT_SPAWN_POSITION.into());
-            (DEFAULT_SPAWN_POSITION.0, DEFAULT_SPAWN_POSITION.2)
+            let spawn_position = self.proxy.config.spawn_position;
+            self.position = Some(spawn_position.into());
+            (DEFAULT_DIMENSION, spawn_position.0, spawn_position.2)
         };
         debug!("spawn position is {:?}", self.position.as_ref().unwrap());
-        let active_server_id = *self
-            .proxy
-            .zoner
-            .zones_in_point(world_to_chunk_position(spawn_pos))
-            .get(0)
-            .unwrap_or(&0);
+        let routed_server_id =
+            match route_login(self.proxy, &self.client_addr, self.name.as_ref().unwrap()) {
+                Route::Continue => None,
+                Route::Server(server_id) => Some(server_id),
+                Route::Deny(reason) => {
+                    v_cur::send_login_disconnect(&mut self.client_writer, &reason)
+                        .await
+                        .ok();
+                    bail!(
+                        "Login for \"{}\" from {} was denied: {}",
+                        self.name.as_ref().unwrap(),
+                        self.client_addr,
+                        reason
+                    );
+                }
+            };
+        let active_server_id = match routed_server_id {
+            Some(server_id) => server_id,
+            None => self
+                .proxy
+                .server_for_join(spawn_dimension, spawn_x, spawn_z)
+                .await
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No server for spawn position ({}, {}) and no default server is \
+                         configured",
+                        spawn_x,
+                        spawn_z
+                    )
+                })?,
+        };
         debug!("player should join server {}", active_server_id);
         let server = Arc::clone(
             self.proxy
@@ -87,28 +148,32 @@ impl<'a> ClientBuilder<'a> {
                 .read()
                 .await
                 .get(&active_server_id)
-                .unwrap(),
+                .ok_or_else(|| anyhow!("No server with id {}", active_server_id))?,
         );
-        let server_craft_conn = server
-            .connect()
+        if server.protocol != self.client_protocol {
+            // the server's chunk data and such would not be understood by the client
+            v_cur::send_login_disconnect(
+                &mut self.client_writer,
+                protocol_mismatch_message(server.protocol, self.client_protocol),
+            )
             .await
-            .with_context(|| "Failed to connect client to server")?;
-        let (server_reader, server_writer) = server_craft_conn.into_split();
-        let mut server_conn = SplinterServerConnection {
-            writer: Mutex::new(server_writer),
-            reader: Mutex::new(server_reader),
-            server: (*server).clone(),
-            alive: AtomicBool::new(true),
-            eid: -1,
-            uuid: UUID4::from(0u128),
-            known_chunks: Mutex::new(HashSet::new()),
-        };
+            .ok();
+            bail!(
+                "Client \"{}\" is on protocol {}, but server {} is on protocol {}",
+                self.name.as_ref().unwrap(),
+                self.client_protocol,
+                server.id,
+                server.protocol
+            );
+        }
+        let mut server_conn =
+            SplinterServerConnection::new((*server).clone(), server.connect().await?);
         info!(
             "Connection for client \"{}\" initiated with {}",
             self.name.as_ref().unwrap(),
             server.address
         );
-        v_cur::send_handshake(&mut server_conn, self.proxy)
+        v_cur::send_handshake(&mut server_conn)
             .await
             .with_context(|| {
                 format!(
@@ -129,11 +194,16 @@ impl<'a> ClientBuilder<'a> {
         self.server_conn = Some(server_conn);
         Ok(())
     }
-    pub fn login_set_compression(&mut self, threshold: i32) {
-        let threshold = if threshold > 0 { Some(threshold) } else { None };
+    pub fn login_set_compression(&mut self, threshold: i32) -> anyhow::Result<()> {
         let conn = self.server_conn.as_mut().unwrap();
-        conn.writer.get_mut().set_compression_threshold(threshold);
-        conn.reader.get_mut().set_compression_threshold(threshold);
+        let (read_threshold, write_threshold) = conn.server.compression_thresholds(threshold)?;
+        conn.writer
+            .get_mut()
+            .set_compression_threshold(write_threshold);
+        conn.reader
+            .get_mut()
+            .set_compression_threshold(read_threshold);
+        Ok(())
     }
     pub async fn login_success(
         &mut self,
@@ -172,12 +242,9 @@ impl<'a> ClientBuilder<'a> {
         Ok(())
     }
     pub async fn play_join_game(&mut self) -> anyhow::Result<()> {
-        const MAX_BRAND_SIZE: usize = 128;
-        let brand = if self.proxy.config.brand.len() >= MAX_BRAND_SIZE {
-            &self.proxy.config.brand[0..MAX_BRAND_SIZE]
-        } else {
-            self.proxy.config.brand.as_str()
-        };
+        self.joined_at = Some(Instant::now());
+        let server_id = self.server_conn.as_ref().unwrap().server.id;
+        let brand = v_cur::proxy_brand(&self.proxy.config, server_id);
         v_cur::send_brand(&mut self.client_writer, brand)
             .await
             .with_context(|| {
@@ -200,31 +267,73 @@ impl<'a> ClientBuilder<'a> {
                     self.server_conn.as_ref().unwrap().server.id,
                 )
             })?;
-        let tags_opt = self.proxy.tags.lock().await.as_ref().cloned();
+        let tags_opt = self
+            .proxy
+            .tags
+            .lock()
+            .await
+            .get(&self.client_protocol)
+            .cloned();
         if let Some(tags) = tags_opt {
-            v_cur::send_tags(&mut self.client_writer, &tags)
-                .await
-                .with_context(|| {
-                    format!(
-                        "Failed to send tags packet to client {}",
-                        self.name.as_ref().unwrap(),
-                    )
-                })?;
+            self.send_tags(&tags).await?;
         }
         Ok(())
     }
+    /// Handles tags from the client's server. The client's server is always on the client's
+    /// protocol, so the first tags from a server on each protocol are kept for that protocol
     pub async fn play_tags(&mut self, tags: Tags) -> anyhow::Result<()> {
-        if self.proxy.tags.lock().await.is_none() {
-            v_cur::send_tags(&mut self.client_writer, &tags)
-                .await
-                .with_context(|| {
-                    format!(
-                        "Failed to send tags packet to client {}",
-                        self.name.as_ref().unwrap(),
-                    )
-                })?;
-            *self.proxy.tags.lock().await = Some(tags);
+        let tags = self
+            .proxy
+            .tags
+            .lock()
+            .await
+            .entry(self.client_protocol)
+            .or_insert(tags)
+            .clone();
+        // the client may not have gone through the client settings path yet
+        self.send_tags(&tags).await
+    }
+    /// Sends empty tags to the client if the server hasn't sent any within the configured time
+    /// after joining, so that login can finish. Returns whether the empty tags were sent.
+    ///
+    /// This is checked between packets rather than on a timer, so it relies on the server
+    /// sending something after the time is up, which it does regularly (chunks, keep alives).
+    pub async fn check_tags_fallback(&mut self) -> anyhow::Result<bool> {
+        let delay = match self.proxy.config.tags_fallback_secs {
+            Some(secs) => Duration::from_secs_f64(secs),
+            None => return Ok(false),
+        };
+        let joined_at = match self.joined_at {
+            Some(joined_at) => joined_at,
+            None => return Ok(false),
+        };
+        // we also need the client's settings to finish logging in
+        if self.tags_sent || self.settings.is_none() || joined_at.elapsed() < delay {
+            return Ok(false);
+        }
+        warn!(
+            "No tags received for \"{}\" within {:?} of joining, sending empty tags",
+            self.name.as_ref().unwrap(),
+            delay
+        );
+        self.send_tags(&Tags::empty()).await?;
+        Ok(true)
+    }
+    /// Sends tags to the client if they have not already been sent
+    async fn send_tags(&mut self, tags: &Tags) -> anyhow::Result<()> {
+        if self.tags_sent {
+            return Ok(());
         }
+        let maps = self.proxy.id_maps_for(self.client_protocol).await?;
+        v_cur::send_tags(&mut self.client_writer, tags, &maps)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to send tags packet to client {}",
+                    self.name.as_ref().unwrap(),
+                )
+            })?;
+        self.tags_sent = true;
         Ok(())
     }
     pub async fn build(self) -> SplinterClient {
@@ -232,41 +341,102 @@ impl<'a> ClientBuilder<'a> {
             Arc::clone(self.proxy),
             self.name.unwrap(),
             self.client_writer,
+            self.client_stream,
             Arc::new(self.server_conn.unwrap()),
             self.position.unwrap(),
+            self.client_protocol,
+            self.proxy_eid.unwrap(),
         );
         cl.settings.store(Arc::new(self.settings.unwrap()));
+        if let Some(brand) = self.brand {
+            cl.brand.store(Arc::new(brand));
+        }
+        if let Some(dimension) = self.dimension {
+            cl.dimension.store(Arc::new(dimension));
+        }
         cl
     }
 }
 
+/// Gets the message shown to a player who can't join a server because it is on a different version
+pub fn protocol_mismatch_message(server_protocol: i32, client_protocol: i32) -> String {
+    format!(
+        "This part of the world runs on {}, but you are on {}",
+        describe_version(server_protocol),
+        describe_version(client_protocol),
+    )
+}
+
+/// Logs a client in and relays its packets until it disconnects
+///
+/// Returns an error only for failures on the proxy's or a server's end, such as a protocol error.
+/// A client closing the connection, even in the middle of logging in, is not an error.
 pub async fn handle_client_login(
     mut conn: AsyncCraftConnection,
+    stream: AsyncArc<Async<TcpStream>>,
     addr: SocketAddr,
     proxy: Arc<SplinterProxy>,
+    client_protocol: i32,
 ) -> anyhow::Result<()> {
     conn.set_state(State::Login);
     let (mut client_conn_reader, client_conn_writer) = conn.into_split();
-    let mut client_builder = ClientBuilder::new(&proxy, addr, client_conn_writer);
+    let mut client_builder =
+        ClientBuilder::new(&proxy, addr, client_protocol, client_conn_writer, stream);
     let mut next_sender = PacketDirection::ServerBound;
-    loop {
-        if let Some(val) = v_cur::handle_client_login_packet(
-            &mut next_sender,
-            &mut client_builder,
-            &mut client_conn_reader,
-        )
-        .await
-        .with_context(|| "Handling login packet")?
-        {
-            if val {
-                break;
+    // resolves to whether the client logged in, rather than closing the connection
+    let login = async {
+        loop {
+            if let Some(val) = v_cur::handle_client_login_packet(
+                &mut next_sender,
+                &mut client_builder,
+                &mut client_conn_reader,
+            )
+            .await
+            .with_context(|| "Handling login packet")?
+            {
+                if val || client_builder.check_tags_fallback().await? {
+                    break Ok::<_, anyhow::Error>(true);
+                }
+            } else {
+                break Ok(false);
             }
-        } else {
-            bail!(
-                "Client \"{}\", {} connection closed during login",
+        }
+    };
+    let login_timeout = proxy.config.login_timeout_secs.map(Duration::from_secs_f64);
+    let logged_in: anyhow::Result<Option<bool>> = match login_timeout {
+        Some(login_timeout) => {
+            future::or(async { login.await.map(Some) }, async {
+                Timer::after(login_timeout).await;
+                Ok(None)
+            })
+            .await
+        }
+        None => login.await.map(Some),
+    };
+    match logged_in? {
+        Some(true) => {}
+        Some(false) => {
+            // the client closing the connection isn't a problem on our end
+            info!(
+                "Client \"{}\", {} closed the connection during login",
                 client_builder.name.unwrap_or_else(String::new),
                 addr,
             );
+            return Ok(());
+        }
+        None => {
+            warn!(
+                "Client \"{}\", {} did not finish logging in within {:?}, disconnecting",
+                client_builder.name.as_deref().unwrap_or(""),
+                addr,
+                login_timeout.unwrap(),
+            );
+            // dropping the server connection closes it, so the server isn't left waiting on a
+            // login that will never finish
+            if let Some(server_conn) = client_builder.server_conn.take() {
+                server_conn.alive.store(false, Ordering::Relaxed);
+            }
+            return Ok(());
         }
     }
     let client = client_builder.build().await;
@@ -285,6 +455,13 @@ pub async fn handle_client_login(
         .write()
         .await
         .insert(client_arc.name.clone(), Arc::clone(&client_arc));
+    client_arc.send_join_commands().await;
+    // dropped when this returns, so the messages are cancelled if the client leaves first
+    let _welcome_task = if proxy.config.welcome_messages.is_empty() {
+        None
+    } else {
+        Some(smol::spawn(send_welcome_messages(Arc::clone(&client_arc))))
+    };
 
     // move on to relay loop
     let (res_a, res_b) = future::zip(
@@ -3,6 +3,7 @@ use std::{
     fmt::Debug,
     net::{SocketAddr, TcpStream},
     sync::{atomic::Ordering, Arc},
+    time::Duration,
 };
 
 use async_compat::Compat;
@@ -11,8 +12,10 @@ use craftio_rs::{CraftAsyncReader, CraftConnection, CraftReader, CraftWriter};
 use smol::Async;
 
 use crate::{
-    proxy::{client::SplinterClient, server::SplinterServerConnection, SplinterProxy},
-    systems::playersave::PlInfoPlayer,
+    proxy::{
+        client::SplinterClient, server::SplinterServerConnection, ClientKickReason, SplinterProxy,
+    },
+    systems::keepalive::read_before_silence_timeout,
 };
 
 pub mod current;
@@ -21,6 +24,8 @@ mod login;
 pub mod v_cur;
 pub use login::*;
 pub mod plugin;
+mod version;
+pub use version::*;
 
 use current::{
     proto::{HandshakeNextState, Packet756 as PacketLatest, RawPacket756 as RawPacketLatest},
@@ -46,27 +51,21 @@ pub struct Tags {
 ///
 /// Expects the JSON file to be in the format of a list of objects, and each object has a `name`
 /// string and an `id` number.
-fn load_json_id_name_pairs(data: impl AsRef<str>) -> Vec<(i32, String)> {
-    let parsed = match json::parse(data.as_ref()) {
-        Ok(parsed) => parsed,
-        Err(e) => {
-            error!("Failed to parse json: {}", e);
-            panic!("File parse error");
-        }
-    };
+fn load_json_id_name_pairs(data: impl AsRef<str>) -> anyhow::Result<Vec<(i32, String)>> {
+    let parsed = json::parse(data.as_ref())?;
     let mut list = vec![];
     for block_data in parsed.members() {
         list.push((
             block_data["id"]
                 .as_i32()
-                .expect("Failed to convert JSON id to i32"),
+                .ok_or_else(|| anyhow!("Failed to convert JSON id to i32"))?,
             block_data["name"]
                 .as_str()
-                .expect("Failed to convert JSON name to str")
+                .ok_or_else(|| anyhow!("Failed to convert JSON name to str"))?
                 .into(),
         ));
     }
-    list
+    Ok(list)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -79,6 +78,7 @@ pub enum PacketDestination {
 
 pub async fn handle_handshake(
     mut conn: AsyncCraftConnection,
+    stream: AsyncArc<Async<TcpStream>>,
     addr: SocketAddr,
     proxy: Arc<SplinterProxy>,
 ) -> anyhow::Result<()> {
@@ -87,7 +87,11 @@ pub async fn handle_handshake(
         Some(PacketLatest::Handshake(body)) => match body.next_state {
             HandshakeNextState::Status => v_cur::handle_client_status(conn, addr, proxy).await?,
             HandshakeNextState::Login => {
-                handle_client_login(conn, addr, proxy).await?;
+                if ProtocolVersion::from_number(*body.version).is_some() {
+                    handle_client_login(conn, stream, addr, proxy, *body.version).await?;
+                } else {
+                    v_cur::handle_unsupported_version(conn, addr, proxy, *body.version).await?;
+                }
             }
         },
         Some(other_packet) => bail!(
@@ -108,45 +112,91 @@ impl SplinterClient {
         let sender = PacketDirection::ClientBound;
         let mut active_server;
         loop {
-            // server->proxy->client
-            active_server = client.active_server.load();
-            if !self.alive.load(Ordering::Relaxed) || !active_server.alive.load(Ordering::Relaxed) {
-                // debug!(
-                //     "active connection for {}, {} no longer alive (client state: {:?})",
-                //     active_server.server.id,
-                //     &client.name,
-                //     self.alive.load(Ordering::Relaxed),
-                // );
-                break;
-            }
-            let server_reader: &mut AsyncCraftReader = &mut *active_server.reader.lock().await;
-            match v_cur::handle_server_packet(
-                &proxy,
-                self,
-                server_reader,
-                &active_server.server,
-                &sender,
-            )
-            .await
-            {
-                Ok(Some(())) => {}
-                Ok(None) => {
+            loop {
+                // server->proxy->client
+                active_server = client.active_server.load();
+                if !self.alive.load(Ordering::Relaxed)
+                    || !active_server.alive.load(Ordering::Relaxed)
+                {
                     // debug!(
-                    //     "server {} closed connection with {}!",
-                    //     active_server.server.id, &client.name
+                    //     "active connection for {}, {} no longer alive (client state: {:?})",
+                    //     active_server.server.id,
+                    //     &client.name,
+                    //     self.alive.load(Ordering::Relaxed),
                     // );
                     break;
                 }
-                Err(e) => {
-                    error!("Failed to handle packet from server: {:?}", e);
+                let server_reader: &mut AsyncCraftReader =
+                    &mut *active_server.reader.lock().await;
+                let handle = v_cur::handle_server_packet(
+                    &proxy,
+                    self,
+                    server_reader,
+                    &active_server.server,
+                    &sender,
+                );
+                let res = match read_before_silence_timeout(&proxy, handle).await {
+                    Some(res) => res,
+                    None => {
+                        warn!(
+                            "Server {} went silent for \"{}\", closing the connection",
+                            active_server.server.id, &self.name
+                        );
+                        break;
+                    }
+                };
+                match res {
+                    Ok(Some(())) => {}
+                    Ok(None) => {
+                        // debug!(
+                        //     "server {} closed connection with {}!",
+                        //     active_server.server.id, &client.name
+                        // );
+                        break;
+                    }
+                    Err(e) => {
+                        error!("Failed to handle packet from server: {:?}", e);
+                    }
                 }
             }
+            active_server.alive.store(false, Ordering::Relaxed);
+            debug!(
+                "Server connection between {} and server id {} closed",
+                self.name, active_server.server.id
+            );
+            if !self.alive.load(Ordering::Relaxed) {
+                break;
+            }
+            if client.is_reconnecting() {
+                // in a soft reconnect, the server kicks the connection being replaced before the
+                // new one is made active, so wait for it to be
+                drop(client.reconnect_lock.lock().await);
+                continue;
+            }
+            // the connection was closed after being replaced, like in a soft reconnect, so the
+            // client still has a server
+            let current_server = client.active_server.load();
+            if !Arc::ptr_eq(&*active_server, &*current_server)
+                && current_server.alive.load(Ordering::Relaxed)
+            {
+                continue;
+            }
+            if let Some(grace_period_secs) = proxy.config.backend_grace_period_secs {
+                if self
+                    .reconnect_active_server(Duration::from_secs_f64(grace_period_secs))
+                    .await
+                {
+                    continue;
+                }
+                if let Err(e) = proxy
+                    .kick_client(&self.name, ClientKickReason::ServerLost)
+                    .await
+                {
+                    error!("Failed to kick \"{}\" after losing their server: {}", &self.name, e);
+                }
+            }
+            break;
         }
-        active_server.alive.store(false, Ordering::Relaxed);
-        debug!(
-            "Server connection between {} and server id {} closed",
-            self.name, active_server.server.id
-        );
         Ok(())
     }
     pub async fn handle_client_relay(
@@ -172,18 +222,58 @@ impl SplinterClient {
             }
         }
         proxy.players.write().await.remove(&self.name);
+        proxy.display_names.lock().await.remove(&self.uuid);
         self.alive.store(false, Ordering::Relaxed);
-        let pos = &**self.position.load();
-        self.proxy.player_data.lock().await.players.insert(
-            self.uuid,
-            PlInfoPlayer {
-                x: pos.x,
-                y: pos.y,
-                z: pos.z,
-                name: self.name.clone(),
-            },
-        );
+        self.proxy.remember_player(self).await;
         info!("Client \"{}\" connection closed", &self.name);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use futures_lite::future;
+    use smol::Timer;
+
+    use super::*;
+    use crate::proxy::testing::client_with_dummies;
+
+    #[test]
+    fn relay_waits_for_soft_reconnect() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1, 2]).await;
+            // the server ends of the connections to servers 1 and 2, in the order they were made
+            let replaced_peer = listener.accept().unwrap().0;
+            let replacement_peer = listener.accept().unwrap().0;
+            let mut relay = smol::spawn({
+                let client = Arc::clone(&client);
+                async move {
+                    let proxy = Arc::clone(&client.proxy);
+                    client.handle_server_relay(proxy, Arc::clone(&client)).await
+                }
+            });
+
+            // the server kicks the connection being replaced before the replacement is active
+            let reconnect_guard = client.reconnect_lock.lock().await;
+            client.reconnecting.store(true, Ordering::Relaxed);
+            drop(replaced_peer);
+            Timer::after(Duration::from_millis(100)).await;
+            assert!(future::poll_once(&mut relay).await.is_none());
+
+            let replacement = Arc::clone(&client.dummy_servers.load()[0].1);
+            client.active_server.store(Arc::clone(&replacement));
+            client.reconnecting.store(false, Ordering::Relaxed);
+            drop(reconnect_guard);
+            Timer::after(Duration::from_millis(100)).await;
+            // the relay reads from the replacement, until it's closed too
+            assert!(future::poll_once(&mut relay).await.is_none());
+            assert!(replacement.alive.load(Ordering::Relaxed));
+            drop(replacement_peer);
+            relay.await.unwrap();
+            assert!(!replacement.alive.load(Ordering::Relaxed));
+        });
+    }
+}
@@ -5,3 +5,23 @@ pub fn position_set(x: f64, y: f64, z: f64) -> Vec<u8> {
     data.extend(f64::to_be_bytes(z));
     data
 }
+
+/// Reads a VarInt length-prefixed UTF-8 string, such as the one in a `minecraft:brand` plugin
+/// message
+pub fn read_string(data: &[u8]) -> Option<String> {
+    let mut len: usize = 0;
+    let mut offset = 0;
+    loop {
+        let byte = *data.get(offset)?;
+        len |= ((byte & 0x7F) as usize) << (7 * offset);
+        offset += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if offset >= 5 {
+            return None;
+        }
+    }
+    let bytes = data.get(offset..offset.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
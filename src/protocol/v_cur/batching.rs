@@ -0,0 +1,29 @@
+use super::parse_packet_kinds;
+use crate::protocol::current::PacketLatestKind;
+
+/// Packets that may be written to clients right away while batching, by the name they're given
+/// in the config
+const FLUSHABLE_PACKETS: &[(&str, PacketLatestKind)] = &[
+    // movement
+    ("EntityPosition", PacketLatestKind::PlayEntityPosition),
+    ("EntityPositionAndRotation", PacketLatestKind::PlayEntityPositionAndRotation),
+    ("EntityRotation", PacketLatestKind::PlayEntityRotation),
+    ("EntityHeadLook", PacketLatestKind::PlayEntityHeadLook),
+    ("EntityTeleport", PacketLatestKind::PlayEntityTeleport),
+    ("EntityVelocity", PacketLatestKind::PlayEntityVelocity),
+    ("PlayerPositionAndLook", PacketLatestKind::PlayServerPlayerPositionAndLook),
+    // combat
+    ("EntityAnimation", PacketLatestKind::PlayEntityAnimation),
+    ("EntityStatus", PacketLatestKind::PlayEntityStatus),
+    ("UpdateHealth", PacketLatestKind::PlayUpdateHealth),
+    ("BlockBreakAnimation", PacketLatestKind::PlayBlockBreakAnimation),
+    // connection
+    ("KeepAlive", PacketLatestKind::PlayServerKeepAlive),
+    ("Ping", PacketLatestKind::PlayPing),
+];
+
+/// Gets the packet kinds written to clients right away while batching from their names in the
+/// config
+pub fn parse_latency_sensitive_kinds(names: &[String]) -> anyhow::Result<Vec<PacketLatestKind>> {
+    parse_packet_kinds(names, FLUSHABLE_PACKETS, "written right away")
+}
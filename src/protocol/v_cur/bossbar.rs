@@ -0,0 +1,142 @@
+use std::{
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+    time::Duration,
+};
+
+use smol::Timer;
+
+use crate::{
+    protocol::{
+        current::{
+            proto::{
+                BossBarAction,
+                BossBarAddSpec,
+                BossBarColor,
+                BossBarDivision,
+                BossBarFlags,
+                PlayBossBarSpec,
+            },
+            uuid::UUID4,
+            PacketLatest,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::{
+        chat::ToChat,
+        client::SplinterClient,
+        mapping::uuid_from_bytes,
+        SplinterProxy,
+    },
+};
+
+/// Number of boss bars the proxy has shown, used to give each one its own uuid
+static BOSS_BAR_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// A boss bar shown to every player by the proxy rather than by a server
+#[derive(Clone)]
+pub struct ProxyBossBar {
+    pub uuid: UUID4,
+    pub title: String,
+    pub color: BossBarColor,
+}
+
+/// Gets the boss bar color with the given name, such as "red"
+pub fn parse_boss_bar_color(name: &str) -> Option<BossBarColor> {
+    Some(match name.to_lowercase().as_str() {
+        "pink" => BossBarColor::Pink,
+        "blue" => BossBarColor::Blue,
+        "red" => BossBarColor::Red,
+        "green" => BossBarColor::Green,
+        "yellow" => BossBarColor::Yellow,
+        "purple" => BossBarColor::Purple,
+        "white" => BossBarColor::White,
+        _ => return None,
+    })
+}
+
+/// Makes a uuid for a new proxy boss bar. These are name based in a namespace of their own, so
+/// they can't collide with the random uuids servers give their boss bars
+fn next_boss_bar_uuid() -> UUID4 {
+    let count = BOSS_BAR_COUNT.fetch_add(1, Ordering::Relaxed);
+    uuid_from_bytes(format!("SplinterBossBar:{}", count).as_bytes())
+}
+
+impl SplinterProxy {
+    /// Shows a boss bar to every player for a while, removing it afterwards
+    pub async fn show_boss_bar(
+        self: &Arc<Self>,
+        title: String,
+        color: BossBarColor,
+        duration: Duration,
+    ) {
+        let bar = ProxyBossBar {
+            uuid: next_boss_bar_uuid(),
+            title,
+            color,
+        };
+        self.boss_bars.lock().await.push(bar.clone());
+        for client in self.players.read().await.values() {
+            if let Err(e) = client.show_boss_bar(&bar).await {
+                error!("Failed to show boss bar to \"{}\": {:?}", &client.name, e);
+            }
+        }
+        let proxy = Arc::clone(self);
+        smol::spawn(async move {
+            Timer::after(duration).await;
+            proxy
+                .boss_bars
+                .lock()
+                .await
+                .retain(|shown| shown.uuid != bar.uuid);
+            for client in proxy.players.read().await.values() {
+                if let Err(e) = client.hide_boss_bar(bar.uuid).await {
+                    error!("Failed to remove boss bar for \"{}\": {:?}", &client.name, e);
+                }
+            }
+        })
+        .detach();
+    }
+}
+
+impl SplinterClient {
+    pub async fn show_boss_bar(&self, bar: &ProxyBossBar) -> anyhow::Result<()> {
+        self.write_packet(LazyDeserializedPacket::from_packet(
+            PacketLatest::PlayBossBar(PlayBossBarSpec {
+                uuid: bar.uuid,
+                action: BossBarAction::Add(BossBarAddSpec {
+                    title: bar.title.to_chat(),
+                    health: 1.,
+                    color: bar.color,
+                    division: BossBarDivision::NoDivision,
+                    flags: BossBarFlags(0),
+                }),
+            }),
+        ))
+        .await
+    }
+    pub async fn hide_boss_bar(&self, uuid: UUID4) -> anyhow::Result<()> {
+        self.write_packet(LazyDeserializedPacket::from_packet(
+            PacketLatest::PlayBossBar(PlayBossBarSpec {
+                uuid,
+                action: BossBarAction::Remove,
+            }),
+        ))
+        .await
+    }
+    /// Shows the client the proxy's boss bars again, so that they stay up after switching servers
+    pub async fn resend_proxy_boss_bars(&self) {
+        let bars = self.proxy.boss_bars.lock().await.clone();
+        for bar in bars.iter() {
+            if let Err(e) = self.show_boss_bar(bar).await {
+                error!("Failed to resend boss bar to \"{}\": {:?}", &self.name, e);
+                break;
+            }
+        }
+    }
+}
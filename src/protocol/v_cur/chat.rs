@@ -1,8 +1,14 @@
+use std::{
+    sync::Arc,
+    time::Instant,
+};
+
 use craftio_rs::CraftAsyncWriter;
 
 use super::{
     PacketDestination,
     RelayPass,
+    PRIORITY_CONSUME,
 };
 use crate::{
     protocol::{
@@ -10,37 +16,132 @@ use crate::{
             ChatPosition,
             Packet756 as PacketLatest,
             Packet756Kind as PacketLatestKind,
+            PlayActionBarSpec,
             PlayClientChatMessageSpec,
             PlayServerChatMessageSpec,
         },
         events::LazyDeserializedPacket,
+        ProtocolVersion,
     },
     proxy::{
         chat::{
             receive_chat_message,
             ToChat,
         },
-        client::SplinterClient,
+        client::{
+            Mute,
+            SplinterClient,
+        },
+    },
+    systems::commands::{
+        process_command,
+        CommandSender,
     },
-    systems::commands::CommandSender,
 };
 
 inventory::submit! {
-    RelayPass(Box::new(|proxy, _connection, client, sender, lazy_packet, destination| {
-        if lazy_packet.kind() == PacketLatestKind::PlayClientChatMessage {
-            match lazy_packet.packet() {
-                Ok(PacketLatest::PlayClientChatMessage(body)) => smol::block_on(receive_chat_message(proxy, client, sender, &body.message)),
-                Ok(_) => unreachable!(),
-                Err(e) => {
-                    error!("Failed to deserialize chat message: {}", e);
+    RelayPass {
+        priority: PRIORITY_CONSUME,
+        pass: Box::new(|proxy, _connection, client, sender, lazy_packet, destination| {
+            if lazy_packet.kind() == PacketLatestKind::PlayClientChatMessage {
+                match lazy_packet.packet() {
+                    Ok(PacketLatest::PlayClientChatMessage(body)) => smol::block_on(async {
+                        if client.is_muted_for(&body.message).await {
+                            client.notify_muted().await;
+                        } else if let Some(command) = client.proxy_command(&body.message) {
+                            client.run_proxy_command(command);
+                        } else {
+                            receive_chat_message(proxy, client, sender, &body.message).await;
+                        }
+                    }),
+                    Ok(_) => unreachable!(),
+                    Err(e) => {
+                        error!("Failed to deserialize chat message: {}", e);
+                    }
                 }
+                *destination = PacketDestination::None;
             }
-            *destination = PacketDestination::None;
-        }
-    }))
+        }),
+    }
 }
 
 impl SplinterClient {
+    /// Whether a chat message from the client should be dropped because the client is muted.
+    /// Commands are allowed unless the config says to mute them too
+    pub async fn is_muted_for(&self, msg: &str) -> bool {
+        let is_command = msg.starts_with('/') || self.proxy_command(msg).is_some();
+        if is_command && !self.proxy.config.mute_commands {
+            return false;
+        }
+        let mute = &mut *self.mute.lock().await;
+        match mute {
+            Some(Mute {
+                expires: Some(expires),
+            }) if *expires <= Instant::now() => {
+                *mute = None;
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+    /// The proxy command in a chat message from the client, without the configured prefix.
+    /// Messages without the prefix and passthrough commands aren't proxy commands
+    pub fn proxy_command<'a>(&self, msg: &'a str) -> Option<&'a str> {
+        let prefix = self.proxy.config.proxy_command_prefix.as_deref()?;
+        let command = msg.strip_prefix(prefix)?;
+        let name = command.split_whitespace().next()?;
+        if self
+            .proxy
+            .config
+            .passthrough_commands
+            .iter()
+            .any(|passthrough| passthrough == name)
+        {
+            return None;
+        }
+        Some(command)
+    }
+    /// Runs a proxy command for the client in the background, so that the command doesn't hold
+    /// up relaying the client's packets
+    fn run_proxy_command(self: &Arc<Self>, command: &str) {
+        info!("{} ran proxy command: {}", &self.name, command);
+        let split = command
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        let client = Arc::clone(self);
+        smol::spawn(async move {
+            // a command always has a name, or it wouldn't be a proxy command
+            let (cmd, args) = split.split_first().unwrap();
+            let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+            let sender = CommandSender::Player(Arc::clone(&client));
+            if let Err(e) = process_command(&client.proxy, cmd, args.as_slice(), &sender).await {
+                if let Err(e) = sender.respond(format!("Command failed: {:#}", e)).await {
+                    error!(
+                        "Failed to send command failure message to {}: {}",
+                        &client.name, e
+                    );
+                }
+            }
+        })
+        .detach();
+    }
+    /// Tells the client its message wasn't sent because it is muted, if the config says to
+    async fn notify_muted(&self) {
+        if !self.proxy.config.notify_muted {
+            return;
+        }
+        if let Err(e) = self
+            .send_message(
+                self.localize("muted", "You are muted", &[]),
+                &CommandSender::Console,
+            )
+            .await
+        {
+            error!("Failed to notify \"{}\" that they are muted: {}", &self.name, e);
+        }
+    }
     pub async fn send_message(
         &self,
         msg: impl ToChat,
@@ -58,6 +159,27 @@ impl SplinterClient {
         ))
         .await
     }
+    /// Shows a message above the client's hotbar
+    pub async fn send_action_bar(&self, msg: impl ToChat) -> anyhow::Result<()> {
+        match ProtocolVersion::from_number(self.protocol) {
+            // action bar text has its own packet from 1.17 onwards
+            Some(ProtocolVersion::V756) => {
+                self.write_packet(LazyDeserializedPacket::from_packet(
+                    PacketLatest::PlayActionBar(PlayActionBarSpec {
+                        action_bar_text: msg.to_chat(),
+                    }),
+                ))
+                .await
+            }
+            _ => {
+                warn!(
+                    "Not sending action bar message to \"{}\": unsupported for protocol {}",
+                    &self.name, self.protocol
+                );
+                Ok(())
+            }
+        }
+    }
     pub async fn relay_message(&self, msg: &str) -> anyhow::Result<()> {
         self.active_server
             .load()
@@ -1,6 +1,15 @@
+use std::{
+    collections::HashSet,
+    time::Duration,
+};
+
+use craftio_rs::CraftAsyncWriter;
+use smol::Timer;
+
 use super::{
     PacketDestination,
     RelayPass,
+    PRIORITY_CONSUME,
 };
 use crate::{
     protocol::current::{
@@ -10,6 +19,7 @@ use crate::{
     proxy::{
         client::{
             ChunkLoadData,
+            ClientSettings,
             SplinterClient,
         },
         server::SplinterServerConnection,
@@ -17,39 +27,51 @@ use crate::{
 };
 
 inventory::submit! {
-    RelayPass(Box::new(|_proxy, connection, client, _sender, lazy_packet, destination| {
-        if matches!(lazy_packet.kind(),
-            PacketLatestKind::PlayChunkData
-            | PacketLatestKind::PlayUpdateLight
-            | PacketLatestKind::PlayUnloadChunk
-        ) {
-            if let Ok(packet) = lazy_packet.packet() {
-                let pass_through = smol::block_on(async {
-                    match packet {
-                        PacketLatest::PlayChunkData(body) => {
-                            let chunk = (body.x, body.z);
-                            connection.update_chunk(&*client, true, chunk).await
-                        },
-                        PacketLatest::PlayUpdateLight(body) => {
-                            let chunk = (*body.chunk.x, *body.chunk.z);
-                            connection.update_chunk(&*client, false, chunk).await
-                        },
-                        PacketLatest::PlayUnloadChunk(body) => {
-                            let chunk = (body.position.x, body.position.z);
-                            connection.remove_chunk(&*client, chunk).await
-                        },
-                        _ => unreachable!(),
+    RelayPass {
+        priority: PRIORITY_CONSUME,
+        pass: Box::new(|_proxy, connection, client, _sender, lazy_packet, destination| {
+            if matches!(lazy_packet.kind(),
+                PacketLatestKind::PlayChunkData
+                | PacketLatestKind::PlayUpdateLight
+                | PacketLatestKind::PlayUnloadChunk
+            ) {
+                if let Ok(packet) = lazy_packet.packet() {
+                    let pass_through = smol::block_on(async {
+                        match packet {
+                            PacketLatest::PlayChunkData(body) => {
+                                let chunk = (body.x, body.z);
+                                connection.update_chunk(&*client, true, chunk).await
+                            },
+                            PacketLatest::PlayUpdateLight(body) => {
+                                let chunk = (*body.chunk.x, *body.chunk.z);
+                                connection.update_chunk(&*client, false, chunk).await
+                            },
+                            PacketLatest::PlayUnloadChunk(body) => {
+                                let chunk = (body.position.x, body.position.z);
+                                connection.remove_chunk(&*client, chunk).await
+                            },
+                            _ => unreachable!(),
+                        }
+                    });
+                    if !pass_through {
+                        *destination = PacketDestination::None;
                     }
-                });
-                if !pass_through {
-                    *destination = PacketDestination::None;
                 }
             }
-        }
-    }))
+        }),
+    }
 }
 
 impl SplinterServerConnection {
+    /// Sends the client's settings to the server
+    pub async fn send_settings(&self, settings: ClientSettings) -> anyhow::Result<()> {
+        self.writer
+            .lock()
+            .await
+            .write_packet_async(PacketLatest::PlayClientSettings(settings.into()))
+            .await?;
+        Ok(())
+    }
     /// Returns whether we pass the packet on
     pub async fn update_chunk(
         &self,
@@ -90,6 +112,14 @@ impl SplinterServerConnection {
             true
         }
     }
+    /// Releases this connection's hold on all the chunks it loaded for the client, without
+    /// unloading them on the client
+    pub async fn release_chunks(&self, client: &SplinterClient) {
+        let chunks = self.known_chunks.lock().await.iter().copied().collect::<Vec<_>>();
+        for chunk in chunks {
+            self.remove_chunk(client, chunk).await;
+        }
+    }
     pub async fn remove_chunk(&self, client: &SplinterClient, chunk: (i32, i32)) -> bool {
         if self.known_chunks.lock().await.remove(&chunk) {
             let client_known_chunks = &mut *client.known_chunks.lock().await;
@@ -105,3 +135,75 @@ impl SplinterServerConnection {
         return false;
     }
 }
+
+impl SplinterClient {
+    /// Makes the client's servers send it their chunks again, and forgets whatever chunks are
+    /// still tracked for it afterwards. Returns how many chunks the client was known to have
+    /// loaded beforehand.
+    ///
+    /// To get the servers to resend chunks, their view distance for the player is dropped to the
+    /// minimum and then restored. The chunks outside of the minimum distance are unloaded while it
+    /// is dropped, which still reaches the client as the chunks are tracked until then. Only once
+    /// that's had time to happen are the client's and each server connection's chunk tracking
+    /// cleared, so that they stay consistent with each other and the chunks sent once the view
+    /// distance is restored are passed on as new loads. Chunks within the minimum distance stay
+    /// loaded on the client without being tracked, and are dropped by the client on its own once
+    /// they are out of its view distance.
+    pub async fn resync_chunks(&self) -> anyhow::Result<usize> {
+        const MIN_VIEW_DISTANCE: i8 = 2;
+        let chunk_count = self.known_chunks.lock().await.len();
+        let active_server = self.active_server.load();
+        let dummy_servers = self.dummy_servers.load();
+        let connections = std::iter::once(&*active_server)
+            .chain(dummy_servers.iter().map(|(_, conn)| conn))
+            .collect::<Vec<_>>();
+        let settings = (**self.settings.load()).clone();
+        let mut nudged_settings = settings.clone();
+        nudged_settings.view_distance = MIN_VIEW_DISTANCE.min(settings.view_distance);
+        for conn in connections.iter() {
+            conn.send_settings(nudged_settings.clone()).await?;
+        }
+        Timer::after(Duration::from_secs(1)).await;
+        self.known_chunks.lock().await.clear();
+        for conn in connections.iter() {
+            conn.known_chunks.lock().await.clear();
+        }
+        for conn in connections.iter() {
+            conn.send_settings(settings.clone()).await?;
+        }
+        Ok(chunk_count)
+    }
+    /// Removes the client's known chunk entries that have a reference count of zero, which no
+    /// connection holds and so would never be unloaded. Returns how many entries were removed.
+    ///
+    /// Entries are only removed by their reference count, since one that a connection doesn't
+    /// know about may just be in the middle of being removed, or belong to a connection being
+    /// swapped that is neither active nor a dummy for the moment.
+    pub async fn compact_known_chunks(&self) -> usize {
+        let client_known_chunks = &mut *self.known_chunks.lock().await;
+        let count_before = client_known_chunks.len();
+        client_known_chunks.retain(|_, load_data| load_data.refcount > 0);
+        count_before - client_known_chunks.len()
+    }
+    /// Counts the client's known chunks that none of its connections know about, which may have
+    /// leaked from a missed unload. This is only a snapshot for diagnosing leaks, since chunks can
+    /// be added and removed while it's counted
+    pub async fn untracked_known_chunks(&self) -> usize {
+        let active_server = self.active_server.load();
+        let dummy_servers = self.dummy_servers.load();
+        let connections =
+            std::iter::once(&*active_server).chain(dummy_servers.iter().map(|(_, conn)| conn));
+        // each connection's chunks are locked before the client's, in the same order as removing
+        // a chunk locks them
+        let mut connection_chunks = HashSet::new();
+        for conn in connections {
+            connection_chunks.extend(conn.known_chunks.lock().await.iter().copied());
+        }
+        self.known_chunks
+            .lock()
+            .await
+            .keys()
+            .filter(|chunk| !connection_chunks.contains(chunk))
+            .count()
+    }
+}
@@ -0,0 +1,189 @@
+use craftio_rs::CraftAsyncWriter;
+
+use super::{
+    RelayPass,
+    PRIORITY_OBSERVE,
+};
+use crate::{
+    protocol::{
+        current::{
+            proto::{
+                DiggingFace,
+                DiggingStatus,
+                PlayBlockBreakAnimationSpec,
+                PlayPlayerDiggingSpec,
+                PlayerDiggingStatus,
+            },
+            protocol::PacketDirection,
+            types::IntPosition,
+            PacketLatest,
+            PacketLatestKind,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::{
+        client::SplinterClient,
+        server::SplinterServerConnection,
+    },
+};
+
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_OBSERVE,
+        pass: Box::new(|_proxy, _connection, client, sender, lazy_packet, _destination| {
+            if *sender == PacketDirection::ClientBound
+                && lazy_packet.kind() == PacketLatestKind::PlayAcknowledgePlayerDigging
+            {
+                match lazy_packet.packet() {
+                    Ok(PacketLatest::PlayAcknowledgePlayerDigging(body)) => {
+                        track_acknowledged_dig(
+                            &mut *smol::block_on(client.digging_at.lock()),
+                            body.location,
+                            &body.status,
+                            body.successful,
+                        );
+                    }
+                    Ok(_) => unreachable!(),
+                    Err(e) => error!("Failed to deserialize acknowledge digging packet: {}", e),
+                }
+            }
+        }),
+    }
+}
+
+/// Updates the block break in progress from the active server acknowledging a dig
+///
+/// Only the server's acknowledgements are tracked, since a break isn't in progress until the
+/// server accepts its start, and isn't over until the server acknowledges it was finished or
+/// cancelled.
+fn track_acknowledged_dig(
+    digging_at: &mut Option<IntPosition>,
+    location: IntPosition,
+    status: &DiggingStatus,
+    successful: bool,
+) {
+    match status {
+        DiggingStatus::Started if successful => *digging_at = Some(location),
+        DiggingStatus::Started => {}
+        DiggingStatus::Cancelled | DiggingStatus::Finished => {
+            if *digging_at == Some(location) {
+                *digging_at = None;
+            }
+        }
+    }
+}
+
+/// The packets that reset a block break, which are a cancel for the servers, and an animation
+/// stage for the client that removes the break animation
+fn digging_reset_packets(location: IntPosition, proxy_eid: i32) -> (PacketLatest, PacketLatest) {
+    let cancel = PacketLatest::PlayPlayerDigging(PlayPlayerDiggingSpec {
+        status: PlayerDiggingStatus::Cancelled,
+        location,
+        face: DiggingFace::Bottom,
+    });
+    // a destroy stage outside of 0-9 removes the animation
+    let clear_animation = PacketLatest::PlayBlockBreakAnimation(PlayBlockBreakAnimationSpec {
+        entity_id: proxy_eid.into(),
+        location,
+        destroy_stage: -1,
+    });
+    (cancel, clear_animation)
+}
+
+impl SplinterClient {
+    /// Resets a block break the client was in the middle of when switching away from a server
+    ///
+    /// The acknowledgement for the break would come from the server being switched away from, so
+    /// it never reaches the client. Instead, every server is told the break was cancelled and the
+    /// client's break animation is cleared. If the client keeps digging, the new server sees
+    /// the finished break without a start and corrects the client's block.
+    pub async fn reset_digging(&self, previous_conn: &SplinterServerConnection, proxy_eid: i32) {
+        let location = match self.digging_at.lock().await.take() {
+            Some(location) => location,
+            None => return,
+        };
+        debug!(
+            "resetting {}'s block break at {:?} after switching from server {}",
+            &self.name, location, previous_conn.server.id
+        );
+        let (cancel, clear_animation) = digging_reset_packets(location, proxy_eid);
+        let dummies = self.dummy_servers.load();
+        let connections = std::iter::once(previous_conn)
+            .chain(std::iter::once(&**self.active_server.load()))
+            .chain(dummies.iter().map(|(_, conn)| &**conn));
+        for conn in connections {
+            if let Err(e) = conn.writer.lock().await.write_packet_async(cancel.clone()).await {
+                error!(
+                    "Failed to cancel block break for {}-{}: {:?}",
+                    &self.name, conn.server.id, e
+                );
+            }
+        }
+        if let Err(e) = self
+            .write_packet(LazyDeserializedPacket::from_packet(clear_animation))
+            .await
+        {
+            error!(
+                "Failed to clear block break animation for \"{}\": {:?}",
+                &self.name, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location() -> IntPosition {
+        IntPosition {
+            x: 4,
+            y: 64,
+            z: -7,
+        }
+    }
+
+    #[test]
+    fn swap_mid_dig_clears_animation() {
+        let mut digging_at = None;
+        track_acknowledged_dig(&mut digging_at, location(), &DiggingStatus::Started, true);
+        assert_eq!(digging_at, Some(location()));
+
+        // the swap takes the break in progress, and clears its animation on the client
+        let location = digging_at.take().unwrap();
+        match digging_reset_packets(location, 5) {
+            (
+                PacketLatest::PlayPlayerDigging(cancel),
+                PacketLatest::PlayBlockBreakAnimation(animation),
+            ) => {
+                assert!(matches!(cancel.status, PlayerDiggingStatus::Cancelled));
+                assert_eq!(cancel.location, location);
+                assert_eq!(*animation.entity_id, 5);
+                assert_eq!(animation.location, location);
+                assert!(!(0..=9).contains(&animation.destroy_stage));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn only_acknowledged_digs_are_in_progress() {
+        let mut digging_at = None;
+        // a start the server refused
+        track_acknowledged_dig(&mut digging_at, location(), &DiggingStatus::Started, false);
+        assert_eq!(digging_at, None);
+
+        track_acknowledged_dig(&mut digging_at, location(), &DiggingStatus::Started, true);
+        // finishing a different block doesn't end the break
+        let other = IntPosition {
+            x: 5,
+            y: 64,
+            z: -7,
+        };
+        track_acknowledged_dig(&mut digging_at, other, &DiggingStatus::Finished, true);
+        assert_eq!(digging_at, Some(location()));
+
+        track_acknowledged_dig(&mut digging_at, location(), &DiggingStatus::Finished, true);
+        assert_eq!(digging_at, None);
+    }
+}
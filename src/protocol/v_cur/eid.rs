@@ -1,6 +1,9 @@
+use std::collections::HashSet;
+
 use super::{
     PacketDestination,
     RelayPass,
+    PRIORITY_TRANSLATE,
 };
 use crate::{
     protocol::current::{
@@ -8,41 +11,56 @@ use crate::{
             EntityMetadataFieldData,
             SculkDestinationIdentifier,
         },
-        protocol::PacketDirection,
+        protocol::{
+            HasPacketKind,
+            PacketDirection,
+        },
         types::VarInt,
         PacketLatest,
         PacketLatestKind,
     },
-    proxy::{
-        client::SplinterClient,
-        mapping::{
-            EntityData,
-            SplinterMapping,
-            SplinterMappingResult,
-        },
-        server::SplinterServer,
+    proxy::mapping::{
+        EntityData,
+        SplinterMapping,
+        SplinterMappingResult,
     },
 };
 
 inventory::submit! {
-    RelayPass(Box::new(|proxy, connection, client, sender, lazy_packet, destination| {
-        if has_eids(lazy_packet.kind()) {
-            if let Ok(packet) = lazy_packet.packet() {
-                let map = &mut *smol::block_on(proxy.mapping.lock());
-                match map_eid(&*client, map, packet, sender, &connection.server) {
-                    SplinterMappingResult::Server(server_id) => {
-                        *destination = PacketDestination::Server(server_id);
-                        //debug!("mapping packet {:?} to server {}", lazy_packet.kind(), server_id);
+    RelayPass {
+        priority: PRIORITY_TRANSLATE,
+        pass: Box::new(|proxy, connection, client, sender, lazy_packet, destination| {
+            if has_eids(lazy_packet.kind()) {
+                if let Ok(packet) = lazy_packet.packet() {
+                    let map = &mut *smol::block_on(proxy.mapping.lock());
+                    if let Some(server_eid) = spawned_eid(packet) {
+                        let max_mappings = proxy.config.max_eid_mappings;
+                        if !map.has_room_for_eid(connection.server.id, server_eid, max_mappings) {
+                            warn!(
+                                "Dropping spawn of entity {} from server {}, as {} eids are \
+                                 already mapped",
+                                server_eid, connection.server.id, max_mappings
+                            );
+                            *destination = PacketDestination::None;
+                            return;
+                        }
                     }
-                    SplinterMappingResult::None => {
-                        *destination = PacketDestination::None;
-                        //debug!("refusing to send packet of kind {:?} (no eid mapping)", packet);
+                    let known_eids = &mut *smol::block_on(client.known_eids.lock());
+                    match map_eid(known_eids, map, packet, sender, connection.server.id) {
+                        SplinterMappingResult::Server(server_id) => {
+                            *destination = PacketDestination::Server(server_id);
+                            //debug!("mapping packet {:?} to server {}", lazy_packet.kind(), server_id);
+                        }
+                        SplinterMappingResult::None => {
+                            *destination = PacketDestination::None;
+                            //debug!("refusing to send packet of kind {:?} (no eid mapping)", packet);
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
-        }
-    }))
+        }),
+    }
 }
 
 pub fn has_eids(kind: PacketLatestKind) -> bool {
@@ -85,12 +103,26 @@ pub fn has_eids(kind: PacketLatestKind) -> bool {
     )
 }
 
+/// The server eid of the entity a packet spawns, if it spawns one
+fn spawned_eid(packet: &PacketLatest) -> Option<i32> {
+    match packet {
+        PacketLatest::PlaySpawnEntity(body) => Some(*body.entity_id),
+        PacketLatest::PlaySpawnExperienceOrb(body) => Some(*body.entity_id),
+        PacketLatest::PlaySpawnLivingEntity(body) => Some(*body.entity_id),
+        PacketLatest::PlaySpawnPainting(body) => Some(*body.entity_id),
+        PacketLatest::PlaySpawnPlayer(body) => Some(*body.entity_id),
+        _ => None,
+    }
+}
+
+/// Maps the eids in a packet between a server's eids and the proxy's eids, recording the proxy
+/// eids a clientbound packet tells the client about in `known_eids`
 pub fn map_eid(
-    client: &SplinterClient,
+    known_eids: &mut HashSet<i32>,
     map: &mut SplinterMapping,
     packet: &mut PacketLatest,
     sender: &PacketDirection,
-    server: &SplinterServer,
+    server_id: u64,
 ) -> SplinterMappingResult {
     match sender {
         PacketDirection::ClientBound => {
@@ -128,36 +160,41 @@ pub fn map_eid(
                     }
                 }
                 PacketLatest::PlayFacePlayer(body) => {
-                    if let Some(target) = body.entity.as_mut() {
-                        (vec![], vec![&mut target.entity_id])
-                    } else {
-                        (vec![], vec![])
+                    let target_eid = body.entity.as_ref().map(|target| *target.entity_id);
+                    if let Some(target_eid) = target_eid {
+                        match map.eids.get_by_right(&(server_id, target_eid)) {
+                            Some(proxy_eid) => {
+                                body.entity.as_mut().unwrap().entity_id = (*proxy_eid).into();
+                            }
+                            // the server also sends where the entity is, so the client can
+                            // face that instead of the packet being dropped
+                            None => body.entity = None,
+                        }
                     }
+                    (vec![], vec![])
+                }
+                PacketLatest::PlayAttachEntity(body) => {
+                    // the holder may not have spawned yet, in which case dropping the packet
+                    // would leave the leash unattached for good
+                    if body.holding_entity_id >= 0 {
+                        body.holding_entity_id =
+                            map.get_or_register_eid_mapping(server_id, body.holding_entity_id);
+                    }
+                    (vec![&mut body.attached_entity_id], vec![])
                 }
-                PacketLatest::PlayAttachEntity(body) => (
-                    if body.holding_entity_id < 0 {
-                        vec![&mut body.attached_entity_id]
-                    } else {
-                        vec![&mut body.attached_entity_id, &mut body.holding_entity_id]
-                    },
-                    vec![],
-                ),
                 PacketLatest::PlayCollectItem(body) => (
                     vec![],
                     vec![&mut body.collected_entity_id, &mut body.collector_entity_id],
                 ),
                 PacketLatest::PlaySetPassengers(body) => {
+                    // passengers may not have spawned yet, like the holder of a leash
                     // TODO: spelling error in mcproto
-                    (
-                        vec![],
-                        body.passenger_entitiy_ids.iter_mut().fold(
-                            vec![&mut body.entity_id],
-                            |mut acc, item| {
-                                acc.push(item);
-                                acc
-                            },
-                        ),
-                    )
+                    for passenger_id in body.passenger_entitiy_ids.iter_mut() {
+                        *passenger_id = map
+                            .get_or_register_eid_mapping(server_id, **passenger_id)
+                            .into();
+                    }
+                    (vec![], vec![&mut body.entity_id])
                 }
 
                 // entity spawning
@@ -167,7 +204,9 @@ pub fn map_eid(
                         id: *body.entity_id,
                         entity_type,
                     });
-                    body.entity_id = map.register_eid_mapping(server.id, *body.entity_id).into();
+                    body.entity_id = map
+                        .get_or_register_eid_mapping(server_id, *body.entity_id)
+                        .into();
                     // debug!("entity spawn type: {}", entity_type);
                     (
                         match entity_type {
@@ -180,10 +219,10 @@ pub fn map_eid(
                                 if body.data > 0 {
                                     // body.data is option varint. we need to specially handle this
                                     if let Some(mapped_id) =
-                                        map.eids.get_by_right(&(server.id, body.data - 1))
+                                        map.eids.get_by_right(&(server_id, body.data - 1))
                                     {
                                         body.data = mapped_id + 1;
-                                        smol::block_on(client.known_eids.lock()).insert(*mapped_id);
+                                        known_eids.insert(*mapped_id);
                                     } else {
                                         return SplinterMappingResult::None;
                                     }
@@ -203,8 +242,8 @@ pub fn map_eid(
                         id: *body.entity_id,
                         entity_type: 25,
                     });
-                    let new_eid = map.register_eid_mapping(server.id, *body.entity_id);
-                    smol::block_on(client.known_eids.lock()).insert(new_eid);
+                    let new_eid = map.get_or_register_eid_mapping(server_id, *body.entity_id);
+                    known_eids.insert(new_eid);
                     body.entity_id = new_eid.into();
                     (vec![], vec![])
                 }
@@ -213,8 +252,8 @@ pub fn map_eid(
                         id: *body.entity_id,
                         entity_type: *body.entity_type,
                     });
-                    let new_eid = map.register_eid_mapping(server.id, *body.entity_id);
-                    smol::block_on(client.known_eids.lock()).insert(new_eid);
+                    let new_eid = map.get_or_register_eid_mapping(server_id, *body.entity_id);
+                    known_eids.insert(new_eid);
                     body.entity_id = new_eid.into();
                     (vec![], vec![])
                 }
@@ -223,8 +262,8 @@ pub fn map_eid(
                         id: *body.entity_id,
                         entity_type: 60,
                     });
-                    let new_eid = map.register_eid_mapping(server.id, *body.entity_id);
-                    smol::block_on(client.known_eids.lock()).insert(new_eid);
+                    let new_eid = map.get_or_register_eid_mapping(server_id, *body.entity_id);
+                    known_eids.insert(new_eid);
                     body.entity_id = new_eid.into();
                     (vec![], vec![])
                 }
@@ -234,15 +273,15 @@ pub fn map_eid(
                         entity_type: 111,
                     });
                     let new_eid = if let Some(mapped_id) =
-                        map.eids.get_by_right(&(server.id, *body.entity_id))
+                        map.eids.get_by_right(&(server_id, *body.entity_id))
                     {
                         *mapped_id
                     } else {
-                        map.register_eid_mapping(server.id, *body.entity_id)
+                        map.register_eid_mapping(server_id, *body.entity_id)
                         // for whatever reason, server has two eids per player or something. im
                         // not sure. this fixes it though
                     };
-                    smol::block_on(client.known_eids.lock()).insert(new_eid);
+                    known_eids.insert(new_eid);
                     body.entity_id = new_eid.into();
                     (vec![], vec![])
                 }
@@ -250,13 +289,13 @@ pub fn map_eid(
                 PacketLatest::PlayEntityMetadata(body) => {
                     // we specially need to handle mapping here for the proxy side eid
                     let proxy_eid = if let Some(proxy_eid) =
-                        map.eids.get_by_right(&(server.id, *body.entity_id))
+                        map.eids.get_by_right(&(server_id, *body.entity_id))
                     {
                         *proxy_eid
                     } else {
                         return SplinterMappingResult::None;
                     };
-                    smol::block_on(client.known_eids.lock()).insert(proxy_eid);
+                    known_eids.insert(proxy_eid);
                     body.entity_id = proxy_eid.into();
                     if let Some(data) = map.entity_data.get(&proxy_eid) {
                         match data.entity_type {
@@ -268,10 +307,9 @@ pub fn map_eid(
                                     let found_id: i32 = **id;
                                     if found_id > 0 {
                                         if let Some(mapped_id) =
-                                            map.eids.get_by_right(&(server.id, found_id - 1))
+                                            map.eids.get_by_right(&(server_id, found_id - 1))
                                         {
-                                            smol::block_on(client.known_eids.lock())
-                                                .insert(*mapped_id);
+                                            known_eids.insert(*mapped_id);
                                             *id = (mapped_id + 1).into();
                                         } else {
                                             return SplinterMappingResult::None;
@@ -287,10 +325,9 @@ pub fn map_eid(
                                     let found_id: i32 = **id;
                                     if found_id > 0 {
                                         if let Some(mapped_id) =
-                                            map.eids.get_by_right(&(server.id, found_id - 1))
+                                            map.eids.get_by_right(&(server_id, found_id - 1))
                                         {
-                                            smol::block_on(client.known_eids.lock())
-                                                .insert(*mapped_id);
+                                            known_eids.insert(*mapped_id);
                                             *id = (mapped_id + 1).into();
                                         } else {
                                             return SplinterMappingResult::None;
@@ -307,10 +344,9 @@ pub fn map_eid(
                                         let found_id: i32 = **id;
                                         if found_id > 0 {
                                             if let Some(mapped_id) =
-                                                map.eids.get_by_right(&(server.id, found_id - 1))
+                                                map.eids.get_by_right(&(server_id, found_id - 1))
                                             {
-                                                smol::block_on(client.known_eids.lock())
-                                                    .insert(*mapped_id);
+                                                known_eids.insert(*mapped_id);
                                                 *id = (mapped_id + 1).into(); // docs dont say + 1, but Im assuming that is the case here
                                             } else {
                                                 return SplinterMappingResult::None;
@@ -327,10 +363,9 @@ pub fn map_eid(
                                     let found_id: i32 = **id;
                                     if found_id > 0 {
                                         if let Some(mapped_id) =
-                                            map.eids.get_by_right(&(server.id, found_id - 1))
+                                            map.eids.get_by_right(&(server_id, found_id - 1))
                                         {
-                                            smol::block_on(client.known_eids.lock())
-                                                .insert(*mapped_id);
+                                            known_eids.insert(*mapped_id);
                                             *id = (mapped_id + 1).into(); // docs dont say +1, same as above
                                         } else {
                                             return SplinterMappingResult::None;
@@ -344,28 +379,42 @@ pub fn map_eid(
                     (vec![], vec![])
                 }
                 PacketLatest::PlayDestroyEntities(ref mut body) => {
-                    for eid in body.entity_ids.iter_mut() {
-                        // since we're removing the id from the mapping table here, we have to map them here as well
-                        *eid = if let Some(mapped_id) = map.eids.get_by_right(&(server.id, **eid)) {
-                            smol::block_on(client.known_eids.lock()).remove(mapped_id);
-                            (*mapped_id).into()
-                        } else {
-                            return SplinterMappingResult::None;
-                        };
+                    // mapped here rather than below, since ids without a mapping are left out
+                    // instead of dropping the packet. Otherwise one id the client was never sent
+                    // would leave the rest on the client and in its known eids, keeping their
+                    // mappings from being cleaned up
+                    let mut mapped_ids = Vec::new();
+                    for eid in body.entity_ids.iter() {
+                        if let Some(mapped_id) = map.eids.get_by_right(&(server_id, **eid)) {
+                            known_eids.remove(mapped_id);
+                            mapped_ids.push(VarInt::from(*mapped_id));
+                        }
+                    }
+                    if mapped_ids.is_empty() {
+                        return SplinterMappingResult::None;
                     }
+                    body.entity_ids = mapped_ids.into();
                     (vec![], vec![])
                 }
-                _ => unreachable!(),
+                _ => {
+                    // has_eids and the arms above have drifted apart. Dropping the packet is
+                    // better than relaying server eids the client doesn't know
+                    error!(
+                        "Clientbound {:?} is marked as having eids, but has no eid mapping",
+                        packet.kind()
+                    );
+                    return SplinterMappingResult::None;
+                }
             };
             for id in nums {
-                *id = if let Some(mapped_id) = map.eids.get_by_right(&(server.id, *id)) {
+                *id = if let Some(mapped_id) = map.eids.get_by_right(&(server_id, *id)) {
                     *mapped_id
                 } else {
                     return SplinterMappingResult::None;
                 };
             }
             for id in varnums {
-                *id = if let Some(mapped_id) = map.eids.get_by_right(&(server.id, **id)) {
+                *id = if let Some(mapped_id) = map.eids.get_by_right(&(server_id, **id)) {
                     (*mapped_id).into()
                 } else {
                     return SplinterMappingResult::None;
@@ -373,7 +422,7 @@ pub fn map_eid(
             }
             if let Some(mut data) = entity_data {
                 let proxy_eid =
-                    if let Some(mapped_id) = map.eids.get_by_right(&(server.id, data.id)) {
+                    if let Some(mapped_id) = map.eids.get_by_right(&(server_id, data.id)) {
                         *mapped_id
                     } else {
                         return SplinterMappingResult::None;
@@ -384,12 +433,22 @@ pub fn map_eid(
             return SplinterMappingResult::Client;
         }
         PacketDirection::ServerBound => {
+            // vehicle controls (vehicle move, steer vehicle, steer boat) only refer to the vehicle
+            // the player is riding implicitly, so they have no eids to map and are relayed as
+            // they are. When switching servers, the client is put on the vehicle the player rides
+            // on the new server, if any, so the controls keep steering it there
             let eid = match packet {
                 PacketLatest::PlayQueryEntityNbt(body) => &mut body.entity_id,
                 PacketLatest::PlayInteractEntity(body) => &mut body.entity_id,
                 PacketLatest::PlayEntityAction(body) => &mut body.entity_id,
                 PacketLatest::PlayUpdateCommandBlockMinecart(body) => &mut body.entity_id,
-                _ => unreachable!(),
+                _ => {
+                    error!(
+                        "Serverbound {:?} is marked as having eids, but has no eid mapping",
+                        packet.kind()
+                    );
+                    return SplinterMappingResult::None;
+                }
             };
             if let Some((server_id, server_eid)) = map.eids.get_by_left(&**eid) {
                 *eid = (*server_eid).into();
@@ -399,3 +458,235 @@ pub fn map_eid(
     };
     return SplinterMappingResult::None;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::current::{
+        proto::{
+            FacePlayerEntityTarget,
+            FacePlayerKind,
+            PlayAttachEntitySpec,
+            PlayDestroyEntitiesSpec,
+            PlayEntityEffectSpec,
+            PlayFacePlayerSpec,
+            PlaySpawnExperienceOrbSpec,
+        },
+        types::Vec3,
+    };
+
+    fn map_clientbound(
+        map: &mut SplinterMapping,
+        known_eids: &mut HashSet<i32>,
+        packet: &mut PacketLatest,
+        server_id: u64,
+    ) -> SplinterMappingResult {
+        map_eid(
+            known_eids,
+            map,
+            packet,
+            &PacketDirection::ClientBound,
+            server_id,
+        )
+    }
+
+    fn spawn_packet(entity_id: i32) -> PacketLatest {
+        PacketLatest::PlaySpawnExperienceOrb(PlaySpawnExperienceOrbSpec {
+            entity_id: entity_id.into(),
+            position: Vec3 {
+                x: 0.,
+                y: 64.,
+                z: 0.,
+            },
+            count: 1,
+        })
+    }
+
+    fn destroy_packet(entity_ids: &[i32]) -> PacketLatest {
+        PacketLatest::PlayDestroyEntities(PlayDestroyEntitiesSpec {
+            entity_ids: entity_ids
+                .iter()
+                .map(|eid| VarInt::from(*eid))
+                .collect::<Vec<_>>()
+                .into(),
+        })
+    }
+
+    fn effect_packet(entity_id: i32) -> PacketLatest {
+        PacketLatest::PlayEntityEffect(PlayEntityEffectSpec {
+            entity_id: entity_id.into(),
+            effect_id: 1,
+            amplifier: 0,
+            duration_ticks: 200.into(),
+            flags: Default::default(),
+        })
+    }
+
+    fn effect_eid(packet: &PacketLatest) -> i32 {
+        match packet {
+            PacketLatest::PlayEntityEffect(body) => *body.entity_id,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn self_effect_follows_swap() {
+        let mut map = SplinterMapping::new();
+        let mut known_eids = HashSet::new();
+        // the player is entity 10 on server 1, which it joined through
+        let proxy_eid = map.register_eid_mapping(1, 10);
+        let mut packet = effect_packet(10);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        assert_eq!(effect_eid(&packet), proxy_eid);
+
+        // swap to server 2, where the player is entity 20
+        map.remap_eid(proxy_eid, 2, 20);
+        let mut packet = effect_packet(20);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 2),
+            SplinterMappingResult::Client
+        );
+        assert_eq!(effect_eid(&packet), proxy_eid);
+
+        // the previous server's player entity no longer reaches the client
+        let mut packet = effect_packet(10);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::None
+        );
+    }
+
+    #[test]
+    fn destroy_forgets_spawned_eids() {
+        let mut map = SplinterMapping::new();
+        let mut known_eids = HashSet::new();
+        for eid in [10, 11] {
+            assert_eq!(
+                map_clientbound(&mut map, &mut known_eids, &mut spawn_packet(eid), 1),
+                SplinterMappingResult::Client
+            );
+        }
+        assert_eq!(known_eids.len(), 2);
+
+        // 12 was never spawned, and must not keep the others from being destroyed
+        let mut packet = destroy_packet(&[10, 12, 11]);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        assert!(known_eids.is_empty());
+        match packet {
+            PacketLatest::PlayDestroyEntities(body) => {
+                let destroyed = body.entity_ids.iter().map(|eid| **eid).collect::<Vec<_>>();
+                let expected = [10, 11]
+                    .iter()
+                    .map(|eid| *map.eids.get_by_right(&(1, *eid)).unwrap())
+                    .collect::<Vec<_>>();
+                assert_eq!(destroyed, expected);
+            }
+            _ => unreachable!(),
+        }
+
+        // nothing is left to destroy when none of the ids are mapped
+        let mut packet = destroy_packet(&[12]);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::None
+        );
+    }
+
+    fn face_packet(target_eid: i32) -> PacketLatest {
+        PacketLatest::PlayFacePlayer(PlayFacePlayerSpec {
+            face_kind: FacePlayerKind::Eyes,
+            target: Vec3 {
+                x: 4.,
+                y: 65.,
+                z: 4.,
+            },
+            entity: Some(FacePlayerEntityTarget {
+                entity_id: target_eid.into(),
+                kind: FacePlayerKind::Eyes,
+            }),
+        })
+    }
+
+    fn face_target_eid(packet: &PacketLatest) -> Option<i32> {
+        match packet {
+            PacketLatest::PlayFacePlayer(body) => {
+                body.entity.as_ref().map(|target| *target.entity_id)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn face_unmapped_target_falls_back_to_position() {
+        let mut map = SplinterMapping::new();
+        let mut known_eids = HashSet::new();
+        let proxy_eid = map.register_eid_mapping(1, 10);
+        let mut packet = face_packet(10);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        assert_eq!(face_target_eid(&packet), Some(proxy_eid));
+
+        // the packet is still relayed, facing the position it has instead of the entity
+        let mut packet = face_packet(11);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        assert_eq!(face_target_eid(&packet), None);
+    }
+
+    #[test]
+    fn attach_before_holder_spawns() {
+        let mut map = SplinterMapping::new();
+        let mut known_eids = HashSet::new();
+        let attached_eid = map.register_eid_mapping(1, 10);
+        // the holder, entity 11, hasn't been spawned yet
+        let mut packet = PacketLatest::PlayAttachEntity(PlayAttachEntitySpec {
+            attached_entity_id: 10,
+            holding_entity_id: 11,
+        });
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        let holder_eid = match packet {
+            PacketLatest::PlayAttachEntity(body) => {
+                assert_eq!(body.attached_entity_id, attached_eid);
+                body.holding_entity_id
+            }
+            _ => unreachable!(),
+        };
+        assert_ne!(holder_eid, attached_eid);
+
+        // the holder spawning afterwards gets the eid the leash is already attached to
+        let mut packet = spawn_packet(11);
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        match packet {
+            PacketLatest::PlaySpawnExperienceOrb(body) => assert_eq!(*body.entity_id, holder_eid),
+            _ => unreachable!(),
+        }
+        assert_eq!(map.eid_collisions, 0);
+
+        // detaching doesn't map the holder
+        let mut packet = PacketLatest::PlayAttachEntity(PlayAttachEntitySpec {
+            attached_entity_id: 10,
+            holding_entity_id: -1,
+        });
+        assert_eq!(
+            map_clientbound(&mut map, &mut known_eids, &mut packet, 1),
+            SplinterMappingResult::Client
+        );
+        assert_eq!(map.outstanding_eids(), 2);
+    }
+}
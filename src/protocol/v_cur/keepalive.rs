@@ -12,32 +12,35 @@ use crate::{
         },
         v_cur,
     },
-    systems::keepalive::unix_time_millis,
+    systems::keepalive::SystemClock,
 };
 
 inventory::submit! {
-    v_cur::RelayPass(Box::new(|_proxy, _connection, client, direction, lazy_packet, destination| {
-        match direction {
-            PacketDirection::ServerBound => {
-                if lazy_packet.kind() == PacketLatestKind::PlayClientKeepAlive { // TODO: may want to do something with the keep alive IDs
-                    *smol::block_on(client.last_keep_alive.lock()) = unix_time_millis();
-                    *destination = v_cur::PacketDestination::None;
+    v_cur::RelayPass {
+        priority: v_cur::PRIORITY_CONSUME,
+        pass: Box::new(|_proxy, _connection, client, direction, lazy_packet, destination| {
+            match direction {
+                PacketDirection::ServerBound => {
+                    if lazy_packet.kind() == PacketLatestKind::PlayClientKeepAlive { // TODO: may want to do something with the keep alive IDs
+                        smol::block_on(client.record_keep_alive(&SystemClock));
+                        *destination = v_cur::PacketDestination::None;
+                    }
                 }
-            }
-            PacketDirection::ClientBound => {
-                if lazy_packet.kind() == PacketLatestKind::PlayServerKeepAlive {
-                    if let Ok(PacketLatest::PlayServerKeepAlive(body)) = lazy_packet.packet() {
-                        // respond to server
+                PacketDirection::ClientBound => {
+                    if lazy_packet.kind() == PacketLatestKind::PlayServerKeepAlive {
+                        if let Ok(PacketLatest::PlayServerKeepAlive(body)) = lazy_packet.packet() {
+                            // respond to server
 
-                        let server_conn = client.active_server.load();
-                        if let Err(e) = smol::block_on(async { server_conn.writer.lock().await.write_packet_async(PacketLatest::PlayClientKeepAlive(PlayClientKeepAliveSpec {
-                            id: body.id,
-                        })).await }) {
-                            error!("Failed to send keep alive from \"{}\" to server id {}: {}", &client.name, server_conn.server.id, e);
+                            let server_conn = client.active_server.load();
+                            if let Err(e) = smol::block_on(async { server_conn.writer.lock().await.write_packet_async(PacketLatest::PlayClientKeepAlive(PlayClientKeepAliveSpec {
+                                id: body.id,
+                            })).await }) {
+                                error!("Failed to send keep alive from \"{}\" to server id {}: {}", &client.name, server_conn.server.id, e);
+                            }
                         }
                     }
                 }
             }
-        }
-    }))
+        }),
+    }
 }
@@ -1,7 +1,6 @@
 use std::{
     collections::HashSet,
     str,
-    sync::Arc,
 };
 
 use anyhow::Context;
@@ -21,6 +20,8 @@ use crate::{
                 ClientStatusAction,
                 HandshakeNextState,
                 HandshakeSpec,
+                LoginDisconnectSpec,
+                LoginPluginResponseSpec,
                 LoginSetCompressionSpec,
                 LoginStartSpec,
                 LoginSuccessSpec,
@@ -29,16 +30,19 @@ use crate::{
                 PlayClientSettingsSpec,
                 PlayClientStatusSpec,
                 PlayServerPluginMessageSpec,
-                PlayTagsSpec,
                 PlayTeleportConfirmSpec,
             },
             protocol::PacketDirection,
-            types::VarInt,
+            types::{
+                Chat,
+                VarInt,
+            },
             uuid::UUID4,
             PacketLatest,
             RawPacketLatest,
         },
         plugin,
+        v_cur::IdNameMaps,
         AsyncCraftWriter,
         ClientBuilder,
         Tags,
@@ -50,8 +54,8 @@ use crate::{
             MainHand,
             SkinPart,
         },
+        config::SplinterConfig,
         server::SplinterServerConnection,
-        SplinterProxy,
     },
 };
 
@@ -78,13 +82,70 @@ pub async fn handle_client_login_packet(
         }
     };
     if let Some(packet) = packet {
+        if *next_sender == PacketDirection::ClientBound {
+            if let Some(server_conn) = builder.server_conn.as_ref() {
+                server_conn
+                    .track_resync(&builder.proxy.resync_kinds, &packet)
+                    .await;
+            }
+        }
         match packet {
             PacketLatest::LoginStart(body) => {
                 builder.login_start(&body.name).await?;
                 *next_sender = PacketDirection::ClientBound;
             }
             PacketLatest::LoginSetCompression(body) => {
-                builder.login_set_compression(*body.threshold);
+                builder.login_set_compression(*body.threshold)?;
+                *next_sender = PacketDirection::ClientBound;
+            }
+            PacketLatest::LoginPluginRequest(body) => {
+                if builder.proxy.config.forward_login_plugin_requests {
+                    debug!(
+                        "Forwarding login plugin request on channel \"{}\" to {}",
+                        body.channel, builder.client_addr
+                    );
+                    builder
+                        .client_writer
+                        .write_packet_async(PacketLatest::LoginPluginRequest(body))
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "Failed to forward login plugin request to {}",
+                                builder.client_addr
+                            )
+                        })?;
+                    // the client's response comes next
+                    *next_sender = PacketDirection::ServerBound;
+                } else {
+                    debug!(
+                        "Answering login plugin request on channel \"{}\" for {} as not \
+                         understood",
+                        body.channel, builder.client_addr
+                    );
+                    send_login_plugin_response(
+                        builder.server_conn.as_mut().unwrap(),
+                        body.message_id,
+                        None,
+                    )
+                    .await?;
+                    *next_sender = PacketDirection::ClientBound;
+                }
+            }
+            PacketLatest::LoginPluginResponse(body) => {
+                builder
+                    .server_conn
+                    .as_mut()
+                    .unwrap()
+                    .writer
+                    .get_mut()
+                    .write_packet_async(PacketLatest::LoginPluginResponse(body))
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to forward login plugin response from {}",
+                            builder.client_addr
+                        )
+                    })?;
                 *next_sender = PacketDirection::ClientBound;
             }
             PacketLatest::LoginSuccess(body) => {
@@ -105,6 +166,7 @@ pub async fn handle_client_login_packet(
             }
             PacketLatest::PlayJoinGame(mut body) => {
                 builder.server_conn.as_mut().unwrap().eid = body.entity_id;
+                builder.dimension = Some(body.world_name.clone());
                 let map = &mut *builder.proxy.mapping.lock().await;
                 let server_id = builder.server_conn.as_ref().unwrap().server.id;
                 body.entity_id = if let Some(existing_id) =
@@ -114,6 +176,7 @@ pub async fn handle_client_login_packet(
                 } else {
                     map.register_eid_mapping(server_id, body.entity_id)
                 };
+                builder.proxy_eid = Some(body.entity_id);
                 debug!(
                     "player eid ({}, {}) -> {}",
                     server_id,
@@ -133,8 +196,15 @@ pub async fn handle_client_login_packet(
                 builder.play_join_game().await?;
                 *next_sender = PacketDirection::ServerBound;
             }
-            PacketLatest::PlayClientPluginMessage(_body) => {
-                //..
+            PacketLatest::PlayClientPluginMessage(body) => {
+                if body.channel == "minecraft:brand" {
+                    builder.brand = plugin::read_string(&body.data.data);
+                    debug!(
+                        "\"{}\" has client brand {:?}",
+                        builder.name.as_ref().unwrap(),
+                        builder.brand
+                    );
+                }
                 *next_sender = PacketDirection::ServerBound;
             }
             PacketLatest::PlayServerPluginMessage(body) => {
@@ -173,7 +243,8 @@ pub async fn handle_client_login_packet(
                 *next_sender = PacketDirection::ClientBound;
             }
             PacketLatest::PlayTags(body) => {
-                let tags = Tags::from(&body);
+                let maps = builder.proxy.id_maps_for(builder.client_protocol).await?;
+                let tags = Tags::from_proto(&body, &maps);
                 builder.play_tags(tags).await?;
                 return Ok(Some(true));
             }
@@ -191,15 +262,12 @@ pub async fn handle_client_login_packet(
     }
 }
 
-pub async fn send_handshake(
-    server_conn: &mut SplinterServerConnection,
-    proxy: &Arc<SplinterProxy>,
-) -> anyhow::Result<()> {
+pub async fn send_handshake(server_conn: &mut SplinterServerConnection) -> anyhow::Result<()> {
     server_conn
         .writer
         .get_mut()
         .write_packet_async(PacketLatest::Handshake(HandshakeSpec {
-            version: proxy.config.protocol.into(),
+            version: server_conn.server.protocol.into(),
             server_address: format!("{}", server_conn.server.address.ip()),
             server_port: server_conn.server.address.port(),
             next_state: HandshakeNextState::Login,
@@ -207,6 +275,17 @@ pub async fn send_handshake(
         .await
         .map_err(|e| e.into())
 }
+pub async fn send_login_disconnect(
+    writer: &mut AsyncCraftWriter,
+    message: impl AsRef<str>,
+) -> anyhow::Result<()> {
+    writer
+        .write_packet_async(PacketLatest::LoginDisconnect(LoginDisconnectSpec {
+            message: Chat::from_text(message.as_ref()),
+        }))
+        .await
+        .map_err(|e| e.into())
+}
 pub async fn send_login_start(
     server_conn: &mut SplinterServerConnection,
     name: impl ToString,
@@ -220,6 +299,24 @@ pub async fn send_login_start(
         .await
         .map_err(|e| e.into())
 }
+/// Answers a login plugin request from a server. Sending no data answers that the request wasn't
+/// understood
+pub async fn send_login_plugin_response(
+    server_conn: &mut SplinterServerConnection,
+    message_id: VarInt,
+    data: Option<Vec<u8>>,
+) -> anyhow::Result<()> {
+    server_conn
+        .writer
+        .get_mut()
+        .write_packet_async(PacketLatest::LoginPluginResponse(LoginPluginResponseSpec {
+            message_id,
+            successful: data.is_some(),
+            data: data.unwrap_or_default().into(),
+        }))
+        .await
+        .map_err(|e| e.into())
+}
 pub async fn send_set_compression(
     writer: &mut AsyncCraftWriter,
     threshold: i32,
@@ -244,19 +341,38 @@ pub async fn send_login_success(
         .await
         .map_err(|e| e.into())
 }
+/// Brands longer than this are cut short, so that the length fits in a single byte
+const MAX_BRAND_SIZE: usize = 127;
+
+/// Gets the brand the proxy shows a client on a server, which is the configured brand followed by
+/// `|shard=<server id>` if the server is included. The configured brand is cut short if needed so
+/// that the whole brand is within the size limit
+pub fn proxy_brand(config: &SplinterConfig, server_id: u64) -> String {
+    let suffix = if config.brand_includes_server {
+        format!("|shard={}", server_id)
+    } else {
+        String::new()
+    };
+    let mut end = config.brand.len().min(MAX_BRAND_SIZE - suffix.len());
+    while !config.brand.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &config.brand[..end], suffix)
+}
+pub fn brand_packet(brand: impl AsRef<str>) -> PacketLatest {
+    PacketLatest::PlayServerPluginMessage(PlayServerPluginMessageSpec {
+        channel: "minecraft:brand".into(),
+        data: [&[brand.as_ref().len() as u8], brand.as_ref().as_bytes()]
+            .concat()
+            .into(),
+    })
+}
 pub async fn send_brand(
     writer: &mut AsyncCraftWriter,
     brand: impl AsRef<str>,
 ) -> anyhow::Result<()> {
     writer
-        .write_packet_async(PacketLatest::PlayServerPluginMessage(
-            PlayServerPluginMessageSpec {
-                channel: "minecraft:brand".into(),
-                data: [&[brand.as_ref().len() as u8], brand.as_ref().as_bytes()]
-                    .concat()
-                    .into(),
-            },
-        ))
+        .write_packet_async(brand_packet(brand))
         .await
         .map_err(|e| e.into())
 }
@@ -271,9 +387,13 @@ pub async fn send_client_settings(
         .await
         .map_err(|e| e.into())
 }
-pub async fn send_tags(writer: &mut AsyncCraftWriter, tags: &Tags) -> anyhow::Result<()> {
+pub async fn send_tags(
+    writer: &mut AsyncCraftWriter,
+    tags: &Tags,
+    maps: &IdNameMaps,
+) -> anyhow::Result<()> {
     writer
-        .write_packet_async(PacketLatest::PlayTags(PlayTagsSpec::from(tags)))
+        .write_packet_async(PacketLatest::PlayTags(tags.to_proto(maps)))
         .await
         .map_err(|e| e.into())
 }
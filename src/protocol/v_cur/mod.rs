@@ -1,47 +1,118 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use anyhow::Context;
+use async_dup::Arc as AsyncArc;
 use craftio_rs::{CraftAsyncReader, CraftAsyncWriter, CraftIo};
+use futures_lite::future;
+use smol::{
+    channel::{Receiver, TrySendError},
+    Async, Timer,
+};
 
 use super::{AsyncCraftConnection, AsyncCraftReader, AsyncCraftWriter, PacketDestination};
 use crate::{
     protocol::{
         current::{
             proto::{
+                HandshakeNextState, HandshakeSpec, LoginDisconnectSpec,
                 Packet756 as PacketLatest, PlayDisconnectSpec, PlayServerKeepAliveSpec,
                 RawPacket756 as RawPacketLatest, StatusPongSpec, StatusRequestSpec,
                 StatusResponseSpec,
             },
             protocol::{PacketDirection, State},
+            status::StatusSpec,
             types::Chat,
+            PacketLatestKind,
         },
-        events::LazyDeserializedPacket,
+        describe_version,
+        events::{LazyDeserializedPacket, OwnedPacket},
+        supported_versions_string,
     },
     proxy::{
         client::SplinterClient,
+        error::SplinterError,
         server::{SplinterServer, SplinterServerConnection},
         ClientKickReason, SplinterProxy,
     },
 };
 
+mod batching;
+mod bossbar;
 mod chat;
 mod chunk;
+mod digging;
 mod eid;
 mod keepalive;
 mod login;
+mod mount;
+mod ping;
+mod playerinfo;
+mod resourcepack;
+mod respawn;
+mod resync;
+mod scoreboard;
 mod sync;
 mod tags;
+mod weather;
+pub use batching::parse_latency_sensitive_kinds;
+pub use bossbar::{parse_boss_bar_color, ProxyBossBar};
 pub use chat::*;
 pub use eid::*;
 pub use login::*;
+pub use mount::is_mount_packet;
+pub use resync::parse_resync_kinds;
 pub use sync::*;
 pub use tags::*;
 
+/// Gets packet kinds from their names in the config, looking them up in a table of the packets
+/// that are allowed. `what` describes what is done with them, for the error about a name that
+/// isn't in the table
+fn parse_packet_kinds(
+    names: &[String],
+    table: &[(&str, PacketLatestKind)],
+    what: &str,
+) -> anyhow::Result<Vec<PacketLatestKind>> {
+    let mut kinds = vec![];
+    for name in names.iter() {
+        let kind = table
+            .iter()
+            .find(|(allowed, _)| allowed == name)
+            .map(|(_, kind)| *kind)
+            .ok_or_else(|| {
+                anyhow!(
+                    "\"{}\" can't be {}. Packets that can be are: {}",
+                    name,
+                    what,
+                    table
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    Ok(kinds)
+}
+
 pub async fn handle_client_status(
     mut conn: AsyncCraftConnection,
     addr: SocketAddr,
     proxy: Arc<SplinterProxy>,
 ) -> anyhow::Result<()> {
+    if !proxy.allow_status_request(addr.ip()) {
+        debug!("Dropping status request from {} over the rate limit", addr);
+        return Ok(());
+    }
     conn.set_state(State::Status);
     conn.write_packet_async(PacketLatest::StatusResponse(StatusResponseSpec {
         response: proxy.config.server_status(&*proxy),
@@ -68,6 +139,52 @@ pub async fn handle_client_status(
     Ok(())
 }
 
+/// Asks a server for its status, as a client would for the server list
+pub async fn fetch_status(server: &SplinterServer) -> anyhow::Result<StatusSpec> {
+    let mut conn = server.connect().await?;
+    conn.write_packet_async(PacketLatest::Handshake(HandshakeSpec {
+        version: server.protocol.into(),
+        server_address: format!("{}", server.address.ip()),
+        server_port: server.address.port(),
+        next_state: HandshakeNextState::Status,
+    }))
+    .await?;
+    conn.set_state(State::Status);
+    conn.write_packet_async(PacketLatest::StatusRequest(StatusRequestSpec))
+        .await?;
+    match conn.read_packet_async::<RawPacketLatest>().await? {
+        Some(PacketLatest::StatusResponse(body)) => Ok(body.response),
+        Some(other) => bail!("Unexpected packet {:?} from server {}", other, server.id),
+        None => bail!("Server {} closed the connection", server.id),
+    }
+}
+
+/// Disconnects a client attempting to log in with a protocol version the proxy does not support,
+/// telling them which versions are supported
+pub async fn handle_unsupported_version(
+    mut conn: AsyncCraftConnection,
+    addr: SocketAddr,
+    proxy: Arc<SplinterProxy>,
+    version: i32,
+) -> anyhow::Result<()> {
+    info!(
+        "Rejecting login from {} with unsupported version {}",
+        addr,
+        describe_version(version)
+    );
+    conn.set_state(State::Login);
+    conn.write_packet_async(PacketLatest::LoginDisconnect(LoginDisconnectSpec {
+        message: Chat::from_text(&format!(
+            "{}. This proxy supports {}; you are on {}.",
+            proxy.config.improper_version_disconnect_message,
+            supported_versions_string(),
+            describe_version(version),
+        )),
+    }))
+    .await?;
+    Ok(())
+}
+
 type RelayPassFn = Box<
     dyn Send
         + Sync
@@ -80,10 +197,101 @@ type RelayPassFn = Box<
             &mut PacketDestination,
         ),
 >;
-pub struct RelayPass(pub RelayPassFn);
+/// A pass every relayed packet goes through, which can inspect or modify the packet and where it
+/// is sent
+///
+/// Passes run in ascending order of priority, and passes with the same priority run in no
+/// particular order. Use one of the `PRIORITY_` constants unless a pass needs to run before or
+/// after a specific other pass:
+/// - `PRIORITY_OBSERVE` passes only track state from packets and don't change them
+/// - `PRIORITY_TRANSLATE` passes rewrite packet contents, such as mapping entity ids, and may
+///   route the packet based on what they translated
+/// - `PRIORITY_CONSUME` passes handle packets entirely within the proxy, so they run last to make
+///   sure nothing routes the packet anywhere after they set the destination to
+///   `PacketDestination::None`
+pub struct RelayPass {
+    pub priority: i32,
+    pub pass: RelayPassFn,
+}
+
+pub const PRIORITY_OBSERVE: i32 = -100;
+pub const PRIORITY_TRANSLATE: i32 = 0;
+pub const PRIORITY_CONSUME: i32 = 100;
 
 inventory::collect!(RelayPass);
 
+lazy_static! {
+    /// All registered relay passes, sorted by priority
+    static ref RELAY_PASSES: Vec<&'static RelayPass> = {
+        let mut passes = inventory::iter::<RelayPass>.into_iter().collect::<Vec<_>>();
+        passes.sort_by_key(|pass| pass.priority);
+        passes
+    };
+}
+
+/// The most times relay passes can route a packet back the way it came before it is dropped.
+/// Legitimate flows never route a packet back, so this only guards against misbehaving passes
+/// fighting over where a packet goes
+const MAX_RELAY_HOPS: usize = 2;
+
+/// Whether a destination sends a packet back the way it came, such as a packet from a server to
+/// the servers
+fn routes_back(sender: &PacketDirection, destination: PacketDestination) -> bool {
+    match sender {
+        PacketDirection::ClientBound => matches!(
+            destination,
+            PacketDestination::Server(_) | PacketDestination::AllServers
+        ),
+        PacketDirection::ServerBound => destination == PacketDestination::Client,
+    }
+}
+
+/// Runs a packet through all relay passes in order of priority
+///
+/// Each pass that routes the packet back the way it came counts as a hop, and if there are more
+/// than `MAX_RELAY_HOPS` hops the packet is dropped, logging the passes that routed it.
+fn run_relay_passes(
+    proxy: &Arc<SplinterProxy>,
+    client: &Arc<SplinterClient>,
+    sender: &PacketDirection,
+    lazy_packet: &mut LazyDeserializedPacket,
+    destination: &mut PacketDestination,
+) {
+    let connection = client.active_server.load();
+    let mut hops = 0;
+    // the passes that changed the destination, as (index, priority, new destination)
+    let mut chain = Vec::new();
+    for (index, pass) in RELAY_PASSES.iter().enumerate() {
+        let previous_destination = *destination;
+        (pass.pass)(proxy, &*connection, client, sender, lazy_packet, destination);
+        if *destination != previous_destination {
+            chain.push((index, pass.priority, *destination));
+            if routes_back(sender, *destination) {
+                hops += 1;
+            }
+        }
+    }
+    if hops > MAX_RELAY_HOPS {
+        warn!(
+            "Dropping {:?} packet of kind {:?} for \"{}\" after it was routed back {} times. \
+             Relay passes that routed it: {}",
+            sender,
+            lazy_packet.kind(),
+            &client.name,
+            hops,
+            chain
+                .iter()
+                .map(|(index, priority, destination)| format!(
+                    "pass {} (priority {}) to {:?}",
+                    index, priority, destination
+                ))
+                .collect::<Vec<_>>()
+                .join(", then ")
+        );
+        *destination = PacketDestination::None;
+    }
+}
+
 pub async fn handle_server_packet(
     proxy: &Arc<SplinterProxy>,
     client: &Arc<SplinterClient>,
@@ -98,17 +306,14 @@ pub async fn handle_server_packet(
         .with_context(|| format!("Failed to read packet {}: ", server.id))?;
     match packet_opt {
         Some(raw_packet) => {
+            client.capture_packet(sender, server.id, &raw_packet).await;
             let mut lazy_packet = LazyDeserializedPacket::from_raw_packet(raw_packet);
             let mut destination = PacketDestination::Client;
-            for pass in inventory::iter::<RelayPass> {
-                (pass.0)(
-                    proxy,
-                    &*client.active_server.load(),
-                    client,
-                    sender,
-                    &mut lazy_packet,
-                    &mut destination,
-                );
+            run_relay_passes(proxy, client, sender, &mut lazy_packet, &mut destination);
+            if client.is_reconnecting() {
+                // the connection is being replaced, so what it sends, such as being kicked for
+                // the replacement logging in, is stale
+                destination = PacketDestination::None;
             }
             let kind = lazy_packet.kind();
             // debug!("got packet of type {:?}", kind);
@@ -138,29 +343,75 @@ pub async fn handle_client_packet(
         .with_context(|| format!("Failed to read packet from {}", client.name))?;
     match packet_opt {
         Some(raw_packet) => {
+            client
+                .capture_packet(sender, client.server_id(), &raw_packet)
+                .await;
             let mut lazy_packet = LazyDeserializedPacket::from_raw_packet(raw_packet);
             let mut destination = PacketDestination::AllServers;
-            for pass in inventory::iter::<RelayPass> {
-                (pass.0)(
-                    proxy,
-                    &*client.active_server.load(),
-                    client,
-                    sender,
-                    &mut lazy_packet,
-                    &mut destination,
-                );
+            run_relay_passes(proxy, client, sender, &mut lazy_packet, &mut destination);
+            if lazy_packet.failed_to_deserialize() {
+                lazy_packet =
+                    handle_undeserializable_packet(proxy, client, lazy_packet, &mut destination);
+            }
+            if client.is_reconnecting() && destination != PacketDestination::Client {
+                // there's no active server to send to, and the packets will be stale by the time
+                // it's back
+                destination = PacketDestination::None;
+            }
+            if let Err(e) = send_packet(client, &destination, lazy_packet).await {
+                match e.downcast_ref::<SplinterError>() {
+                    // the relay for that connection takes care of it closing
+                    Some(SplinterError::ConnectionClosed(server_id)) => debug!(
+                        "dropping packet from {} to closed connection to server {}",
+                        &client.name, server_id
+                    ),
+                    _ => {
+                        return Err(e.context(format!(
+                            "Sending packet from client \"{}\" failure",
+                            &client.name
+                        )))
+                    }
+                }
             }
-            send_packet(client, &destination, lazy_packet)
-                .await
-                .with_context(|| {
-                    format!("Sending packet from client \"{}\" failure", &client.name)
-                })?;
             Ok(Some(()))
         }
         None => Ok(None),
     }
 }
 
+/// Whether a client packet that couldn't be deserialized has been logged yet
+static LOGGED_UNDESERIALIZABLE: AtomicBool = AtomicBool::new(false);
+
+/// Decides what to do with a packet from a client that failed to deserialize, such as one from a
+/// newer client version, so that it doesn't error out the client's relay. Unless passthrough is
+/// enabled in the config, the packet is dropped
+fn handle_undeserializable_packet<'a>(
+    proxy: &Arc<SplinterProxy>,
+    client: &Arc<SplinterClient>,
+    lazy_packet: LazyDeserializedPacket<'a>,
+    destination: &mut PacketDestination,
+) -> LazyDeserializedPacket<'a> {
+    let passthrough = proxy.config.passthrough_undeserializable_packets;
+    if !LOGGED_UNDESERIALIZABLE.swap(true, Ordering::Relaxed) {
+        warn!(
+            "Failed to deserialize packet of kind {:?} from \"{}\", {} it. Further packets that \
+             fail to deserialize will not be logged",
+            lazy_packet.kind(),
+            &client.name,
+            if passthrough { "relaying" } else { "dropping" }
+        );
+    }
+    if !passthrough {
+        *destination = PacketDestination::None;
+    }
+    // the raw packet is relayed rather than the failed deserialization
+    LazyDeserializedPacket::from_raw_packet(lazy_packet.into_original_raw_packet().unwrap())
+}
+
+/// Sends a packet to its destination
+///
+/// Sending to a server connection that has closed fails with `SplinterError::ConnectionClosed`,
+/// except that closed dummy connections are skipped when sending to all servers.
 pub async fn send_packet<'a>(
     client: &Arc<SplinterClient>,
     destination: &PacketDestination,
@@ -168,7 +419,8 @@ pub async fn send_packet<'a>(
 ) -> anyhow::Result<()> {
     match destination {
         PacketDestination::Client => {
-            write_packet(&mut *client.writer.lock().await, lazy_packet)
+            client
+                .write_packet(lazy_packet)
                 .await
                 .with_context(|| {
                     format!("Failed to write packet to client \"{}\"", &client.name,)
@@ -177,23 +429,30 @@ pub async fn send_packet<'a>(
         PacketDestination::Server(server_id) => {
             let active_server = client.active_server.load();
             let dummy_servers = client.dummy_servers.load();
-            let writer = &mut *(if active_server.server.id == *server_id {
-                active_server.writer.lock().await
+            let server_conn = if active_server.server.id == *server_id {
+                &*active_server
             } else {
                 if let Some((_id, server_conn)) =
                     dummy_servers.iter().find(|(id, _)| *id == *server_id)
                 {
-                    server_conn.writer.lock().await
+                    &**server_conn
                 } else {
                     bail!("No connected server from mapped server id");
                 }
-            });
+            };
+            if !server_conn.alive.load(Ordering::Relaxed) {
+                return Err(SplinterError::ConnectionClosed(*server_id).into());
+            }
+            let writer = &mut *server_conn.writer.lock().await;
             write_packet(writer, lazy_packet)
                 .await
                 .with_context(|| format!("Failed to write packet to server \"{}\"", server_id))?;
         }
         PacketDestination::AllServers => {
             for (server_id, server_conn) in client.dummy_servers.load().iter() {
+                if !server_conn.alive.load(Ordering::Relaxed) {
+                    continue;
+                }
                 let writer = &mut *server_conn.writer.lock().await;
                 write_packet(writer, lazy_packet.clone())
                     .await
@@ -202,6 +461,9 @@ pub async fn send_packet<'a>(
                     })?;
             }
             let active_server = client.active_server.load();
+            if !active_server.alive.load(Ordering::Relaxed) {
+                return Err(SplinterError::ConnectionClosed(active_server.server.id).into());
+            }
             let writer = &mut *active_server.writer.lock().await;
 
             write_packet(writer, lazy_packet).await.with_context(|| {
@@ -232,16 +494,107 @@ async fn write_packet(
     Ok(())
 }
 
+/// Writes the packets queued for a client until the client is dropped or a write fails
+///
+/// If `batch_client_packets` is enabled, Nagle's algorithm is left on while more packets are
+/// waiting, so that bursts like chunks being loaded go out in fewer, fuller segments. It's turned
+/// off for the last packet waiting and for latency sensitive packets, which sends them along with
+/// anything held back before them right away. Nothing is buffered in the proxy; it's only Nagle's
+/// algorithm being toggled, at the cost of a setsockopt call each time it's turned on or off.
+pub async fn write_queued_packets(
+    proxy: Arc<SplinterProxy>,
+    name: String,
+    mut writer: AsyncCraftWriter,
+    stream: AsyncArc<Async<TcpStream>>,
+    queue: Receiver<OwnedPacket>,
+) {
+    let mut nodelay = proxy.config.tcp_nodelay;
+    while let Ok(packet) = queue.recv().await {
+        let data;
+        let lazy_packet = match packet {
+            OwnedPacket::Deserialized(packet) => LazyDeserializedPacket::from_packet(packet),
+            OwnedPacket::Raw(id, raw_data) => {
+                data = raw_data;
+                match RawPacketLatest::create(id, &data) {
+                    Ok(raw_packet) => LazyDeserializedPacket::from_raw_packet(raw_packet),
+                    Err(e) => {
+                        error!("Failed to recreate queued packet for \"{}\": {}", &name, e);
+                        continue;
+                    }
+                }
+            }
+        };
+        if proxy.config.batch_client_packets {
+            let flush =
+                queue.is_empty() || proxy.latency_sensitive_kinds.contains(&lazy_packet.kind());
+            if flush != nodelay {
+                match stream.get_ref().set_nodelay(flush) {
+                    Ok(()) => nodelay = flush,
+                    Err(e) => debug!("Failed to set TCP_NODELAY for \"{}\": {}", &name, e),
+                }
+            }
+        }
+        let res = write_packet(&mut writer, lazy_packet).await;
+        if let Err(e) = res {
+            debug!("Failed to write packet to client \"{}\": {}", &name, e);
+            // further writes fail instead of queueing
+            queue.close();
+            break;
+        }
+    }
+}
+
 impl SplinterClient {
+    /// Queues a packet to be written to the client
+    ///
+    /// A full socket buffer only makes the client's writer task wait, so a client that is slow to
+    /// read fills its queue instead. If the queue is full, this waits for the writer to make room
+    /// for up to the configured `client_queue_wait_secs`. A client whose queue stays full isn't
+    /// keeping up with what is sent to it, so it is disconnected by shutting down its stream,
+    /// which also ends a write to it that's stuck waiting on the socket.
     pub async fn write_packet(&self, packet: LazyDeserializedPacket<'_>) -> anyhow::Result<()> {
-        let mut writer = self.writer.lock().await;
-        if packet.is_deserialized() {
-            writer.write_packet_async(packet.into_packet()?)
-        } else {
-            writer.write_raw_packet_async(packet.into_raw_packet().unwrap())
+        let packet = match self.outgoing.try_send(packet.into_owned()?) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(packet)) => packet,
+            Err(TrySendError::Closed(_)) => {
+                bail!("Connection to client \"{}\" is closed", &self.name)
+            }
+        };
+        let wait = Duration::from_secs_f64(self.proxy.config.client_queue_wait_secs);
+        let sent = future::or(async { Some(self.outgoing.send(packet).await) }, async {
+            Timer::after(wait).await;
+            None
+        })
+        .await;
+        match sent {
+            Some(Ok(())) => Ok(()),
+            Some(Err(_)) => bail!("Connection to client \"{}\" is closed", &self.name),
+            None => {
+                warn!(
+                    "Outgoing packet queue for \"{}\" overflowed, disconnecting",
+                    &self.name
+                );
+                self.outgoing.close();
+                self.alive.store(false, Ordering::Relaxed);
+                if let Err(e) = self.stream.get_ref().shutdown(Shutdown::Both) {
+                    debug!("Failed to shut down stream of \"{}\": {}", &self.name, e);
+                }
+                bail!("Outgoing packet queue for \"{}\" is full", &self.name)
+            }
         }
-        .await?;
-        Ok(())
+    }
+    /// Sends a packet to the client outside of the relay, for integrations that need to show the
+    /// client something of their own. The packet does not go through any relay passes, so it
+    /// must already use the client's entity ids and must be a clientbound packet
+    ///
+    /// This can be called from any task or thread, since the packet is only queued here and
+    /// written by the client's writer task. Packets are written in the order they are queued,
+    /// interleaved with relayed packets, and the same queue limit applies, so injecting too many
+    /// packets at once disconnects the client.
+    pub async fn inject_clientbound(&self, packet: PacketLatest) -> anyhow::Result<()> {
+        self.write_packet(LazyDeserializedPacket::from_packet(packet))
+            .await
+            .with_context(|| format!("Failed to inject packet for client \"{}\"", &self.name))
     }
     pub async fn send_kick(&self, reason: ClientKickReason) -> anyhow::Result<()> {
         self.write_packet(LazyDeserializedPacket::from_packet(
@@ -258,3 +611,67 @@ impl SplinterClient {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        protocol::current::{proto::PlayOpenSignEditorSpec, types::IntPosition},
+        proxy::testing::client_with_dummies,
+    };
+
+    /// Sign editor positions are block positions, which aren't remapped, so no pass should
+    /// change or drop the packet, including after a swap
+    #[test]
+    fn sign_editor_relays_unchanged() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = smol::block_on(async {
+            let client = client_with_dummies(&listener, &[1, 2]).await;
+            client.swap_dummy(2).await.unwrap();
+            client
+        });
+        let location = IntPosition {
+            x: -12,
+            y: 70,
+            z: 301,
+        };
+        let mut lazy_packet = LazyDeserializedPacket::from_packet(PacketLatest::PlayOpenSignEditor(
+            PlayOpenSignEditorSpec { location },
+        ));
+        let mut destination = PacketDestination::Client;
+        run_relay_passes(
+            &client.proxy,
+            &client,
+            &PacketDirection::ClientBound,
+            &mut lazy_packet,
+            &mut destination,
+        );
+        assert_eq!(destination, PacketDestination::Client);
+        match lazy_packet.packet() {
+            Ok(PacketLatest::PlayOpenSignEditor(body)) => assert_eq!(body.location, location),
+            _ => panic!("sign editor packet was changed"),
+        }
+    }
+
+    #[test]
+    fn only_reversed_destinations_route_back() {
+        assert!(routes_back(
+            &PacketDirection::ClientBound,
+            PacketDestination::AllServers
+        ));
+        assert!(routes_back(
+            &PacketDirection::ServerBound,
+            PacketDestination::Client
+        ));
+        assert!(!routes_back(
+            &PacketDirection::ClientBound,
+            PacketDestination::Client
+        ));
+        assert!(!routes_back(
+            &PacketDirection::ServerBound,
+            PacketDestination::Server(1)
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+use super::{
+    RelayPass,
+    PRIORITY_OBSERVE,
+};
+use crate::{
+    protocol::{
+        current::{
+            proto::PlaySetPassengersSpec,
+            protocol::PacketDirection,
+            types::VarInt,
+            PacketLatest,
+            PacketLatestKind,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::{
+        client::{
+            Mount,
+            SplinterClient,
+        },
+        mapping::SplinterMapping,
+        server::SplinterServerConnection,
+    },
+};
+
+inventory::submit! {
+    RelayPass {
+        // observed before translation, so that the ids are the active server's
+        priority: PRIORITY_OBSERVE,
+        pass: Box::new(|_proxy, connection, client, sender, lazy_packet, _destination| {
+            if *sender != PacketDirection::ClientBound || !is_mount_packet(lazy_packet.kind()) {
+                return;
+            }
+            match lazy_packet.packet() {
+                Ok(packet) => smol::block_on(client.track_mount(connection, packet)),
+                Err(e) => error!("Failed to deserialize passenger packet: {}", e),
+            }
+        }),
+    }
+}
+
+/// Whether packets of the kind can change what the player is riding
+pub fn is_mount_packet(kind: PacketLatestKind) -> bool {
+    matches!(
+        kind,
+        PacketLatestKind::PlaySetPassengers | PacketLatestKind::PlayDestroyEntities
+    )
+}
+
+/// Updates what the player is riding on a server from a packet the server sent, with the
+/// server's eids
+fn track_mount(mounts: &mut Vec<Mount>, conn: &SplinterServerConnection, packet: &PacketLatest) {
+    let server_id = conn.server.id;
+    match packet {
+        PacketLatest::PlaySetPassengers(body) => {
+            let vehicle = *body.entity_id;
+            let passengers = body
+                .passenger_entitiy_ids
+                .iter()
+                .map(|id| **id)
+                .collect::<Vec<_>>();
+            if passengers.contains(&conn.eid) {
+                // the player can only ride one vehicle on a server
+                mounts.retain(|mount| mount.server_id != server_id);
+                mounts.push(Mount {
+                    server_id,
+                    vehicle,
+                    passengers,
+                });
+            } else {
+                mounts.retain(|mount| mount.server_id != server_id || mount.vehicle != vehicle);
+            }
+        }
+        PacketLatest::PlayDestroyEntities(body) => {
+            mounts.retain(|mount| {
+                mount.server_id != server_id
+                    || !body.entity_ids.iter().any(|id| **id == mount.vehicle)
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Set passengers packet for a vehicle on a server, with the proxy's eids. Passengers the client
+/// doesn't know are left out. `None` if the client doesn't know the vehicle
+fn set_passengers_packet(
+    mapping: &SplinterMapping,
+    server_id: u64,
+    vehicle: i32,
+    passengers: impl Iterator<Item = i32>,
+) -> Option<PacketLatest> {
+    let vehicle = *mapping.eids.get_by_right(&(server_id, vehicle))?;
+    let passengers = passengers
+        .filter_map(|id| mapping.eids.get_by_right(&(server_id, id)))
+        .map(|id| VarInt::from(*id))
+        .collect::<Vec<_>>();
+    Some(PacketLatest::PlaySetPassengers(PlaySetPassengersSpec {
+        entity_id: vehicle.into(),
+        passenger_entitiy_ids: passengers.into(),
+    }))
+}
+
+/// The packets that move the client from the vehicle it was riding on the previous server onto
+/// the one it's riding on the now active server, if any
+///
+/// Entities aren't shared between servers, so the client can't keep riding the previous
+/// server's vehicle. It's shown that vehicle without it as a passenger, and then, if the active
+/// server has the player riding something, it's put back on that. The mapping must already point
+/// the client's own eid at the active server's player.
+fn mount_swap_packets(
+    mounts: &[Mount],
+    previous_conn: &SplinterServerConnection,
+    active_conn: &SplinterServerConnection,
+    mapping: &SplinterMapping,
+) -> Vec<PacketLatest> {
+    let mut packets = Vec::new();
+    let previous_id = previous_conn.server.id;
+    let active_id = active_conn.server.id;
+    if let Some(mount) = mounts.iter().find(|mount| mount.server_id == previous_id) {
+        let passengers = mount
+            .passengers
+            .iter()
+            .copied()
+            .filter(|id| *id != previous_conn.eid);
+        // the vehicle may already have been removed from the client
+        packets.extend(set_passengers_packet(
+            mapping,
+            previous_id,
+            mount.vehicle,
+            passengers,
+        ));
+    }
+    if let Some(mount) = mounts.iter().find(|mount| mount.server_id == active_id) {
+        packets.extend(set_passengers_packet(
+            mapping,
+            active_id,
+            mount.vehicle,
+            mount.passengers.iter().copied(),
+        ));
+    }
+    packets
+}
+
+impl SplinterClient {
+    /// Updates what the player is riding on a server from a packet the server sent
+    pub async fn track_mount(&self, conn: &SplinterServerConnection, packet: &PacketLatest) {
+        track_mount(&mut *self.mounts.lock().await, conn, packet);
+    }
+    /// Gets the packets that move the client off of the vehicle it was riding on a server being
+    /// switched away from, and onto the vehicle the player rides on the now active server, if
+    /// any. They're collected under the mapping lock and written with `write_mount_packets`
+    /// after it's released. Nothing is collected if the player isn't riding anything on either
+    pub async fn mount_swap_packets(
+        &self,
+        previous_conn: &SplinterServerConnection,
+        active_conn: &SplinterServerConnection,
+        mapping: &SplinterMapping,
+    ) -> Vec<PacketLatest> {
+        let mounts = self.mounts.lock().await;
+        if mounts.is_empty() {
+            return Vec::new();
+        }
+        debug!(
+            "moving {} between vehicles after switching from server {} to {}",
+            &self.name, previous_conn.server.id, active_conn.server.id
+        );
+        mount_swap_packets(&mounts, previous_conn, active_conn, mapping)
+    }
+    /// Writes the packets from `mount_swap_packets` to the client
+    pub async fn write_mount_packets(&self, packets: Vec<PacketLatest>) {
+        for packet in packets {
+            if let Err(e) = self
+                .write_packet(LazyDeserializedPacket::from_packet(packet))
+                .await
+            {
+                error!(
+                    "Failed to update the vehicle of \"{}\": {:?}",
+                    &self.name, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::Arc,
+    };
+
+    use super::{
+        super::{
+            run_relay_passes,
+            PacketDestination,
+        },
+        *,
+    };
+    use crate::{
+        protocol::current::proto::{
+            PlayDestroyEntitiesSpec,
+            PlaySteerBoatSpec,
+        },
+        proxy::testing::client_with_dummies,
+    };
+
+    fn set_passengers(vehicle: i32, passengers: &[i32]) -> PacketLatest {
+        PacketLatest::PlaySetPassengers(PlaySetPassengersSpec {
+            entity_id: vehicle.into(),
+            passenger_entitiy_ids: passengers
+                .iter()
+                .map(|id| VarInt::from(*id))
+                .collect::<Vec<_>>()
+                .into(),
+        })
+    }
+
+    fn passengers_of(packet: &PacketLatest) -> (i32, Vec<i32>) {
+        match packet {
+            PacketLatest::PlaySetPassengers(body) => (
+                *body.entity_id,
+                body.passenger_entitiy_ids.iter().map(|id| **id).collect(),
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn swap_moves_client_between_vehicles() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            // the player is entity 10 on server 1 and entity 20 on server 2
+            let client = client_with_dummies(&listener, &[1, 2]).await;
+            let previous_conn = client.active_server.load_full();
+            let active_conn = Arc::clone(&client.dummy_servers.load()[0].1);
+            let mut mapping = client.proxy.mapping.lock().await;
+            let previous_vehicle = mapping.register_eid_mapping(1, 100);
+            let other_passenger = mapping.register_eid_mapping(1, 101);
+            let active_vehicle = mapping.register_eid_mapping(2, 200);
+            client
+                .track_mount(&previous_conn, &set_passengers(100, &[10, 101]))
+                .await;
+            client
+                .track_mount(&active_conn, &set_passengers(200, &[20]))
+                .await;
+            mapping.remap_eid(client.proxy_eid, 2, 20);
+
+            let packets = client
+                .mount_swap_packets(&previous_conn, &active_conn, &mapping)
+                .await;
+            assert_eq!(packets.len(), 2);
+            assert_eq!(
+                passengers_of(&packets[0]),
+                (previous_vehicle, vec![other_passenger])
+            );
+            assert_eq!(
+                passengers_of(&packets[1]),
+                (active_vehicle, vec![client.proxy_eid])
+            );
+        });
+    }
+
+    #[test]
+    fn unmounted_swap_sends_nothing() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1, 2]).await;
+            let previous_conn = client.active_server.load_full();
+            let active_conn = Arc::clone(&client.dummy_servers.load()[0].1);
+            let mapping = client.proxy.mapping.lock().await;
+            // riding and then getting off
+            client
+                .track_mount(&previous_conn, &set_passengers(100, &[10]))
+                .await;
+            client
+                .track_mount(&previous_conn, &set_passengers(100, &[]))
+                .await;
+            // riding something that's then removed
+            client
+                .track_mount(&active_conn, &set_passengers(200, &[20]))
+                .await;
+            client
+                .track_mount(
+                    &active_conn,
+                    &PacketLatest::PlayDestroyEntities(PlayDestroyEntitiesSpec {
+                        entity_ids: vec![VarInt::from(200)].into(),
+                    }),
+                )
+                .await;
+            assert!(client.mounts.lock().await.is_empty());
+            assert!(client
+                .mount_swap_packets(&previous_conn, &active_conn, &mapping)
+                .await
+                .is_empty());
+        });
+    }
+
+    #[test]
+    fn boat_driver_keeps_control_across_swap() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1, 2]).await;
+            let previous_conn = client.active_server.load_full();
+            let active_conn = Arc::clone(&client.dummy_servers.load()[0].1);
+            // the player drives a boat on server 1, and the same boat is on server 2
+            let active_boat = {
+                let mut mapping = client.proxy.mapping.lock().await;
+                mapping.register_eid_mapping(1, 100);
+                mapping.register_eid_mapping(2, 200)
+            };
+            client
+                .track_mount(&previous_conn, &set_passengers(100, &[10]))
+                .await;
+            client
+                .track_mount(&active_conn, &set_passengers(200, &[20]))
+                .await;
+
+            client.swap_dummy(2).await.unwrap();
+            assert_eq!(client.server_id(), 2);
+            // both servers still have the player in their boat
+            assert_eq!(client.mounts.lock().await.len(), 2);
+            let mapping = client.proxy.mapping.lock().await;
+            let packets = client
+                .mount_swap_packets(&previous_conn, &active_conn, &mapping)
+                .await;
+            assert_eq!(
+                packets.last().map(passengers_of),
+                Some((active_boat, vec![client.proxy_eid]))
+            );
+            drop(mapping);
+
+            // steering has no eids, and still reaches the now active server
+            let mut lazy_packet = LazyDeserializedPacket::from_packet(PacketLatest::PlaySteerBoat(
+                PlaySteerBoatSpec {
+                    left_paddle_turning: true,
+                    right_paddle_turning: false,
+                },
+            ));
+            let mut destination = PacketDestination::AllServers;
+            run_relay_passes(
+                &client.proxy,
+                &client,
+                &PacketDirection::ServerBound,
+                &mut lazy_packet,
+                &mut destination,
+            );
+            assert_eq!(destination, PacketDestination::AllServers);
+            assert!(matches!(
+                lazy_packet.packet(),
+                Ok(PacketLatest::PlaySteerBoat(body)) if body.left_paddle_turning
+            ));
+        });
+    }
+}
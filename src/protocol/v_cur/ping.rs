@@ -0,0 +1,100 @@
+use std::{
+    sync::atomic::{
+        AtomicI32,
+        Ordering,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use futures_lite::future;
+use smol::{
+    channel,
+    Timer,
+};
+
+use super::{
+    PacketDestination,
+    RelayPass,
+    PRIORITY_CONSUME,
+};
+use crate::{
+    protocol::{
+        current::{
+            proto::PlayPingSpec,
+            protocol::PacketDirection,
+            PacketLatest,
+            PacketLatestKind,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::client::SplinterClient,
+};
+
+/// Id of the next ping the proxy sends. Counts up from the lowest id, as servers tend to number
+/// their pings from zero, so the proxy's are unlikely to be in use by a server at the same time
+static NEXT_PING_ID: AtomicI32 = AtomicI32::new(i32::MIN);
+
+// Pongs answering the proxy's own pings are consumed here. Pongs for pings a server sent are
+// left alone, so they are relayed to the server like any other packet.
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_CONSUME,
+        pass: Box::new(|_proxy, _connection, client, sender, lazy_packet, destination| {
+            if *sender != PacketDirection::ServerBound
+                || lazy_packet.kind() != PacketLatestKind::PlayPong
+            {
+                return;
+            }
+            match lazy_packet.packet() {
+                Ok(PacketLatest::PlayPong(body)) => {
+                    if let Some(waiting) = smol::block_on(client.pending_pings.lock()).remove(&body.id) {
+                        // the ping may have already timed out, in which case no one is waiting
+                        waiting.try_send(()).ok();
+                        *destination = PacketDestination::None;
+                    }
+                }
+                Ok(_) => unreachable!(),
+                Err(e) => error!("Failed to deserialize pong: {}", e),
+            }
+        }),
+    }
+}
+
+impl SplinterClient {
+    /// Pings the client and waits for its pong, returning how long the round trip took
+    ///
+    /// The time includes waiting in the client's packet queue, so it is the latency the client
+    /// experiences from the proxy rather than just the network's.
+    pub async fn ping(&self, timeout: Duration) -> anyhow::Result<Duration> {
+        let id = NEXT_PING_ID.fetch_add(1, Ordering::Relaxed);
+        let (waiting, pong) = channel::bounded(1);
+        self.pending_pings.lock().await.insert(id, waiting);
+        let sent_at = Instant::now();
+        let res = match self
+            .write_packet(LazyDeserializedPacket::from_packet(PacketLatest::PlayPing(
+                PlayPingSpec { id },
+            )))
+            .await
+        {
+            Ok(()) => {
+                future::or(async { pong.recv().await.ok() }, async {
+                    Timer::after(timeout).await;
+                    None
+                })
+                .await
+            }
+            Err(e) => {
+                self.pending_pings.lock().await.remove(&id);
+                return Err(e);
+            }
+        };
+        self.pending_pings.lock().await.remove(&id);
+        match res {
+            Some(()) => Ok(sent_at.elapsed()),
+            None => bail!("\"{}\" did not answer a ping within {:?}", &self.name, timeout),
+        }
+    }
+}
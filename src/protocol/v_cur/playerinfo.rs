@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use super::{
+    RelayPass,
+    PRIORITY_OBSERVE,
+    PRIORITY_TRANSLATE,
+};
+use crate::{
+    protocol::{
+        current::{
+            proto::{
+                PlayPlayerInfoSpec,
+                PlayerInfoAction,
+                PlayerInfoActionList,
+            },
+            protocol::PacketDirection,
+            types::Chat,
+            PacketLatest,
+            PacketLatestKind,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::SplinterProxy,
+};
+
+// Clients only get player info from their active server, so display name changes are passed on
+// to the clients on other servers as well.
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_OBSERVE,
+        pass: Box::new(|proxy, connection, _client, sender, lazy_packet, _destination| {
+            if *sender == PacketDirection::ClientBound
+                && lazy_packet.kind() == PacketLatestKind::PlayPlayerInfo
+            {
+                match lazy_packet.packet() {
+                    Ok(PacketLatest::PlayPlayerInfo(body)) => {
+                        if let PlayerInfoActionList::UpdateDisplayName(updates) = &body.actions {
+                            smol::block_on(
+                                rebroadcast_display_names(proxy, connection.server.id, updates),
+                            );
+                        }
+                    }
+                    Ok(_) => unreachable!(),
+                    Err(e) => error!("Failed to deserialize player info packet: {}", e),
+                }
+            }
+        }),
+    }
+}
+
+// Players added to a client's player list, like everyone already there when the client joins,
+// are given the display names other servers have set for them.
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_TRANSLATE,
+        pass: Box::new(|proxy, _connection, _client, sender, lazy_packet, _destination| {
+            if *sender == PacketDirection::ClientBound
+                && lazy_packet.kind() == PacketLatestKind::PlayPlayerInfo
+            {
+                match lazy_packet.packet() {
+                    Ok(PacketLatest::PlayPlayerInfo(body)) => {
+                        if let PlayerInfoActionList::Add(added) = &mut body.actions {
+                            let display_names = smol::block_on(proxy.display_names.lock());
+                            for player in added.iter_mut() {
+                                if player.action.display_name.is_some() {
+                                    continue;
+                                }
+                                if let Some(Some(name)) = display_names.get(&player.uuid) {
+                                    player.action.display_name = Some(name.clone());
+                                }
+                            }
+                        }
+                    }
+                    Ok(_) => unreachable!(),
+                    Err(e) => error!("Failed to deserialize player info packet: {}", e),
+                }
+            }
+        }),
+    }
+}
+
+/// Sends display name updates from a server to the clients on every other server
+///
+/// Every client on the server gets the same update, so only updates that change the display name
+/// the proxy knows of are sent. This also means that the rebroadcast updates, which reach the
+/// clients without going through relay passes, are never rebroadcast themselves.
+async fn rebroadcast_display_names(
+    proxy: &Arc<SplinterProxy>,
+    server_id: u64,
+    updates: &[PlayerInfoAction<Option<Chat>>],
+) {
+    let changed = {
+        let display_names = &mut *proxy.display_names.lock().await;
+        updates
+            .iter()
+            .filter(|update| display_names.get(&update.uuid) != Some(&update.action))
+            .map(|update| {
+                display_names.insert(update.uuid, update.action.clone());
+                update.clone()
+            })
+            .collect::<Vec<_>>()
+    };
+    if changed.is_empty() {
+        return;
+    }
+    let packet = PacketLatest::PlayPlayerInfo(PlayPlayerInfoSpec {
+        actions: PlayerInfoActionList::UpdateDisplayName(changed),
+    });
+    for (_, client) in proxy.players.read().await.iter() {
+        if client.server_id() == server_id {
+            continue;
+        }
+        if let Err(e) = client
+            .write_packet(LazyDeserializedPacket::from_packet(packet.clone()))
+            .await
+        {
+            error!(
+                "Failed to send display name update to \"{}\": {}",
+                &client.name, e
+            );
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use super::{
+    PacketDestination,
+    RelayPass,
+    PRIORITY_TRANSLATE,
+};
+use crate::protocol::current::{
+    proto::ResourcePackStatus,
+    protocol::PacketDirection,
+    PacketLatest,
+    PacketLatestKind,
+};
+
+// Resource pack prompts relay to the client unchanged. The client's status replies should go to
+// the server that sent the prompt rather than every server, since the others never asked, and
+// the latest status is kept for anything that wants to know whether the client has the pack.
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_TRANSLATE,
+        pass: Box::new(|_proxy, connection, client, sender, lazy_packet, destination| {
+            match sender {
+                PacketDirection::ClientBound => {
+                    if lazy_packet.kind() == PacketLatestKind::PlayResourcePackSend {
+                        *smol::block_on(client.resource_pack_server.lock()) = Some(connection.server.id);
+                    }
+                }
+                PacketDirection::ServerBound => {
+                    if lazy_packet.kind() != PacketLatestKind::PlayResourcePackStatus {
+                        return;
+                    }
+                    let status = match lazy_packet.packet() {
+                        Ok(PacketLatest::PlayResourcePackStatus(body)) => body.status,
+                        Ok(_) => unreachable!(),
+                        Err(e) => {
+                            error!("Failed to deserialize resource pack status: {}", e);
+                            return;
+                        }
+                    };
+                    *smol::block_on(client.resource_pack_status.lock()) = Some(status);
+                    let server_slot = &mut *smol::block_on(client.resource_pack_server.lock());
+                    // the client sends more statuses after accepting, until the pack is loaded or
+                    // fails
+                    let server_id = if matches!(status, ResourcePackStatus::Accepted) {
+                        *server_slot
+                    } else {
+                        server_slot.take()
+                    };
+                    // fall back to the active server if the one that sent the prompt has since
+                    // been disconnected
+                    let server_id = server_id
+                        .filter(|id| client.dummy_servers.load().iter().any(|(dummy_id, _)| dummy_id == id))
+                        .unwrap_or(connection.server.id);
+                    *destination = PacketDestination::Server(server_id);
+                }
+            }
+        }),
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::Ordering;
+
+use craftio_rs::CraftAsyncWriter;
+
+use super::{
+    RelayPass,
+    PRIORITY_OBSERVE,
+};
+use crate::{
+    protocol::current::{
+        proto::{
+            ClientStatusAction,
+            PlayClientStatusSpec,
+        },
+        protocol::PacketDirection,
+        PacketLatest,
+        PacketLatestKind,
+    },
+    proxy::{
+        client::SplinterClient,
+        server::SplinterServerConnection,
+    },
+};
+
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_OBSERVE,
+        pass: Box::new(|_proxy, connection, client, sender, lazy_packet, _destination| {
+            match sender {
+                PacketDirection::ServerBound => {
+                    if lazy_packet.kind() == PacketLatestKind::PlayClientStatus {
+                        if let Ok(PacketLatest::PlayClientStatus(body)) = lazy_packet.packet() {
+                            if matches!(body.action, ClientStatusAction::PerformRespawn) {
+                                // the respawn is relayed to every server
+                                clear_awaiting_respawn(client);
+                            }
+                        }
+                    }
+                }
+                PacketDirection::ClientBound => {
+                    if matches!(lazy_packet.kind(),
+                        PacketLatestKind::PlayUpdateHealth
+                        | PacketLatestKind::PlayRespawn
+                    ) {
+                        match lazy_packet.packet() {
+                            Ok(PacketLatest::PlayUpdateHealth(body)) => {
+                                if body.health <= 0. {
+                                    connection.awaiting_respawn.store(true, Ordering::Relaxed);
+                                    if !client.dead.swap(true, Ordering::Relaxed) {
+                                        debug!("{} died", &client.name);
+                                    }
+                                }
+                            }
+                            Ok(PacketLatest::PlayRespawn(body)) => {
+                                connection.awaiting_respawn.store(false, Ordering::Relaxed);
+                                client.dead.store(false, Ordering::Relaxed);
+                                smol::block_on(client.respawned_into(&body.world_name));
+                            }
+                            Ok(_) => unreachable!(),
+                            Err(e) => error!("Failed to deserialize respawn related packet: {}", e),
+                        }
+                    }
+                }
+            }
+        }),
+    }
+}
+
+fn clear_awaiting_respawn(client: &SplinterClient) {
+    client
+        .active_server
+        .load()
+        .awaiting_respawn
+        .store(false, Ordering::Relaxed);
+    for (_, dummy) in client.dummy_servers.load().iter() {
+        dummy.awaiting_respawn.store(false, Ordering::Relaxed);
+    }
+}
+
+impl SplinterClient {
+    /// Whether the client died and has not respawned yet
+    pub fn is_dead(&self) -> bool {
+        self.dead.load(Ordering::Relaxed)
+    }
+}
+
+impl SplinterServerConnection {
+    /// Whether the player on this connection is dead and waiting to respawn
+    pub fn is_awaiting_respawn(&self) -> bool {
+        self.awaiting_respawn.load(Ordering::Relaxed)
+    }
+    /// Asks the server to respawn the player on this connection
+    pub async fn respawn(&self) -> anyhow::Result<()> {
+        self.writer
+            .lock()
+            .await
+            .write_packet_async(PacketLatest::PlayClientStatus(PlayClientStatusSpec {
+                action: ClientStatusAction::PerformRespawn,
+            }))
+            .await?;
+        self.awaiting_respawn.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use super::{
+    parse_packet_kinds,
+    weather::{
+        time_differs,
+        weather_changes,
+        weather_slot,
+        Weather,
+    },
+    RelayPass,
+    PRIORITY_OBSERVE,
+};
+use crate::{
+    protocol::{
+        current::{
+            protocol::{
+                HasPacketKind,
+                PacketDirection,
+            },
+            PacketLatest,
+            PacketLatestKind,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::{
+        client::SplinterClient,
+        server::SplinterServerConnection,
+    },
+};
+
+/// The packets a server last sent of each resynced kind. Most kinds keep only their last packet,
+/// and game state changes keep one for each part of the weather, so a connection holds at most a
+/// few small packets for each kind in `resync_kinds`
+pub type ResyncPackets = HashMap<PacketLatestKind, Vec<PacketLatest>>;
+
+/// Packets that may be resent from a server's last one when switching onto it, by the name
+/// they're given in the config
+///
+/// Only packets that set a single piece of state outright belong here, since only the last one
+/// of each kind is kept. Packets with side effects when replayed, like health updates that can
+/// kill the client, don't.
+const RESYNCABLE_PACKETS: &[(&str, PacketLatestKind)] = &[
+    (
+        "PlayerAbilities",
+        PacketLatestKind::PlayServerPlayerAbilities,
+    ),
+    ("SpawnPosition", PacketLatestKind::PlaySpawnPosition),
+];
+
+/// Packets that are always resent when switching servers, whatever the config says, since every
+/// server keeps its own of them, like the player's experience and the difficulty shown in the
+/// menu. The world border is first so that an initialize is resent before the changes that came
+/// after it
+const ALWAYS_RESYNCED: &[PacketLatestKind] = &[
+    PacketLatestKind::PlayInitializeWorldBorder,
+    PacketLatestKind::PlayWorldBorderCenter,
+    PacketLatestKind::PlayWorldBorderLerpSize,
+    PacketLatestKind::PlayWorldBorderSize,
+    PacketLatestKind::PlayWorldBorderWarningDelay,
+    PacketLatestKind::PlayWorldBorderWarningReach,
+    PacketLatestKind::PlayTimeUpdate,
+    PacketLatestKind::PlayChangeGameState,
+    PacketLatestKind::PlaySetExperience,
+    PacketLatestKind::PlayServerDifficulty,
+];
+
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_OBSERVE,
+        pass: Box::new(|proxy, connection, _client, sender, lazy_packet, _destination| {
+            if *sender == PacketDirection::ClientBound {
+                smol::block_on(connection.track_resync_lazy(&proxy.resync_kinds, lazy_packet));
+            }
+        }),
+    }
+}
+
+/// Whether a packet kind changes the client's world border
+fn is_world_border(kind: PacketLatestKind) -> bool {
+    matches!(
+        kind,
+        PacketLatestKind::PlayInitializeWorldBorder
+            | PacketLatestKind::PlayWorldBorderCenter
+            | PacketLatestKind::PlayWorldBorderLerpSize
+            | PacketLatestKind::PlayWorldBorderSize
+            | PacketLatestKind::PlayWorldBorderWarningDelay
+            | PacketLatestKind::PlayWorldBorderWarningReach
+    )
+}
+
+/// Gets the packet kinds to resend on switching servers from their names in the config. The
+/// kinds that are always resent come first
+pub fn parse_resync_kinds(names: &[String]) -> anyhow::Result<Vec<PacketLatestKind>> {
+    let mut kinds = ALWAYS_RESYNCED.to_vec();
+    kinds.extend(parse_packet_kinds(names, RESYNCABLE_PACKETS, "resynced")?);
+    Ok(kinds)
+}
+
+/// Records a packet in a server's resync packets
+fn track_resync(resync_packets: &mut ResyncPackets, packet: &PacketLatest) {
+    let kind = packet.kind();
+    match packet {
+        PacketLatest::PlayChangeGameState(body) => {
+            // only the weather is kept, with the last packet for each part of it
+            let slot = match weather_slot(&body.reason) {
+                Some(slot) => slot,
+                None => return,
+            };
+            let packets = resync_packets.entry(kind).or_insert_with(Vec::new);
+            packets.retain(|recorded| match recorded {
+                PacketLatest::PlayChangeGameState(recorded) => {
+                    weather_slot(&recorded.reason) != Some(slot)
+                }
+                _ => true,
+            });
+            packets.push(packet.clone());
+        }
+        _ => {
+            if kind == PacketLatestKind::PlayInitializeWorldBorder {
+                // an initialize sets every property of the border
+                resync_packets.retain(|recorded, _| !is_world_border(*recorded));
+            }
+            resync_packets.insert(kind, vec![packet.clone()]);
+        }
+    }
+}
+
+/// The packets to resend to the client when switching from a server with the previous resync
+/// packets to one with the current ones, in the order of the kinds
+///
+/// Packets the client already has from the previous server are skipped. The world border is
+/// resent whole if any of it differs, since its packets build on each other. The time is only
+/// resent when it's further off than the time between updates, and the weather is resent for
+/// each part that differs, with servers that never sent any being clear.
+fn resync_packets(
+    kinds: &[PacketLatestKind],
+    previous: &ResyncPackets,
+    current: &ResyncPackets,
+) -> Vec<PacketLatest> {
+    let border_differs = kinds
+        .iter()
+        .filter(|kind| is_world_border(**kind))
+        .any(|kind| previous.get(kind) != current.get(kind));
+    let mut packets = vec![];
+    for kind in kinds.iter() {
+        let previous_packets = previous.get(kind).map_or(&[][..], Vec::as_slice);
+        let current_packets = current.get(kind).map_or(&[][..], Vec::as_slice);
+        match kind {
+            kind if is_world_border(*kind) => {
+                if border_differs {
+                    packets.extend(current_packets.iter().cloned());
+                }
+            }
+            PacketLatestKind::PlayTimeUpdate => {
+                if let Some(time) = current_packets.first() {
+                    if time_differs(previous_packets.first(), time) {
+                        packets.push(time.clone());
+                    }
+                }
+            }
+            PacketLatestKind::PlayChangeGameState => packets.extend(weather_changes(
+                Weather::from_packets(previous_packets),
+                Weather::from_packets(current_packets),
+            )),
+            _ => packets.extend(
+                current_packets
+                    .iter()
+                    .filter(|packet| !previous_packets.contains(packet))
+                    .cloned(),
+            ),
+        }
+    }
+    packets
+}
+
+impl SplinterServerConnection {
+    /// Records a packet the server sent if it's of a kind resent when switching onto the server.
+    /// This is the one place resynced packets are recorded, whichever way they're read from the
+    /// server
+    pub async fn track_resync(&self, resync_kinds: &[PacketLatestKind], packet: &PacketLatest) {
+        if resync_kinds.contains(&packet.kind()) {
+            track_resync(&mut *self.resync_packets.lock().await, packet);
+        }
+    }
+    /// Same as `track_resync`, for a packet that may not be deserialized yet. Only packets of
+    /// resynced kinds are deserialized
+    pub async fn track_resync_lazy(
+        &self,
+        resync_kinds: &[PacketLatestKind],
+        lazy_packet: &mut LazyDeserializedPacket,
+    ) {
+        let kind = lazy_packet.kind();
+        if !resync_kinds.contains(&kind) {
+            return;
+        }
+        match lazy_packet.packet() {
+            Ok(packet) => track_resync(&mut *self.resync_packets.lock().await, packet),
+            Err(e) => error!(
+                "Failed to deserialize {:?} packet from server {}: {}",
+                kind, self.server.id, e
+            ),
+        }
+    }
+}
+
+impl SplinterClient {
+    /// Resends what a newly active connection's server last sent of each resynced kind, where it
+    /// differs from what the previously active server sent, so that the client doesn't keep the
+    /// previous server's state until the new server happens to send it again
+    pub async fn resend_resync_packets(
+        &self,
+        previous_conn: &SplinterServerConnection,
+        conn: &SplinterServerConnection,
+    ) {
+        let previous = previous_conn.resync_packets.lock().await.clone();
+        let current = conn.resync_packets.lock().await.clone();
+        let packets = resync_packets(&self.proxy.resync_kinds, &previous, &current);
+        if packets.is_empty() {
+            return;
+        }
+        debug!(
+            "resending {} packets from server {} to {}",
+            packets.len(),
+            conn.server.id,
+            &self.name
+        );
+        for packet in packets {
+            if let Err(e) = self
+                .write_packet(LazyDeserializedPacket::from_packet(packet))
+                .await
+            {
+                error!("Failed to resend packets to \"{}\": {:?}", &self.name, e);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::current::proto::{
+        GameChangeReason,
+        PlayChangeGameStateSpec,
+        PlayTimeUpdateSpec,
+    };
+
+    fn time(time_of_day: i64) -> PacketLatest {
+        PacketLatest::PlayTimeUpdate(PlayTimeUpdateSpec {
+            world_age: 24000,
+            time_of_day,
+        })
+    }
+
+    fn game_state(reason: GameChangeReason) -> PacketLatest {
+        PacketLatest::PlayChangeGameState(PlayChangeGameStateSpec {
+            reason,
+        })
+    }
+
+    #[test]
+    fn only_differing_state_is_resent() {
+        let kinds = parse_resync_kinds(&[]).unwrap();
+        let mut previous = ResyncPackets::new();
+        track_resync(&mut previous, &time(6000));
+        track_resync(&mut previous, &game_state(GameChangeReason::BeginRaining));
+        track_resync(
+            &mut previous,
+            &game_state(GameChangeReason::RainLevelChange(1.0)),
+        );
+        let mut current = ResyncPackets::new();
+        track_resync(&mut current, &time(6010));
+        track_resync(
+            &mut current,
+            &game_state(GameChangeReason::RainLevelChange(0.5)),
+        );
+        track_resync(
+            &mut current,
+            &game_state(GameChangeReason::RainLevelChange(1.0)),
+        );
+        // each part of the weather keeps only its last change
+        assert_eq!(current[&PacketLatestKind::PlayChangeGameState].len(), 1);
+
+        // the time is close enough and the rain level is the same, but the new server never
+        // started raining
+        let packets = resync_packets(&kinds, &previous, &current);
+        assert!(packets == vec![game_state(GameChangeReason::EndRaining)]);
+        // and switching back starts it again
+        let packets = resync_packets(&kinds, &current, &previous);
+        assert!(packets == vec![game_state(GameChangeReason::BeginRaining)]);
+    }
+
+    #[test]
+    fn time_is_resent_past_tolerance() {
+        let kinds = parse_resync_kinds(&[]).unwrap();
+        let mut previous = ResyncPackets::new();
+        track_resync(&mut previous, &time(6000));
+        let mut current = ResyncPackets::new();
+        track_resync(&mut current, &time(18000));
+        assert!(resync_packets(&kinds, &previous, &current) == vec![time(18000)]);
+        // nothing is resent for a server that never sent the time
+        assert!(resync_packets(&kinds, &previous, &ResyncPackets::new()).is_empty());
+    }
+}
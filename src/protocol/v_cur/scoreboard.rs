@@ -0,0 +1,82 @@
+use super::{
+    RelayPass,
+    PRIORITY_OBSERVE,
+};
+use crate::{
+    protocol::{
+        current::{
+            proto::PlayDisplayScoreboardSpec,
+            protocol::PacketDirection,
+            PacketLatest,
+            PacketLatestKind,
+        },
+        events::LazyDeserializedPacket,
+    },
+    proxy::{
+        client::SplinterClient,
+        server::SplinterServerConnection,
+    },
+};
+
+inventory::submit! {
+    RelayPass {
+        priority: PRIORITY_OBSERVE,
+        pass: Box::new(|_proxy, connection, _client, sender, lazy_packet, _destination| {
+            if *sender == PacketDirection::ClientBound
+                && lazy_packet.kind() == PacketLatestKind::PlayDisplayScoreboard
+            {
+                match lazy_packet.packet() {
+                    Ok(PacketLatest::PlayDisplayScoreboard(body)) => {
+                        smol::block_on(
+                            connection.track_displayed_objective(body.position, &body.score_name),
+                        );
+                    }
+                    Ok(_) => unreachable!(),
+                    Err(e) => error!("Failed to deserialize display scoreboard packet: {}", e),
+                }
+            }
+        }),
+    }
+}
+
+impl SplinterServerConnection {
+    /// Records which objective the server displayed at a position, where an empty name means it
+    /// cleared the position
+    pub async fn track_displayed_objective(&self, position: i8, objective: &str) {
+        let displayed = &mut *self.displayed_objectives.lock().await;
+        if objective.is_empty() {
+            displayed.remove(&position);
+        } else {
+            displayed.insert(position, objective.to_owned());
+        }
+    }
+}
+
+impl SplinterClient {
+    /// Clears every scoreboard position (list, sidebar, below name, and team sidebars) a
+    /// connection displayed on the client, so they don't stick around after switching away from
+    /// its server
+    pub async fn clear_displayed_objectives(&self, conn: &SplinterServerConnection) {
+        let displayed = std::mem::take(&mut *conn.displayed_objectives.lock().await);
+        for (position, objective) in displayed {
+            debug!(
+                "clearing objective \"{}\" at position {} for {} from server {}",
+                objective, position, &self.name, conn.server.id
+            );
+            if let Err(e) = self
+                .write_packet(LazyDeserializedPacket::from_packet(
+                    PacketLatest::PlayDisplayScoreboard(PlayDisplayScoreboardSpec {
+                        position,
+                        score_name: String::new(),
+                    }),
+                ))
+                .await
+            {
+                error!(
+                    "Failed to clear scoreboard position {} for \"{}\": {:?}",
+                    position, &self.name, e
+                );
+            }
+        }
+    }
+}
@@ -1,47 +1,135 @@
 use std::{
     collections::HashMap,
+    fs,
     iter::FromIterator,
 };
 
+use anyhow::Context;
 use bimap::BiHashMap;
 
-use crate::protocol::{
-    current::{
-        proto::{
-            PlayTagsSpec,
-            TagSpec,
-            TagType,
-            TypedTagList,
-        },
-        types::{
-            CountedArray,
-            VarInt,
+use crate::{
+    protocol::{
+        current::{
+            proto::{
+                PlayTagsSpec,
+                TagSpec,
+                TagType,
+                TypedTagList,
+            },
+            types::{
+                CountedArray,
+                VarInt,
+            },
         },
+        describe_version,
+        load_json_id_name_pairs,
+        TagList,
+        Tags,
     },
-    load_json_id_name_pairs,
-    TagList,
-    Tags,
+    proxy::config::SplinterConfig,
 };
 
-lazy_static! {
-    pub static ref BLOCK_MAP: BiHashMap<i32, String> =
-        BiHashMap::<i32, String>::from_iter(load_json_id_name_pairs(include_str!(
-            "../../../minecraft-data/data/pc/1.17/blocks.json"
-        )));
-    pub static ref ITEM_MAP: BiHashMap<i32, String> =
-        BiHashMap::<i32, String>::from_iter(load_json_id_name_pairs(include_str!(
-            "../../../minecraft-data/data/pc/1.17/items.json"
-        )));
-    pub static ref ENTITY_MAP: BiHashMap<i32, String> =
-        BiHashMap::<i32, String>::from_iter(load_json_id_name_pairs(include_str!(
-            "../../../minecraft-data/data/pc/1.17/entities.json"
-        )));
-    pub static ref FLUID_MAP: BiHashMap<i32, String> = BiHashMap::<i32, String>::from_iter(
-        load_json_id_name_pairs(include_str!("../../../fluids.json")),
-    );
-    pub static ref GAME_EVENT_MAP: BiHashMap<i32, String> = BiHashMap::<i32, String>::from_iter(
-        load_json_id_name_pairs(include_str!("../../../game_events.json")),
-    );
+/// Protocol version of the embedded block, item, and entity data
+const EMBEDDED_DATA_PROTOCOL: i32 = 756;
+
+const EMBEDDED_BLOCKS: &str = include_str!("../../../minecraft-data/data/pc/1.17/blocks.json");
+const EMBEDDED_ITEMS: &str = include_str!("../../../minecraft-data/data/pc/1.17/items.json");
+const EMBEDDED_ENTITIES: &str =
+    include_str!("../../../minecraft-data/data/pc/1.17/entities.json");
+const EMBEDDED_FLUIDS: &str = include_str!("../../../fluids.json");
+const EMBEDDED_GAME_EVENTS: &str = include_str!("../../../game_events.json");
+
+/// Id to name maps used to convert tags between their protocol and named forms
+pub struct IdNameMaps {
+    pub blocks: BiHashMap<i32, String>,
+    pub items: BiHashMap<i32, String>,
+    pub entities: BiHashMap<i32, String>,
+    pub fluids: BiHashMap<i32, String>,
+    pub game_events: BiHashMap<i32, String>,
+}
+
+impl IdNameMaps {
+    /// Loads the id to name maps for a protocol version from the data files specified in the
+    /// config, preferring the files given for that version. Data files that are not specified
+    /// fall back to the data embedded in the proxy, which is only correct for the version it was
+    /// taken from, so other versions fail to load without them
+    pub fn load(config: &SplinterConfig, protocol: i32) -> anyhow::Result<IdNameMaps> {
+        let version_paths = config.version_data_paths.get(&protocol);
+        Ok(IdNameMaps {
+            blocks: load_id_name_map(
+                data_path(
+                    protocol,
+                    "block",
+                    version_paths.and_then(|paths| paths.block_data_path.as_ref()),
+                    config.block_data_path.as_ref(),
+                )?,
+                EMBEDDED_BLOCKS,
+            )?,
+            items: load_id_name_map(
+                data_path(
+                    protocol,
+                    "item",
+                    version_paths.and_then(|paths| paths.item_data_path.as_ref()),
+                    config.item_data_path.as_ref(),
+                )?,
+                EMBEDDED_ITEMS,
+            )?,
+            entities: load_id_name_map(
+                data_path(
+                    protocol,
+                    "entity",
+                    version_paths.and_then(|paths| paths.entity_data_path.as_ref()),
+                    config.entity_data_path.as_ref(),
+                )?,
+                EMBEDDED_ENTITIES,
+            )?,
+            fluids: load_id_name_map(None, EMBEDDED_FLUIDS)?,
+            game_events: load_id_name_map(None, EMBEDDED_GAME_EVENTS)?,
+        })
+    }
+    /// Loads the id to name maps for the version of the embedded data, to check the configured
+    /// data files when starting up
+    pub fn load_default(config: &SplinterConfig) -> anyhow::Result<(i32, IdNameMaps)> {
+        Ok((
+            EMBEDDED_DATA_PROTOCOL,
+            IdNameMaps::load(config, EMBEDDED_DATA_PROTOCOL)?,
+        ))
+    }
+}
+
+/// Picks the data file to use for a protocol version, or none for the embedded data
+fn data_path<'a>(
+    protocol: i32,
+    kind: &str,
+    version_path: Option<&'a String>,
+    path: Option<&'a String>,
+) -> anyhow::Result<Option<&'a String>> {
+    let path = version_path.or(path);
+    if path.is_none() && protocol != EMBEDDED_DATA_PROTOCOL {
+        bail!(
+            "No {} data file is configured for {}, and the embedded data is for {}",
+            kind,
+            describe_version(protocol),
+            describe_version(EMBEDDED_DATA_PROTOCOL)
+        );
+    }
+    Ok(path)
+}
+
+fn load_id_name_map(
+    path: Option<&String>,
+    embedded: &str,
+) -> anyhow::Result<BiHashMap<i32, String>> {
+    let pairs = if let Some(path) = path {
+        load_json_id_name_pairs(
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read data file \"{}\"", path))?,
+        )
+        .with_context(|| format!("Failed to parse data file \"{}\"", path))?
+    } else {
+        load_json_id_name_pairs(embedded)?
+    };
+    Ok(BiHashMap::<i32, String>::from_iter(pairs))
 }
 
 pub fn proto_tags_to_tags(
@@ -54,7 +142,13 @@ pub fn proto_tags_to_tags(
             tag.name.clone(),
             tag.entries
                 .iter()
-                .map(|val| map.get_by_left(&**val).unwrap().clone())
+                .filter_map(|val| {
+                    let name = map.get_by_left(&**val).cloned();
+                    if name.is_none() {
+                        warn!("Unknown id {} in tag \"{}\"", **val, &tag.name);
+                    }
+                    name
+                })
                 .collect::<Vec<String>>(),
         );
     }
@@ -71,7 +165,13 @@ pub fn tags_to_proto_tags(
             name: name.clone(),
             entries: ids
                 .iter()
-                .map(|id| VarInt::from(*map.get_by_right(id).unwrap()))
+                .filter_map(|id| {
+                    let val = map.get_by_right(id).map(|val| VarInt::from(*val));
+                    if val.is_none() {
+                        warn!("Unknown name \"{}\" in tag \"{}\"", id, name);
+                    }
+                    val
+                })
                 .collect::<Vec<VarInt>>()
                 .into(),
         });
@@ -79,16 +179,31 @@ pub fn tags_to_proto_tags(
     list.into()
 }
 
-impl From<&PlayTagsSpec> for Tags {
-    fn from(proto_tags: &PlayTagsSpec) -> Tags {
+impl Tags {
+    /// Creates tags with every tag type present but no tags
+    pub fn empty() -> Tags {
+        let tags = [
+            "minecraft:block",
+            "minecraft:item",
+            "minecraft:fluid",
+            "minecraft:entity_type",
+            "minecraft:game_event",
+        ]
+        .iter()
+        .map(|type_name| (type_name.to_string(), TagList(HashMap::new())))
+        .collect();
+        Tags { tags }
+    }
+    /// Converts tags from their protocol form using the given id to name maps
+    pub fn from_proto(proto_tags: &PlayTagsSpec, maps: &IdNameMaps) -> Tags {
         let mut tags_map = HashMap::new();
         for typed_tags in proto_tags.tags.iter() {
             let (type_name, map) = match &typed_tags.tag_type {
-                TagType::Block => ("minecraft:block", &*BLOCK_MAP),
-                TagType::Item => ("minecraft:item", &*ITEM_MAP),
-                TagType::Fluid => ("minecraft:fluid", &*FLUID_MAP),
-                TagType::EntityType => ("minecraft:entity_type", &*ENTITY_MAP),
-                TagType::GameEvent => ("minecraft:game_event", &*GAME_EVENT_MAP),
+                TagType::Block => ("minecraft:block", &maps.blocks),
+                TagType::Item => ("minecraft:item", &maps.items),
+                TagType::Fluid => ("minecraft:fluid", &maps.fluids),
+                TagType::EntityType => ("minecraft:entity_type", &maps.entities),
+                TagType::GameEvent => ("minecraft:game_event", &maps.game_events),
             };
             tags_map.insert(type_name.into(), proto_tags_to_tags(&typed_tags.tags, map));
         }
@@ -96,18 +211,16 @@ impl From<&PlayTagsSpec> for Tags {
             tags: tags_map,
         }
     }
-}
-
-impl From<&Tags> for PlayTagsSpec {
-    fn from(tags: &Tags) -> PlayTagsSpec {
+    /// Converts these tags to their protocol form using the given id to name maps
+    pub fn to_proto(&self, maps: &IdNameMaps) -> PlayTagsSpec {
         let mut typed_tags = vec![];
-        for (name, tag_list) in tags.tags.iter() {
+        for (name, tag_list) in self.tags.iter() {
             let (tag_type, map) = match name.as_str() {
-                "minecraft:block" => (TagType::Block, &*BLOCK_MAP),
-                "minecraft:item" => (TagType::Item, &*ITEM_MAP),
-                "minecraft:fluid" => (TagType::Fluid, &*FLUID_MAP),
-                "minecraft:entity_type" => (TagType::EntityType, &*ENTITY_MAP),
-                "minecraft:game_event" => (TagType::GameEvent, &*GAME_EVENT_MAP),
+                "minecraft:block" => (TagType::Block, &maps.blocks),
+                "minecraft:item" => (TagType::Item, &maps.items),
+                "minecraft:fluid" => (TagType::Fluid, &maps.fluids),
+                "minecraft:entity_type" => (TagType::EntityType, &maps.entities),
+                "minecraft:game_event" => (TagType::GameEvent, &maps.game_events),
                 _ => continue,
             };
             typed_tags.push(TypedTagList {
@@ -0,0 +1,86 @@
+use crate::protocol::current::{
+    proto::{
+        GameChangeReason,
+        PlayChangeGameStateSpec,
+    },
+    PacketLatest,
+};
+
+/// Difference in time of day, in ticks, below which the client isn't sent the new server's time
+/// on a switch. Servers send the time every second, so the time from the last update is up to
+/// this far behind anyway
+const TIME_TOLERANCE_TICKS: i64 = 20;
+
+/// The weather a server has shown its player
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Weather {
+    pub raining: bool,
+    pub rain_level: f32,
+    pub thunder_level: f32,
+}
+
+impl Weather {
+    /// The weather shown by a server's game state changes, which is clear if it never sent any
+    pub fn from_packets(packets: &[PacketLatest]) -> Self {
+        let mut weather = Weather::default();
+        for packet in packets.iter() {
+            if let PacketLatest::PlayChangeGameState(body) = packet {
+                match body.reason {
+                    GameChangeReason::BeginRaining => weather.raining = true,
+                    GameChangeReason::EndRaining => weather.raining = false,
+                    GameChangeReason::RainLevelChange(level) => weather.rain_level = level,
+                    GameChangeReason::ThunderLevelChange(level) => weather.thunder_level = level,
+                    _ => {}
+                }
+            }
+        }
+        weather
+    }
+}
+
+/// Which part of the weather a game state change sets, if any. Beginning and ending rain set the
+/// same part
+pub fn weather_slot(reason: &GameChangeReason) -> Option<u8> {
+    match reason {
+        GameChangeReason::BeginRaining | GameChangeReason::EndRaining => Some(0),
+        GameChangeReason::RainLevelChange(_) => Some(1),
+        GameChangeReason::ThunderLevelChange(_) => Some(2),
+        _ => None,
+    }
+}
+
+/// The game state changes that show the client the current weather in place of the previous
+pub fn weather_changes(previous: Weather, current: Weather) -> Vec<PacketLatest> {
+    let mut reasons = vec![];
+    if current.raining != previous.raining {
+        reasons.push(if current.raining {
+            GameChangeReason::BeginRaining
+        } else {
+            GameChangeReason::EndRaining
+        });
+    }
+    if current.rain_level != previous.rain_level {
+        reasons.push(GameChangeReason::RainLevelChange(current.rain_level));
+    }
+    if current.thunder_level != previous.thunder_level {
+        reasons.push(GameChangeReason::ThunderLevelChange(current.thunder_level));
+    }
+    reasons
+        .into_iter()
+        .map(|reason| {
+            PacketLatest::PlayChangeGameState(PlayChangeGameStateSpec {
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// Whether a time update is far enough from the previous server's last one to be resent
+pub fn time_differs(previous: Option<&PacketLatest>, current: &PacketLatest) -> bool {
+    match (previous, current) {
+        (Some(PacketLatest::PlayTimeUpdate(previous)), PacketLatest::PlayTimeUpdate(current)) => {
+            (current.time_of_day - previous.time_of_day).abs() > TIME_TOLERANCE_TICKS
+        }
+        _ => true,
+    }
+}
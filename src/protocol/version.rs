@@ -0,0 +1,76 @@
+/// Protocol versions the proxy is able to speak with clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtocolVersion {
+    V756,
+}
+
+impl ProtocolVersion {
+    /// All supported protocol versions, from oldest to newest
+    pub const SUPPORTED: &'static [ProtocolVersion] = &[ProtocolVersion::V756];
+
+    /// Gets the supported protocol version for a handshake version number, if there is one
+    pub fn from_number(number: i32) -> Option<Self> {
+        Self::SUPPORTED
+            .iter()
+            .find(|version| version.number() == number)
+            .copied()
+    }
+    /// Gets the handshake version number of this protocol version
+    pub fn number(&self) -> i32 {
+        match self {
+            ProtocolVersion::V756 => 756,
+        }
+    }
+    /// Gets the human readable game version of this protocol version
+    pub fn name(&self) -> &'static str {
+        version_name(self.number()).unwrap_or("unknown")
+    }
+}
+
+/// Handshake version numbers and the game versions they correspond to. Keep this up to date
+/// with the variants of `ProtocolVersion`.
+pub const PROTOCOL_VERSION_NAMES: &[(i32, &str)] = &[
+    (47, "1.8.9"),
+    (340, "1.12.2"),
+    (404, "1.13.2"),
+    (498, "1.14.4"),
+    (578, "1.15.2"),
+    (735, "1.16"),
+    (736, "1.16.1"),
+    (751, "1.16.2"),
+    (753, "1.16.3"),
+    (754, "1.16.5"),
+    (755, "1.17"),
+    (756, "1.17.1"),
+    (757, "1.18.1"),
+    (758, "1.18.2"),
+    (759, "1.19"),
+    (760, "1.19.2"),
+];
+
+/// Gets the game version name for a handshake version number
+pub fn version_name(number: i32) -> Option<&'static str> {
+    PROTOCOL_VERSION_NAMES
+        .iter()
+        .find(|(num, _)| *num == number)
+        .map(|(_, name)| *name)
+}
+
+/// Describes a handshake version number for use in messages to players
+pub fn describe_version(number: i32) -> String {
+    match version_name(number) {
+        Some(name) => name.into(),
+        None => format!("an unknown version (protocol {})", number),
+    }
+}
+
+/// Gets a description of the range of supported game versions, ex. "1.16.3–1.17.1"
+pub fn supported_versions_string() -> String {
+    let oldest = ProtocolVersion::SUPPORTED.first().unwrap();
+    let newest = ProtocolVersion::SUPPORTED.last().unwrap();
+    if oldest == newest {
+        newest.name().into()
+    } else {
+        format!("{}–{}", oldest.name(), newest.name())
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use anyhow::Context;
+use blocking::unblock;
+use smol::channel::{
+    self,
+    Sender,
+};
+
+use crate::{
+    protocol::current::protocol::{
+        PacketDirection,
+        RawPacket,
+    },
+    proxy::client::SplinterClient,
+};
+
+/// Directory capture files are written to
+const CAPTURE_DIRECTORY: &str = "captures";
+
+/// Bytes in a record before the packet data, not counting the length prefix
+const RECORD_HEADER_SIZE: usize = 1 + 8 + 8 + 4;
+
+/// Raw packets to and from a player being written to a file, for debugging the protocol offline
+///
+/// Every packet is written as a record of big endian fields:
+/// - `u32` length of the rest of the record
+/// - `u8` direction, 0 for serverbound and 1 for clientbound
+/// - `u64` milliseconds since the unix epoch when the proxy read the packet
+/// - `u64` id of the server the packet is from, or the player's active server for serverbound
+///   packets
+/// - `i32` packet id
+/// - the packet data, as it was read, without the packet id
+///
+/// Records are only encoded where packets are read, and are sent to a task that writes them to
+/// the file off of the executor. Records waiting to be written count towards the size limit, so
+/// they can't pile up past it. Dropping the capture lets that task write out what's left and
+/// close the file.
+pub struct PacketCapture {
+    records: Sender<Vec<u8>>,
+    pub path: String,
+    /// Bytes sent to be written to the file so far
+    pub written: u64,
+    max_bytes: u64,
+}
+impl PacketCapture {
+    /// Creates a file in the capture directory and starts the task writing to it
+    pub async fn create(file_name: &str, max_bytes: u64) -> anyhow::Result<Self> {
+        let path = Path::new(CAPTURE_DIRECTORY)
+            .join(sanitize_file_name(file_name)?)
+            .to_string_lossy()
+            .into_owned();
+        let file = {
+            let path = path.clone();
+            unblock(move || {
+                fs::create_dir_all(CAPTURE_DIRECTORY)?;
+                File::create(&path)
+            })
+            .await
+            .with_context(|| format!("Failed to create capture file \"{}\"", &path))?
+        };
+        let (records, receiver) = channel::unbounded::<Vec<u8>>();
+        let task_path = path.clone();
+        smol::spawn(async move {
+            let mut writer = BufWriter::new(file);
+            while let Ok(record) = receiver.recv().await {
+                let mut batch = vec![record];
+                while let Ok(record) = receiver.try_recv() {
+                    batch.push(record);
+                }
+                writer = match unblock(move || {
+                    for record in batch.iter() {
+                        writer.write_all(record)?;
+                    }
+                    Ok::<_, io::Error>(writer)
+                })
+                .await
+                {
+                    Ok(writer) => writer,
+                    Err(e) => {
+                        error!(
+                            "Failed to write capture to \"{}\", stopping it: {}",
+                            &task_path, e
+                        );
+                        // the client stops capturing once it can't send more records
+                        receiver.close();
+                        return;
+                    }
+                };
+            }
+            if let Err(e) = unblock(move || writer.flush()).await {
+                error!("Failed to finish capture to \"{}\": {}", &task_path, e);
+            }
+        })
+        .detach();
+        Ok(Self {
+            records,
+            path,
+            written: 0,
+            max_bytes,
+        })
+    }
+    /// Sends a record of a packet to be written. Returns false without sending it if it would
+    /// take the file over the size limit or the file can't be written, after which the capture
+    /// should be stopped
+    pub fn record<'a>(
+        &mut self,
+        direction: &PacketDirection,
+        server_id: u64,
+        packet: &impl RawPacket<'a>,
+    ) -> bool {
+        let data = packet.data();
+        let length = RECORD_HEADER_SIZE + data.len();
+        if self.written + 4 + length as u64 > self.max_bytes {
+            return false;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or(0);
+        let mut record = Vec::with_capacity(4 + length);
+        record.extend_from_slice(&(length as u32).to_be_bytes());
+        record.push(match direction {
+            PacketDirection::ServerBound => 0,
+            PacketDirection::ClientBound => 1,
+        });
+        record.extend_from_slice(&timestamp.to_be_bytes());
+        record.extend_from_slice(&server_id.to_be_bytes());
+        record.extend_from_slice(&packet.id().id.to_be_bytes());
+        record.extend_from_slice(data);
+        if self.records.try_send(record).is_err() {
+            return false;
+        }
+        self.written += 4 + length as u64;
+        true
+    }
+}
+
+/// Checks a capture file name given in a command, replacing anything but letters, digits, `_`,
+/// `-` and `.` with `_` so that captures can't be written outside of the capture directory
+fn sanitize_file_name(file_name: &str) -> anyhow::Result<String> {
+    let sanitized = file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if sanitized.is_empty() || sanitized.starts_with('.') {
+        bail!("\"{}\" can't be used as a capture file name", file_name);
+    }
+    Ok(sanitized)
+}
+
+impl SplinterClient {
+    /// Starts writing the client's packets to a file in the capture directory, replacing any
+    /// capture already going. Returns the path of the file
+    pub async fn start_capture(&self, file_name: &str) -> anyhow::Result<String> {
+        let capture = PacketCapture::create(file_name, self.proxy.config.capture_max_bytes).await?;
+        let path = capture.path.clone();
+        // the previous capture finishes once it's dropped
+        self.capture.lock().await.replace(capture);
+        Ok(path)
+    }
+    /// Stops writing the client's packets to a file, returning the finished capture's path and
+    /// size if there was one
+    pub async fn stop_capture(&self) -> Option<(String, u64)> {
+        let capture = self.capture.lock().await.take()?;
+        Some((capture.path, capture.written))
+    }
+    /// Records a packet read to or from the client if its packets are being captured. The capture
+    /// is stopped if it fails or reaches the size limit
+    pub async fn capture_packet<'a>(
+        &self,
+        direction: &PacketDirection,
+        server_id: u64,
+        packet: &impl RawPacket<'a>,
+    ) {
+        let capture_lock = &mut *self.capture.lock().await;
+        let capture = match capture_lock.as_mut() {
+            Some(capture) => capture,
+            None => return,
+        };
+        if capture.record(direction, server_id, packet) {
+            return;
+        }
+        warn!(
+            "Stopping capture of \"{}\" to \"{}\" after {} bytes",
+            &self.name, &capture.path, capture.written
+        );
+        capture_lock.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_names_stay_in_directory() {
+        assert_eq!(sanitize_file_name("join.bin").unwrap(), "join.bin");
+        assert_eq!(sanitize_file_name("a/../b").unwrap(), "a_.._b");
+        assert!(sanitize_file_name("../../etc/passwd").is_err());
+        assert!(sanitize_file_name("..").is_err());
+    }
+}
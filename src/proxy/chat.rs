@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
 
 use mcproto_rs::{
     protocol::PacketDirection,
@@ -9,10 +12,12 @@ use mcproto_rs::{
         TextComponent,
     },
 };
+use smol::Timer;
 
 use crate::{
     proxy::{
         client::SplinterClient,
+        config::BroadcastFormat,
         SplinterProxy,
     },
     systems::commands::CommandSender,
@@ -36,6 +41,51 @@ impl ToChat for String {
         Chat::from_text(self.as_str())
     }
 }
+/// Fills in the `{name}` arguments of a message template
+pub fn fill_template(template: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(template.to_owned(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+/// A system message shown in the locale of the player it's sent to. `fallback` is used if no
+/// template is configured for the key, and is what the console gets
+pub struct Localized<'a> {
+    pub key: &'a str,
+    pub fallback: &'a str,
+    pub args: &'a [(&'a str, &'a str)],
+}
+/// A response to a command sender, which may differ with the player it's sent to
+pub trait ToResponse {
+    fn to_player_chat(&self, client: &SplinterClient) -> Chat;
+    fn to_console_text(&self) -> String;
+}
+impl<T: ToChat + ToString> ToResponse for T {
+    fn to_player_chat(&self, _client: &SplinterClient) -> Chat {
+        self.to_chat()
+    }
+    fn to_console_text(&self) -> String {
+        self.to_string()
+    }
+}
+impl ToResponse for Localized<'_> {
+    fn to_player_chat(&self, client: &SplinterClient) -> Chat {
+        client.localize(self.key, self.fallback, self.args)
+    }
+    fn to_console_text(&self) -> String {
+        fill_template(self.fallback, self.args)
+    }
+}
+impl SplinterClient {
+    /// Gets a system message in the client's locale. `fallback` is used if no template is
+    /// configured for the message key in the client's locale or the default locale
+    pub fn localize(&self, key: &str, fallback: &str, args: &[(&str, &str)]) -> Chat {
+        let locale = self.settings.load().locale.clone();
+        match self.proxy.config.message_template(&locale, key) {
+            Some(template) => Chat::from_traditional(&fill_template(template, args), true),
+            None => Chat::from_text(&fill_template(fallback, args)),
+        }
+    }
+}
 pub fn format_chat_message_string(
     sender: &CommandSender,
     message: impl ToChat + ToString,
@@ -73,6 +123,34 @@ pub fn format_chat_message(sender: &CommandSender, message: impl ToChat + ToStri
         },
     })
 }
+/// Formats a message sent with the `say` or `broadcast` commands using the configured template
+/// for the kind of sender
+pub fn format_broadcast(format: &BroadcastFormat, sender: &CommandSender, message: &str) -> Chat {
+    let template = match sender {
+        CommandSender::Console => &format.console,
+        CommandSender::Player(_) => &format.player,
+    };
+    // the sender is filled in first so that a message containing "{sender}" is left alone
+    let text = template
+        .replace("{sender}", &sender.name())
+        .replace("{message}", message);
+    Chat::from_traditional(&text, true)
+}
+
+/// Sends the configured welcome messages to a client after the configured delay
+pub async fn send_welcome_messages(client: Arc<SplinterClient>) {
+    let config = &client.proxy.config;
+    Timer::after(Duration::from_secs_f64(config.welcome_delay_secs)).await;
+    for line in config.welcome_messages.iter() {
+        if let Err(e) = client
+            .send_message(Chat::from_traditional(line, true), &CommandSender::Console)
+            .await
+        {
+            error!("Failed to send welcome message to \"{}\": {}", &client.name, e);
+            break;
+        }
+    }
+}
 
 pub async fn receive_chat_message(
     proxy: &Arc<SplinterProxy>,
@@ -90,7 +168,23 @@ pub async fn receive_chat_message(
     let msg_string = format_chat_message_string(&cmd_sender, msg);
     info!("{}", msg_string);
     if let Some('/') = msg.chars().next() {
-        if let Err(e) = client.relay_message(msg).await {
+        if client.is_reconnecting() {
+            if !client.buffer_command(msg).await {
+                if let Err(e) = client
+                    .send_message(
+                        client.localize(
+                            "command_dropped",
+                            "Too many commands sent while reconnecting, dropping this one",
+                            &[],
+                        ),
+                        &CommandSender::Console,
+                    )
+                    .await
+                {
+                    error!("Failed to send message to \"{}\": {}", &client.name, e);
+                }
+            }
+        } else if let Err(e) = client.relay_message(msg).await {
             error!(
                 "Failed to relay chat message from \"{}\" to server \"{}\": {}",
                 &client.name,
@@ -99,6 +193,7 @@ pub async fn receive_chat_message(
             );
         }
     } else {
+        proxy.chat_log.lock().await.push(&client.name, msg);
         let msg_chat = format_chat_message(&cmd_sender, msg);
         broadcast_message(proxy, &cmd_sender, msg_chat).await;
     }
@@ -0,0 +1,77 @@
+use std::{
+    collections::VecDeque,
+    mem,
+};
+
+use chrono::{
+    DateTime,
+    Local,
+};
+
+/// A chat message players sent, as recorded in the chat log
+pub struct ChatLogEntry {
+    pub time: DateTime<Local>,
+    pub sender: String,
+    pub message: String,
+}
+impl ChatLogEntry {
+    /// Approximate memory the entry takes up, in bytes
+    fn size(&self) -> usize {
+        mem::size_of::<Self>() + self.sender.capacity() + self.message.capacity()
+    }
+}
+
+/// The most recent chat messages, bounded both by number of messages and by approximate memory
+///
+/// The oldest messages are evicted once either limit is reached, so that a burst of long
+/// messages can't grow the log without bound.
+pub struct ChatLog {
+    entries: VecDeque<ChatLogEntry>,
+    /// Approximate memory all of the entries take up, in bytes
+    size: usize,
+    max_messages: usize,
+    max_bytes: usize,
+}
+impl ChatLog {
+    pub fn new(max_messages: usize, max_bytes: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_messages,
+            max_bytes,
+        }
+    }
+    /// Records a message, evicting the oldest messages to stay within the limits. A message too
+    /// large to fit on its own is not recorded
+    pub fn push(&mut self, sender: &str, message: &str) {
+        let entry = ChatLogEntry {
+            time: Local::now(),
+            sender: sender.to_owned(),
+            message: message.to_owned(),
+        };
+        let entry_size = entry.size();
+        if self.max_messages == 0 || entry_size > self.max_bytes {
+            return;
+        }
+        while self.entries.len() >= self.max_messages || self.size + entry_size > self.max_bytes {
+            // something is always in the log at this point, as the entry fits in an empty one
+            let evicted = self.entries.pop_front().unwrap();
+            self.size -= evicted.size();
+        }
+        self.size += entry_size;
+        self.entries.push_back(entry);
+    }
+    /// The most recent messages, oldest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &ChatLogEntry> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(count))
+    }
+    pub fn message_count(&self) -> usize {
+        self.entries.len()
+    }
+    /// Approximate memory the log takes up, in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
@@ -7,6 +7,7 @@ use std::{
         atomic::{AtomicBool, AtomicI8, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
@@ -14,8 +15,13 @@ use arc_swap::ArcSwap;
 use async_compat::CompatExt;
 use async_dup::Arc as AsyncArc;
 use craftio_rs::{CraftAsyncReader, CraftAsyncWriter, CraftConnection, CraftIo};
+use futures_lite::future;
 use smallvec::SmallVec;
-use smol::{lock::Mutex, Async};
+use smol::{
+    channel::{self, Sender},
+    lock::Mutex,
+    Async, Timer,
+};
 
 use crate::{
     protocol::{
@@ -23,30 +29,62 @@ use crate::{
         current::{
             proto::{
                 ClientStatusAction, PlayClientPlayerPositionAndRotationSpec,
-                PlayClientPluginMessageSpec,
+                PlayClientPluginMessageSpec, PlayDestroyEntitiesSpec, ResourcePackStatus,
             },
             protocol::{PacketDirection, State},
-            types::Vec3,
+            types::{IntPosition, VarInt, Vec3},
             uuid::UUID4,
             PacketLatest, RawPacketLatest,
         },
-        v_cur, AsyncCraftWriter,
+        events::{LazyDeserializedPacket, OwnedPacket},
+        v_cur::{self, send_position_set},
+        AsyncCraftWriter,
+    },
+    proxy::{
+        capture::PacketCapture, mapping, server::SplinterServerConnection, ConnectionGuard,
+        SplinterProxy,
+    },
+    systems::{
+        commands::CommandSender,
+        keepalive::{watch_dummy, Clock, SystemClock},
     },
-    proxy::{mapping, server::SplinterServerConnection, SplinterProxy},
-    systems::keepalive::{self, watch_dummy},
 };
 
+/// How long to wait for a prewarmed dummy to connect before giving up on it
+const PREWARM_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct ChunkLoadData {
     pub received_chunkdata: bool,
     pub received_updatelight: bool,
     pub refcount: usize,
 }
 
+/// An entity a client's player is riding on a server, with ids from that server
+pub struct Mount {
+    /// Id of the server the vehicle is on
+    pub server_id: u64,
+    pub vehicle: i32,
+    /// All of the vehicle's passengers, including the client's player
+    pub passengers: Vec<i32>,
+}
+
+/// A client being muted
+pub struct Mute {
+    /// When the mute ends, or never if unset
+    pub expires: Option<Instant>,
+}
+
 pub struct SplinterClient {
     pub name: String,
-    pub writer: Mutex<AsyncCraftWriter>,
+    /// Packets waiting to be written to the client
+    pub outgoing: Sender<OwnedPacket>,
+    /// Stream the client is connected by, for disconnecting it when it can't keep up
+    pub stream: AsyncArc<Async<TcpStream>>,
     pub alive: AtomicBool,
     pub uuid: UUID4,
+    /// Entity id the client knows its own player by. This stays the same across servers, with
+    /// the mapping changed to point it at the player on the active server
+    pub proxy_eid: i32,
     pub settings: ArcSwap<ClientSettings>,
     pub active_server: ArcSwap<SplinterServerConnection>,
     pub dummy_servers: ArcSwap<Vec<(u64, Arc<SplinterServerConnection>)>>,
@@ -57,30 +95,106 @@ pub struct SplinterClient {
     pub known_chunks: Mutex<HashMap<(i32, i32), ChunkLoadData>>,
     pub known_eids: Mutex<HashSet<i32>>,
     pub position: ArcSwap<Vec3<f64>>,
+    /// Brand the client identified itself with (ex. "vanilla", "fabric"), or "unknown"
+    pub brand: ArcSwap<String>,
+    /// Index of the zone the client is currently in, if any
+    pub current_zone: Mutex<Option<usize>>,
+    /// Protocol version the client connected with
+    pub protocol: i32,
+    /// World the client is currently in (ex. "minecraft:overworld")
+    pub dimension: ArcSwap<String>,
+    /// Whether the client changed dimension and its position has not been updated since
+    pub awaiting_position: AtomicBool,
+    /// When the client last changed dimension
+    pub dimension_changed_at: Mutex<Option<Instant>>,
+    /// Whether the client died and has not respawned yet
+    pub dead: AtomicBool,
+    /// Whether the client is waiting for its active server to come back
+    pub reconnecting: AtomicBool,
+    /// Commands sent by the client while reconnecting
+    pub buffered_commands: Mutex<Vec<String>>,
+    /// Id of the server that last sent the client a resource pack, until the client is done
+    /// responding to it
+    pub resource_pack_server: Mutex<Option<u64>>,
+    /// The last status the client sent for a resource pack, if it has been sent one
+    pub resource_pack_status: Mutex<Option<ResourcePackStatus>>,
+    /// Position of the block the client is in the middle of breaking, if any
+    pub digging_at: Mutex<Option<IntPosition>>,
+    /// The entities the client's player is riding, at most one for each server it's connected to
+    pub mounts: Mutex<Vec<Mount>>,
+    /// Whether the client is muted
+    pub mute: Mutex<Option<Mute>>,
+    /// Pings the proxy sent the client that haven't been answered, by id, with what is waiting
+    /// for the answer
+    pub pending_pings: Mutex<HashMap<i32, Sender<()>>>,
+    /// Held while swapping to, adding or replacing a connection, so that concurrent swaps for the
+    /// client (like a command and the zoner firing together) can't interleave and corrupt the
+    /// mapping
+    pub swap_lock: Mutex<()>,
+    /// Whether dummies for adjacent servers are being connected in the background
+    pub prewarming: AtomicBool,
+    /// Held for the whole of a soft reconnect, so that the server relay can wait for the
+    /// replacement connection when the server kicks the one being replaced
+    pub reconnect_lock: Mutex<()>,
+    /// File the client's packets are being written to, if they're being captured
+    pub capture: Mutex<Option<PacketCapture>>,
 }
 impl SplinterClient {
     pub fn new(
         proxy: Arc<SplinterProxy>,
         name: String,
         writer: AsyncCraftWriter,
+        stream: AsyncArc<Async<TcpStream>>,
         active_server: Arc<SplinterServerConnection>,
         position: Vec3<f64>,
+        protocol: i32,
+        proxy_eid: i32,
     ) -> Self {
         let uuid = mapping::uuid_from_name(&name);
+        let (outgoing, queue) = channel::bounded(proxy.config.client_queue_size.max(1));
+        smol::spawn(v_cur::write_queued_packets(
+            Arc::clone(&proxy),
+            name.clone(),
+            writer,
+            AsyncArc::clone(&stream),
+            queue,
+        ))
+        .detach();
         Self {
             name,
-            writer: Mutex::new(writer),
+            outgoing,
+            stream,
             alive: AtomicBool::new(true),
             uuid,
+            proxy_eid,
             settings: ArcSwap::new(Arc::new(ClientSettings::default())),
             active_server: ArcSwap::new(active_server),
             dummy_servers: ArcSwap::new(Arc::new(Vec::new())),
             proxy,
-            last_keep_alive: Mutex::new(keepalive::unix_time_millis()),
+            last_keep_alive: Mutex::new(SystemClock.unix_time_millis()),
             held_slot: AtomicI8::new(0),
             known_chunks: Mutex::new(HashMap::new()),
             known_eids: Mutex::new(HashSet::new()),
             position: ArcSwap::new(Arc::new(position)),
+            brand: ArcSwap::new(Arc::new("unknown".into())),
+            current_zone: Mutex::new(None),
+            protocol,
+            dimension: ArcSwap::new(Arc::new(String::new())),
+            awaiting_position: AtomicBool::new(false),
+            dimension_changed_at: Mutex::new(None),
+            dead: AtomicBool::new(false),
+            reconnecting: AtomicBool::new(false),
+            buffered_commands: Mutex::new(Vec::new()),
+            resource_pack_server: Mutex::new(None),
+            resource_pack_status: Mutex::new(None),
+            digging_at: Mutex::new(None),
+            mounts: Mutex::new(Vec::new()),
+            mute: Mutex::new(None),
+            pending_pings: Mutex::new(HashMap::new()),
+            swap_lock: Mutex::new(()),
+            prewarming: AtomicBool::new(false),
+            reconnect_lock: Mutex::new(()),
+            capture: Mutex::new(None),
         }
     }
     pub async fn set_alive(&self, value: bool) {
@@ -89,6 +203,48 @@ impl SplinterClient {
     pub fn server_id(&self) -> u64 {
         self.active_server.load().server.id
     }
+    /// Records the world the client respawned into, suppressing automatic server switching if it
+    /// is a different dimension, since the position we have is from the previous dimension
+    pub async fn respawned_into(&self, world_name: &str) {
+        if **self.dimension.load() == world_name {
+            return;
+        }
+        debug!("{} changed dimension to {}", &self.name, world_name);
+        self.dimension.store(Arc::new(world_name.to_owned()));
+        self.awaiting_position.store(true, Ordering::Relaxed);
+        *self.dimension_changed_at.lock().await = Some(Instant::now());
+    }
+    /// Records a new position for the client
+    pub fn update_position(&self, position: Vec3<f64>) {
+        self.position.store(Arc::new(position));
+        self.awaiting_position.store(false, Ordering::Relaxed);
+    }
+    /// Whether automatic server switching should be skipped, either because the client is dead,
+    /// or because it recently changed dimension and its position hasn't been confirmed yet. The
+    /// latter lasts at most `window`.
+    pub async fn zoning_suppressed(&self, window: Duration) -> bool {
+        if self.is_dead() {
+            return true;
+        }
+        if !self.awaiting_position.load(Ordering::Relaxed) {
+            return false;
+        }
+        match *self.dimension_changed_at.lock().await {
+            Some(changed_at) => changed_at.elapsed() < window,
+            None => false,
+        }
+    }
+    /// Records that the client has just responded to a keep alive
+    pub async fn record_keep_alive(&self, clock: &impl Clock) {
+        *self.last_keep_alive.lock().await = clock.unix_time_millis();
+    }
+    /// Whether the client has gone longer than the timeout without responding to a keep alive
+    pub async fn keep_alive_timed_out(&self, clock: &impl Clock, timeout_millis: u128) -> bool {
+        clock
+            .unix_time_millis()
+            .saturating_sub(*self.last_keep_alive.lock().await)
+            > timeout_millis
+    }
     pub async fn disconnect_dummy(&self, target_id: u64) -> anyhow::Result<()> {
         debug!("disconecting {}-{}", &self.name, target_id);
         let dummy_servers = &**self.dummy_servers.load();
@@ -118,57 +274,193 @@ impl SplinterClient {
         });
         return res;
     }
+    /// Adds a dummy to the client's dummy servers, evicting the least recently used dummies if
+    /// this would exceed the configured maximum. The dummy being added and any connection to the
+    /// active server are never evicted, so a swap keeps both its target, which is active by the
+    /// time the previously active connection is added, and the previously active connection. If
+    /// nothing else can be evicted, the maximum is exceeded until the next dummy is added.
     pub fn add_dummy(&self, dummy: &Arc<SplinterServerConnection>) {
+        let max_dummies = self.proxy.config.max_dummy_connections;
+        let active_id = self.server_id();
+        let mut evicted = vec![];
         self.dummy_servers.rcu(|servers| {
             let mut new_servers = (**servers).clone();
+            evicted.clear();
+            if let Some(max_dummies) = max_dummies {
+                while new_servers.len() >= max_dummies {
+                    // dummy servers are kept in order of least to most recently used
+                    let ind = match new_servers.iter().position(|(id, _)| *id != active_id) {
+                        Some(ind) => ind,
+                        None => break,
+                    };
+                    evicted.push(new_servers.remove(ind).1);
+                }
+            }
             new_servers.push((dummy.server.id, Arc::clone(dummy)));
             Arc::new(new_servers)
         });
+        for evicted_dummy in evicted {
+            debug!(
+                "evicting dummy {}-{} to stay within the dummy connection limit",
+                &self.name, evicted_dummy.server.id
+            );
+            evicted_dummy.alive.store(false, Ordering::Relaxed);
+        }
+    }
+    /// Marks a dummy as the most recently used, so that it is the last to be evicted
+    pub fn touch_dummy(&self, target_id: u64) {
+        self.dummy_servers.rcu(|servers| {
+            let mut new_servers = (**servers).clone();
+            if let Some(ind) = new_servers.iter().position(|v| v.0 == target_id) {
+                let entry = new_servers.remove(ind);
+                new_servers.push(entry);
+            }
+            Arc::new(new_servers)
+        });
     }
     pub async fn swap_dummy(self: &Arc<SplinterClient>, target_id: u64) -> anyhow::Result<()> {
+        let _swap_guard = self.swap_lock.lock().await;
+        // a swap that held the lock before may have already made the target active
+        if self.server_id() == target_id {
+            return Ok(());
+        }
         debug!("swapping to {}-{}", &self.name, target_id);
         // grab the dummy from the target id
         let dummy = self.grab_dummy(target_id)?;
+        // make sure the dummy isn't stuck dead when the client isn't
+        if dummy.is_awaiting_respawn() && !self.is_dead() {
+            debug!("respawning {}-{} before swapping to it", &self.name, target_id);
+            if let Err(e) = dummy.respawn().await {
+                self.add_dummy(&dummy);
+                return Err(e.context(format!(
+                    "Failed to respawn {}-{} before swapping",
+                    &self.name, target_id
+                )));
+            }
+        }
         // remember the dummy player's eid
         let dummy_eid = dummy.eid;
         // swap the dummy connection with the active connection
         let previously_active_conn = self.active_server.swap(dummy);
-        // get the ampping tables
-        let mapping = &mut *self.proxy.mapping.lock().await;
-        // find the corresponding proxy-side ids
-        let proxy_eid = *mapping
-            .eids
-            .get_by_right(&(previously_active_conn.server.id, previously_active_conn.eid))
-            .unwrap();
-        // replace what the proxy side ids map to to the now active previously dummy eid
-        mapping.eids.insert(proxy_eid, (target_id, dummy_eid));
+        // the new server sets up its own scoreboard
+        self.clear_displayed_objectives(&previously_active_conn).await;
+        let proxy_eid = self.proxy_eid;
+        let active_conn = self.active_server.load_full();
+        let mount_packets = {
+            // get the ampping tables
+            let mapping = &mut *self.proxy.mapping.lock().await;
+            // point the client's own eid at the now active previously dummy eid, so that packets
+            // about the client's player, like status effects, keep reaching it. This doesn't rely
+            // on the previous mapping, which may have been lost
+            mapping.remap_eid(proxy_eid, target_id, dummy_eid);
+            // the vehicle the client was riding stays behind on the previous server
+            self.mount_swap_packets(&previously_active_conn, &active_conn, mapping)
+                .await
+        };
+        self.write_mount_packets(mount_packets).await;
+        // the previous server would have acknowledged any block break in progress
+        self.reset_digging(&previously_active_conn, proxy_eid).await;
+        // servers can have different world borders, time, weather and experience
+        self.resend_resync_packets(&previously_active_conn, &active_conn).await;
+        self.resend_proxy_boss_bars().await;
         // put the previously active connection into the dummy connections
         self.add_dummy(&previously_active_conn);
         // watch the now dummy previously active connection
         watch_dummy(Arc::clone(self), previously_active_conn).await;
+        self.send_join_commands().await;
+        if self.proxy.config.brand_includes_server {
+            let brand = v_cur::proxy_brand(&self.proxy.config, target_id);
+            if let Err(e) = self
+                .write_packet(LazyDeserializedPacket::from_packet(v_cur::brand_packet(brand)))
+                .await
+            {
+                error!("Failed to resend brand to \"{}\": {:?}", &self.name, e);
+            }
+        }
+        if let Some(msg) = self.proxy.config.switch_messages.get(&target_id) {
+            // the swap already happened, so failing to show the message doesn't undo it
+            if let Err(e) = self.send_action_bar(msg.as_str()).await {
+                error!("Failed to send switch message to \"{}\": {:?}", &self.name, e);
+            }
+        }
         Ok(())
     }
     pub async fn connect_dummy(self: &Arc<SplinterClient>, target_id: u64) -> anyhow::Result<()> {
+        if self.is_connected_to(target_id) {
+            return Ok(());
+        }
         debug!("connecting {}-{}", &self.name, target_id);
-        let server = Arc::clone(self.proxy.servers.read().await.get(&target_id).unwrap());
-        let (server_reader, server_writer) = server
-            .connect()
+        // the swap lock isn't held while connecting, since that lasts until the server has
+        // placed the player in the world, and swaps shouldn't wait on it
+        let arc_conn = Arc::new(self.open_connection(target_id).await?);
+        self.add_connected_dummy(arc_conn).await;
+        Ok(())
+    }
+    /// Whether the client is connected to the server, either actively or through a dummy
+    pub fn is_connected_to(&self, target_id: u64) -> bool {
+        self.server_id() == target_id
+            || self.dummy_servers.load().iter().any(|(id, _)| *id == target_id)
+    }
+    /// Adds a newly opened connection as a dummy and watches it, unless another connect finished
+    /// first while it was being opened, in which case the connection is closed. Returns whether
+    /// it was added
+    pub async fn add_connected_dummy(
+        self: &Arc<SplinterClient>,
+        conn: Arc<SplinterServerConnection>,
+    ) -> bool {
+        let _swap_guard = self.swap_lock.lock().await;
+        if self.is_connected_to(conn.server.id) {
+            debug!("closing duplicate connection {}-{}", &self.name, conn.server.id);
+            conn.alive.store(false, Ordering::Relaxed);
+            return false;
+        }
+        self.add_dummy(&conn);
+        watch_dummy(Arc::clone(self), conn).await;
+        true
+    }
+    /// Moves the client off of a server that is going away, onto a server that owns where the
+    /// client is, or the default server if none does
+    pub async fn drain_server(self: &Arc<SplinterClient>, server_id: u64) -> anyhow::Result<()> {
+        let position = self.position.load_full();
+        let target_id = self
+            .proxy
+            .server_for_join(&self.dimension.load(), position.x, position.z)
             .await
-            .with_context(|| format!("Failed to connect dummy to server {}", target_id))?
-            .into_split();
-        let mut server_conn = SplinterServerConnection {
-            writer: Mutex::new(server_writer),
-            reader: Mutex::new(server_reader),
-            server: (*server).clone(),
-            alive: AtomicBool::new(true),
-            eid: -1,
-            uuid: UUID4::from(0u128),
-            known_chunks: Mutex::new(HashSet::new()),
-        };
+            .filter(|target_id| *target_id != server_id)
+            .ok_or_else(|| anyhow!("No other server to move \"{}\" to", &self.name))?;
+        debug!("draining {}-{} onto {}", &self.name, server_id, target_id);
+        self.connect_dummy(target_id).await?;
+        self.swap_dummy(target_id).await
+    }
+    /// Opens a new connection for this client to a server, logging in and waiting until the
+    /// server places the player in the world
+    pub async fn open_connection(
+        &self,
+        target_id: u64,
+    ) -> anyhow::Result<SplinterServerConnection> {
+        let server = self
+            .proxy
+            .servers
+            .read()
+            .await
+            .get(&target_id)
+            .map(Arc::clone)
+            .ok_or_else(|| anyhow!("No server with id {}", target_id))?;
+        if server.protocol != self.protocol {
+            bail!(
+                "Cannot connect \"{}\" on protocol {} to server {} on protocol {}",
+                &self.name,
+                self.protocol,
+                target_id,
+                server.protocol
+            );
+        }
+        let mut server_conn =
+            SplinterServerConnection::new((*server).clone(), server.connect().await?);
 
         // let mut player_position = None;
 
-        v_cur::send_handshake(&mut server_conn, &self.proxy).await?;
+        v_cur::send_handshake(&mut server_conn).await?;
         server_conn.writer.get_mut().set_state(State::Login);
         server_conn.reader.get_mut().set_state(State::Login);
         v_cur::send_login_start(&mut server_conn, &self.name).await?;
@@ -178,25 +470,32 @@ impl SplinterClient {
                 .get_mut()
                 .read_packet_async::<RawPacketLatest>()
                 .await?;
+            // the server sends some of the packets resent on switching before the connection is
+            // watched as a dummy
+            if let Some(packet) = &packet {
+                server_conn.track_resync(&self.proxy.resync_kinds, packet).await;
+            }
             match packet {
                 Some(PacketLatest::LoginEncryptionRequest(_)) => bail!(
                     "Failed to connect to server {} because it requested encryption",
                     target_id
                 ),
                 Some(PacketLatest::LoginSetCompression(body)) => {
-                    let threshold = if *body.threshold > 0 {
-                        Some(*body.threshold)
-                    } else {
-                        None
-                    };
+                    let (read_threshold, write_threshold) =
+                        server.compression_thresholds(*body.threshold)?;
                     server_conn
                         .writer
                         .get_mut()
-                        .set_compression_threshold(threshold);
+                        .set_compression_threshold(write_threshold);
                     server_conn
                         .reader
                         .get_mut()
-                        .set_compression_threshold(threshold);
+                        .set_compression_threshold(read_threshold);
+                }
+                Some(PacketLatest::LoginPluginRequest(body)) => {
+                    // there's no client on this connection to ask
+                    v_cur::send_login_plugin_response(&mut server_conn, body.message_id, None)
+                        .await?;
                 }
                 Some(PacketLatest::LoginSuccess(body)) => {
                     server_conn.uuid = body.uuid;
@@ -293,15 +592,205 @@ impl SplinterClient {
                 None => bail!("Connection attempt to server {} closed", target_id),
             }
         }
-        let arc_conn = Arc::new(server_conn);
-        self.add_dummy(&arc_conn);
-        watch_dummy(Arc::clone(self), arc_conn).await;
-        Ok(())
+        Ok(server_conn)
+    }
+    /// Whether the client is waiting for its active server to come back after it disconnected
+    pub fn is_reconnecting(&self) -> bool {
+        self.reconnecting.load(Ordering::Relaxed)
+    }
+    /// Holds on to a command the client sent while reconnecting, to be sent once the server is
+    /// back. Returns false if the buffer is full and the command was dropped.
+    pub async fn buffer_command(&self, cmd: &str) -> bool {
+        let mut buffer = self.buffered_commands.lock().await;
+        if buffer.len() >= self.proxy.config.reconnect_command_buffer_size {
+            return false;
+        }
+        buffer.push(cmd.to_owned());
+        true
+    }
+    /// Tries to reconnect the client to its active server after that connection closed, retrying
+    /// until the grace period runs out. Returns whether the client was reconnected.
+    pub async fn reconnect_active_server(
+        self: &Arc<SplinterClient>,
+        grace_period: Duration,
+    ) -> bool {
+        const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+        let server_id = self.server_id();
+        info!(
+            "Lost connection between \"{}\" and server {}, waiting up to {:?} for it to return",
+            &self.name, server_id, grace_period
+        );
+        self.reconnecting.store(true, Ordering::Relaxed);
+        if let Err(e) = self
+            .send_message(
+                self.localize(
+                    "reconnecting",
+                    "Lost connection to the server, reconnecting...",
+                    &[],
+                ),
+                &CommandSender::Console,
+            )
+            .await
+        {
+            error!("Failed to send reconnecting message to \"{}\": {:?}", &self.name, e);
+        }
+        let deadline = Instant::now() + grace_period;
+        let reconnected = loop {
+            if !self.alive.load(Ordering::Relaxed) {
+                break false;
+            }
+            // the server may have come back on a different version
+            if let Err(e) = self.proxy.detect_server_protocol(server_id).await {
+                debug!("failed to detect protocol of server {}: {:?}", server_id, e);
+            }
+            match self.open_connection(server_id).await {
+                Ok(conn) => match self.replace_active_server(conn).await {
+                    Ok(()) => break true,
+                    Err(e) => error!(
+                        "Failed to restore connection for \"{}\": {:?}",
+                        &self.name, e
+                    ),
+                },
+                Err(e) => debug!(
+                    "reconnect attempt for {}-{} failed: {:?}",
+                    &self.name, server_id, e
+                ),
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break false;
+            }
+            Timer::after(RETRY_INTERVAL.min(deadline - now)).await;
+        };
+        self.reconnecting.store(false, Ordering::Relaxed);
+        let commands = std::mem::take(&mut *self.buffered_commands.lock().await);
+        if reconnected {
+            info!("Reconnected \"{}\" to server {}", &self.name, server_id);
+            self.relay_commands(commands).await;
+        }
+        reconnected
+    }
+    /// Relays commands buffered while reconnecting to the active server
+    async fn relay_commands(&self, commands: Vec<String>) {
+        for cmd in commands.iter() {
+            if let Err(e) = self.relay_message(cmd).await {
+                error!("Failed to relay buffered command from \"{}\": {:?}", &self.name, e);
+            }
+        }
+    }
+    /// Sends the commands configured for the client's active server to it, as if the client had
+    /// sent them
+    pub async fn send_join_commands(&self) {
+        let server_id = self.server_id();
+        let commands = match self.proxy.config.join_commands.get(&server_id) {
+            Some(commands) => commands,
+            None => return,
+        };
+        for cmd in commands.iter() {
+            if let Err(e) = self.relay_message(&format!("/{}", cmd)).await {
+                // the connection is most likely gone, so the rest would fail too
+                error!(
+                    "Failed to send join command \"{}\" for \"{}\" to server {}: {:?}",
+                    cmd, &self.name, server_id, e
+                );
+                break;
+            }
+        }
+    }
+    /// Replaces the client's active connection with a new one to the same server without
+    /// disconnecting the client, so that the server sends the world and its entities again. For
+    /// fixing a client whose state no longer matches the server's
+    ///
+    /// The server kicks the previous connection when the new one logs in, which isn't passed on
+    /// to the client, and the server relay waits on `reconnect_lock` for the new connection
+    /// rather than treating the server as lost. Entities from the server are removed from the client first so that they
+    /// don't linger if the server doesn't send them again, and the chunk tracking is released so
+    /// that the chunks sent again reach the client.
+    pub async fn soft_reconnect(self: &Arc<SplinterClient>) -> anyhow::Result<()> {
+        let server_id = self.server_id();
+        let _reconnect_guard = self.reconnect_lock.lock().await;
+        self.reconnecting.store(true, Ordering::Relaxed);
+        let res = async {
+            let conn = self.open_connection(server_id).await?;
+            self.forget_server_entities(server_id).await?;
+            self.replace_active_server(conn).await
+        }
+        .await;
+        self.reconnecting.store(false, Ordering::Relaxed);
+        let commands = std::mem::take(&mut *self.buffered_commands.lock().await);
+        if res.is_ok() {
+            self.relay_commands(commands).await;
+        }
+        res
+    }
+    /// Removes the entities of a server from the client, except for the client's own player
+    async fn forget_server_entities(&self, server_id: u64) -> anyhow::Result<()> {
+        let entity_ids = {
+            let mapping = self.proxy.mapping.lock().await;
+            let known_eids = &mut *self.known_eids.lock().await;
+            let entity_ids = known_eids
+                .iter()
+                .filter(|eid| **eid != self.proxy_eid)
+                .filter(|eid| {
+                    matches!(mapping.eids.get_by_left(eid), Some((id, _)) if *id == server_id)
+                })
+                .copied()
+                .collect::<Vec<_>>();
+            for eid in entity_ids.iter() {
+                known_eids.remove(eid);
+            }
+            entity_ids
+        };
+        if entity_ids.is_empty() {
+            return Ok(());
+        }
+        self.write_packet(LazyDeserializedPacket::from_packet(
+            PacketLatest::PlayDestroyEntities(PlayDestroyEntitiesSpec {
+                entity_ids: entity_ids
+                    .into_iter()
+                    .map(VarInt::from)
+                    .collect::<Vec<_>>()
+                    .into(),
+            }),
+        ))
+        .await
+    }
+    /// Replaces the client's active connection with a newly opened one to the same server
+    async fn replace_active_server(&self, conn: SplinterServerConnection) -> anyhow::Result<()> {
+        // a swap in between would remap the wrong connection
+        let _swap_guard = self.swap_lock.lock().await;
+        let new_id = (conn.server.id, conn.eid);
+        let previous_conn = self.active_server.swap(Arc::new(conn));
+        // the new connection will send the chunks again
+        previous_conn.release_chunks(self).await;
+        self.proxy
+            .mapping
+            .lock()
+            .await
+            .eids
+            .insert(self.proxy_eid, new_id);
+        let pos = &**self.position.load();
+        send_position_set(
+            &mut *self.active_server.load().writer.lock().await,
+            pos.x,
+            pos.y,
+            pos.z,
+        )
+        .await
     }
     // if this fails, this probably isnt really recoverable without a lot of effort lol
+    /// Keeps dummies for the servers the client is touching, switching to one of them if the
+    /// active server isn't one, and disconnects the rest
+    ///
+    /// Dummies are also kept for the adjacent servers, if any are given, so that moving onto them
+    /// doesn't wait on a connection. Only as many as fit in the dummy connection limit alongside
+    /// the touching servers are kept, and servers that couldn't be reached recently are skipped
+    /// until their backoff is over. They're connected in the background after everything else,
+    /// so that neither this client nor the ones updated after it wait on their logins.
     pub async fn update_touching_servers(
         self: &Arc<SplinterClient>,
         servers: SmallVec<[u64; 2]>,
+        adjacent: Vec<u64>,
     ) -> anyhow::Result<()> {
         debug!(
             "touching servers: [{}]",
@@ -311,31 +800,97 @@ impl SplinterClient {
         );
         let active_id = self.active_server.load().server.id;
         let dummy_servers = &**self.dummy_servers.load();
-        for server_id in servers.iter() {
-            // if there is a server in the provided list that we are not connected to
-            if *server_id != active_id && !dummy_servers.iter().any(|(id, _)| *id == *server_id) {
+        // go in reverse so that the first server, which we may switch to, is the most recently
+        // used dummy and won't be evicted by the dummy connection limit
+        for server_id in servers.iter().rev() {
+            if *server_id == active_id {
+                continue;
+            }
+            if dummy_servers.iter().any(|(id, _)| *id == *server_id) {
+                self.touch_dummy(*server_id);
+            } else {
+                // if there is a server in the provided list that we are not connected to
                 self.connect_dummy(*server_id).await?;
             }
         }
         // if our active server is not in the list
         if !servers.iter().any(|id| *id == active_id) {
             // we need to switch servers!
-            // get the next available server from the provided list
-            let next_server_id = *servers.get(0).unwrap();
+            // get the least loaded server from the provided list
+            let next_server_id = self
+                .proxy
+                .least_loaded_server(&servers)
+                .await
+                .ok_or_else(|| anyhow!("No server to switch {} to", &self.name))?;
             self.swap_dummy(next_server_id).await?;
             // the active server will be removed in the next step
         }
+        let active_id = self.server_id();
+        let mut prewarmed = adjacent
+            .into_iter()
+            .filter(|id| *id != active_id && !servers.contains(id))
+            .filter(|id| self.is_connected_to(*id) || !self.proxy.prewarm_backing_off(*id))
+            .collect::<Vec<_>>();
+        if let Some(max_dummies) = self.proxy.config.max_dummy_connections {
+            let touching_dummies = servers.iter().filter(|id| **id != active_id).count();
+            prewarmed.truncate(max_dummies.saturating_sub(touching_dummies));
+        }
         // if there is a server not in the provided list that we are connected to
         let dummy_servers = &**self.dummy_servers.load(); // dummy server list may have changed, reload it
         for (dummy_id, _) in dummy_servers.iter() {
-            // if there is a dummy server that does not have a match in the provided list
-            if !servers.iter().any(|id| *id == *dummy_id) {
+            // if there is a dummy server that does not have a match in the provided lists
+            if !servers.iter().any(|id| *id == *dummy_id) && !prewarmed.contains(dummy_id) {
                 // we need to disconnect from it
                 self.disconnect_dummy(*dummy_id).await?;
             }
         }
+        self.prewarm(prewarmed);
         Ok(())
     }
+    /// Connects dummies to servers in the background, one after another, unless the client is
+    /// already doing so. Each connection gives up after `PREWARM_CONNECT_TIMEOUT`, and a server
+    /// that can't be connected to is recorded as unreachable so that it's backed off from
+    fn prewarm(self: &Arc<SplinterClient>, server_ids: Vec<u64>) {
+        if server_ids.iter().all(|id| self.is_connected_to(*id))
+            || self.prewarming.swap(true, Ordering::Relaxed)
+        {
+            return;
+        }
+        let client = Arc::clone(self);
+        smol::spawn(async move {
+            for server_id in server_ids {
+                if !client.alive.load(Ordering::Relaxed) {
+                    break;
+                }
+                if client.is_connected_to(server_id) {
+                    continue;
+                }
+                let connected = future::or(
+                    async { Some(client.connect_dummy(server_id).await) },
+                    async {
+                        Timer::after(PREWARM_CONNECT_TIMEOUT).await;
+                        None
+                    },
+                )
+                .await;
+                let reachable = match connected {
+                    Some(Ok(())) => true,
+                    // it's only a head start, so the switch will connect again if it comes to it
+                    Some(Err(e)) => {
+                        debug!("Failed to prewarm {}-{}: {:?}", &client.name, server_id, e);
+                        false
+                    }
+                    None => {
+                        debug!("Timed out prewarming {}-{}", &client.name, server_id);
+                        false
+                    }
+                };
+                client.proxy.record_reachability(server_id, reachable);
+            }
+            client.prewarming.store(false, Ordering::Relaxed);
+        })
+        .detach();
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
@@ -394,6 +949,7 @@ pub fn handle(
     stream: Async<TcpStream>,
     addr: SocketAddr,
     proxy: Arc<SplinterProxy>,
+    guard: ConnectionGuard,
 ) -> anyhow::Result<()> {
     let arc_stream = AsyncArc::new(stream);
     let (reader, writer) = (
@@ -403,10 +959,84 @@ pub fn handle(
     let conn = CraftConnection::from_async((reader, writer), PacketDirection::ServerBound);
     smol::spawn(async move {
         // wait for initial handshake
-        if let Err(e) = protocol::handle_handshake(conn, addr, proxy).await {
+        if let Err(e) = protocol::handle_handshake(conn, arc_stream, addr, proxy).await {
             error!("Failed to handle handshake: {:?}", e,);
         }
+        // the connection is closed, so it no longer counts towards the limit
+        drop(guard);
     })
     .detach();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use futures_lite::future;
+
+    use super::*;
+    use crate::proxy::testing::client_with_dummies;
+
+    /// Checks that the client has exactly one connection to each of the servers, and that its
+    /// own eid is mapped to its player on the active server
+    async fn assert_consistent(client: &SplinterClient, server_ids: &[u64]) {
+        let active = client.active_server.load();
+        let mut connected = client
+            .dummy_servers
+            .load()
+            .iter()
+            .map(|(id, _)| *id)
+            .chain(std::iter::once(active.server.id))
+            .collect::<Vec<_>>();
+        connected.sort_unstable();
+        assert_eq!(connected, server_ids);
+        assert_eq!(
+            client.proxy.mapping.lock().await.eids.get_by_left(&client.proxy_eid),
+            Some(&(active.server.id, active.eid))
+        );
+    }
+
+    #[test]
+    fn concurrent_swaps_stay_consistent() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1, 2, 3]).await;
+            let (first, second) = future::zip(client.swap_dummy(2), client.swap_dummy(3)).await;
+            first.unwrap();
+            second.unwrap();
+            assert_consistent(&client, &[1, 2, 3]).await;
+
+            // two swaps to the same server
+            let (first, second) = future::zip(client.swap_dummy(1), client.swap_dummy(1)).await;
+            first.unwrap();
+            second.unwrap();
+            assert_eq!(client.server_id(), 1);
+            assert_consistent(&client, &[1, 2, 3]).await;
+        });
+    }
+
+    #[test]
+    fn connect_finishing_during_swap_is_not_duplicated() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1, 2]).await;
+            let server = client.dummy_servers.load()[0].1.server.clone();
+            // a connection to server 2 opened while the existing one was being swapped to
+            let duplicate = Arc::new(SplinterServerConnection::new(
+                server.clone(),
+                server.connect().await.unwrap(),
+            ));
+            let (swapped, added) = future::zip(
+                client.swap_dummy(2),
+                client.add_connected_dummy(Arc::clone(&duplicate)),
+            )
+            .await;
+            swapped.unwrap();
+            assert!(!added);
+            assert!(!duplicate.alive.load(Ordering::Relaxed));
+            assert_eq!(client.server_id(), 2);
+            assert_consistent(&client, &[1, 2]).await;
+        });
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fs::{
         self,
         File,
@@ -23,20 +24,412 @@ use crate::{
         },
         types::Chat,
     },
-    proxy::SplinterProxy,
+    proxy::{
+        build_info,
+        SplinterProxy,
+    },
+    systems::{
+        playersave::DEFAULT_SPAWN_POSITION,
+        zoning::Zone,
+    },
 };
 
+/// A simulation server the proxy connects players to
+///
+/// Can be written in the config as just the address string, or as a struct to set other options.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "ServerEntryRepr")]
+pub struct ServerEntry {
+    pub address: String,
+    /// Protocol version the server speaks. Defaults to the proxy's protocol if unset
+    pub protocol: Option<i32>,
+    /// How much load the server takes relative to others when choosing between servers. A server
+    /// with weight 2 is given twice the players of one with weight 1
+    pub weight: u32,
+    /// Compression threshold the proxy uses for packets it sends to the server. Defaults to the
+    /// threshold the server asks for if unset. Servers reject compressed packets smaller than
+    /// their own threshold, so connecting fails if this is below it
+    pub compression_threshold: Option<i32>,
+}
+impl From<&str> for ServerEntry {
+    fn from(address: &str) -> Self {
+        Self {
+            address: address.into(),
+            protocol: None,
+            weight: 1,
+            compression_threshold: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ServerEntryRepr {
+    Address(String),
+    Full {
+        address: String,
+        #[serde(default)]
+        protocol: Option<i32>,
+        #[serde(default = "default_weight")]
+        weight: u32,
+        #[serde(default)]
+        compression_threshold: Option<i32>,
+    },
+}
+fn default_weight() -> u32 {
+    1
+}
+impl From<ServerEntryRepr> for ServerEntry {
+    fn from(repr: ServerEntryRepr) -> Self {
+        match repr {
+            ServerEntryRepr::Address(address) => Self::from(address.as_str()),
+            ServerEntryRepr::Full {
+                address,
+                protocol,
+                weight,
+                compression_threshold,
+            } => Self {
+                address,
+                protocol,
+                weight,
+                compression_threshold,
+            },
+        }
+    }
+}
+
+/// Where executed commands are recorded for auditing
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AuditLogDestination {
+    /// Commands are not audited
+    Disabled,
+    /// Commands are logged at info level to the main log
+    MainLog,
+    /// Commands are appended to the file at the given path
+    File(String),
+}
+impl Default for AuditLogDestination {
+    fn default() -> Self {
+        Self::Disabled
+    }
+}
+
+/// What to do about the servers being reachable when the proxy starts
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BackendCheckMode {
+    /// Servers are not checked
+    Disabled,
+    /// Unreachable servers are warned about
+    Warn,
+    /// The proxy fails to start if any server is unreachable
+    Abort,
+}
+impl Default for BackendCheckMode {
+    fn default() -> Self {
+        Self::Warn
+    }
+}
+
+/// Messages sent to a player when they enter or leave a zone
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZoneMessages {
+    pub enter: Option<String>,
+    pub leave: Option<String>,
+}
+
+/// A rule for routing players logging in from a network
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IpRoute {
+    /// Network in CIDR notation (ex. "10.0.0.0/8"), or a single address
+    pub network: String,
+    pub action: IpRouteAction,
+}
+
+/// What to do with players logging in from a network
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum IpRouteAction {
+    /// Put them on the server with this id
+    Server(u64),
+    /// Refuse their login with this message
+    Deny(String),
+}
+
+/// A command the proxy runs on a schedule, as if it were entered in the console
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledCommand {
+    pub schedule: CommandSchedule,
+    /// The command and its arguments (ex. "say Restarting soon")
+    pub command: String,
+}
+
+/// When a scheduled command runs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CommandSchedule {
+    /// Every this many seconds, first this long after the proxy starts
+    Interval(u64),
+    /// Every day at this local time, in 24 hour "HH:MM" format (ex. "04:30")
+    Daily(String),
+}
+
+/// Paths to minecraft-data files for one protocol version
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersionDataPaths {
+    pub block_data_path: Option<String>,
+    pub item_data_path: Option<String>,
+    pub entity_data_path: Option<String>,
+}
+
+/// Signals for an external autoscaler about how busy each server is
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoscaleConfig {
+    /// How often player counts are checked, in seconds
+    pub interval_secs: f64,
+    /// Player count at or above which a server is busy
+    pub busy_players: usize,
+    /// Player count at or below which a server is idle
+    pub idle_players: usize,
+    /// How many players a count must move back past a threshold before a server stops being
+    /// busy or idle, so that a count hovering around a threshold doesn't flap
+    pub hysteresis: usize,
+    pub output: AutoscaleOutput,
+}
+impl Default for AutoscaleConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10.,
+            busy_players: 50,
+            idle_players: 0,
+            hysteresis: 5,
+            output: AutoscaleOutput::File("autoscale.json".into()),
+        }
+    }
+}
+
+/// Where autoscaling signals go
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AutoscaleOutput {
+    /// Every server's player count and state are written to this file on every check
+    File(String),
+    /// Every change in a server's state is posted as JSON to this URL. Only plain "http://"
+    /// URLs are supported
+    Webhook(String),
+}
+
+/// Formats of messages sent with the `say` and `broadcast` commands
+///
+/// `{message}` is replaced with the message and `{sender}` with the name of who sent it. Color
+/// and style codes may be written with `&` or `§`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BroadcastFormat {
+    /// Format of messages sent from the console
+    pub console: String,
+    /// Format of messages sent by players
+    pub player: String,
+}
+impl Default for BroadcastFormat {
+    fn default() -> Self {
+        Self {
+            console: "&d[Server] &f{message}".into(),
+            player: "&d[{sender}] &f{message}".into(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SplinterConfig {
     pub protocol: i32,
     pub display_version: Option<String>,
-    pub simulation_servers: Vec<(u64, String)>,
+    pub simulation_servers: Vec<(u64, ServerEntry)>,
     pub proxy_address: String,
     pub max_players: Option<i32>,
     pub motd: String,
     pub compression_threshold: Option<i32>,
     pub improper_version_disconnect_message: String,
     pub brand: String,
+    /// Whether the brand shown to players ends with `|shard=<server id>` for the server they're
+    /// on, such as "Splinter|shard=2", so that client mods can tell which server they're on. It
+    /// is sent again whenever the player switches servers
+    pub brand_includes_server: bool,
+    /// Players not seen within this many days are removed from the player data. Pruning is
+    /// disabled if unset
+    pub player_data_max_age_days: Option<u64>,
+    /// Path to a minecraft-data `blocks.json` used for tag conversion. Uses the embedded data if
+    /// unset
+    pub block_data_path: Option<String>,
+    /// Path to a minecraft-data `items.json` used for tag conversion. Uses the embedded data if
+    /// unset
+    pub item_data_path: Option<String>,
+    /// Path to a minecraft-data `entities.json` used for tag conversion. Uses the embedded data
+    /// if unset
+    pub entity_data_path: Option<String>,
+    /// Data files used for tag conversion with players on a specific protocol version, by
+    /// protocol version number. Files not given here fall back to the ones above. The embedded
+    /// data is only used for the protocol version it is from
+    pub version_data_paths: HashMap<i32, VersionDataPaths>,
+    /// Messages to send when players enter or leave a zone, by zone index
+    pub zone_messages: HashMap<usize, ZoneMessages>,
+    /// Maximum number of dummy connections a client may hold at once. The least recently used
+    /// dummies are disconnected when this is exceeded. Unlimited if unset
+    pub max_dummy_connections: Option<usize>,
+    /// Whether to keep dummy connections to the servers of zones next to the ones a player is
+    /// in, so that crossing into them doesn't wait on a new connection. These count towards
+    /// `max_dummy_connections`, behind the servers the player is touching. A server that can't
+    /// be connected to isn't tried again for a while
+    pub prewarm_adjacent: bool,
+    /// Packets resent to players from the last one the new server sent when they switch servers,
+    /// so that state like whether they can fly isn't left from the previous server. Can be
+    /// "PlayerAbilities" or "SpawnPosition". The world border, time, weather, experience and
+    /// difficulty are always resent. Each connection keeps one packet of each kind listed, which
+    /// is only a few dozen bytes each
+    pub resync_packets: Vec<String>,
+    /// Where to log every executed command along with who executed it
+    pub command_audit_log: AuditLogDestination,
+    /// Maximum number of simultaneous connections from a single IP address. Unlimited if unset
+    pub max_connections_per_ip: Option<usize>,
+    /// Status requests, as sent to fill in the server list, answered per minute from a single IP
+    /// address. Requests over the rate are dropped without a response. A minute's worth can be
+    /// made at once, so this should allow for players refreshing their server list a few times.
+    /// Unlimited if unset
+    pub status_requests_per_minute: Option<u32>,
+    /// After a player changes dimension, automatic server switching is suppressed until their
+    /// position is updated, for at most this many seconds
+    pub respawn_switch_suppression_secs: f64,
+    /// How many seconds to wait for a player's server to come back after it disconnects before
+    /// kicking them. If unset, players are not reconnected
+    pub backend_grace_period_secs: Option<f64>,
+    /// Maximum number of commands to hold on to for a player while they are reconnecting
+    pub reconnect_command_buffer_size: usize,
+    /// Server to place players on when their position is not in any zone. If unset, players
+    /// outside of every zone cannot join
+    pub default_server: Option<u64>,
+    /// Whether to disable Nagle's algorithm on client and server connections, sending small
+    /// packets immediately
+    pub tcp_nodelay: bool,
+    /// Whether to batch packets to clients while more are waiting to be written, sending them
+    /// once the queue empties or a latency sensitive packet is written. Bursts like chunk loading
+    /// go out in fewer segments at the cost of some delay, and `tcp_nodelay` no longer applies to
+    /// clients. This toggles Nagle's algorithm with a setsockopt call whenever a client switches
+    /// between batching and writing right away
+    pub batch_client_packets: bool,
+    /// Packets written to clients right away when batching, by name (ex. "EntityPosition"). The
+    /// default covers movement, combat, and keep alives
+    pub latency_sensitive_packets: Vec<String>,
+    /// Maximum number of pending connections waiting to be accepted
+    pub listen_backlog: i32,
+    /// Whether to load and save player data. If disabled, players always join at the default
+    /// spawn position
+    pub player_data_enabled: bool,
+    /// If a player's server hasn't sent tags this many seconds after they join, send them empty
+    /// tags so they can finish logging in. If unset, wait for the server's tags indefinitely
+    pub tags_fallback_secs: Option<f64>,
+    /// Zones to use in specific dimensions, by dimension name, in place of the default zones.
+    /// Dimensions not listed use the default zones
+    pub dimension_zones: HashMap<String, Vec<(u64, Zone)>>,
+    /// Formats of messages sent with the `say` and `broadcast` commands
+    pub broadcast_format: BroadcastFormat,
+    /// Whether to gzip the player data file. Either format is loaded regardless
+    pub compress_player_data: bool,
+    /// Message templates by locale (ex. "en_us") and then message key. `{name}` in a template is
+    /// replaced with the message's `name` argument, and color codes may be written with `&`
+    pub messages: HashMap<String, HashMap<String, String>>,
+    /// Locale whose messages are used when the client's locale doesn't have a message
+    pub default_locale: String,
+    /// Whether to check that every server is reachable on startup
+    pub startup_backend_check: BackendCheckMode,
+    /// Whether to include the proxy's version and commit in the server list version name
+    pub status_build_info: bool,
+    /// Maximum number of packets waiting to be written to a client. Clients that fall this far
+    /// behind are disconnected if the queue doesn't make room within `client_queue_wait_secs`
+    pub client_queue_size: usize,
+    /// How long to wait, in seconds, for room in a client's full packet queue before
+    /// disconnecting the client. Lets a briefly congested client catch up
+    pub client_queue_wait_secs: f64,
+    /// Whether packets from clients that fail to deserialize are relayed to servers as they are.
+    /// They are dropped if disabled. Enabling this can forward malformed data to servers
+    pub passthrough_undeserializable_packets: bool,
+    /// How often to remove stale entries from clients' known chunks, in seconds. Disabled if
+    /// unset
+    pub chunk_compaction_interval_secs: Option<f64>,
+    /// Chat lines sent to players after they join. Color codes may be written with `&`
+    pub welcome_messages: Vec<String>,
+    /// How long after joining to send the welcome messages, in seconds, so that they show up
+    /// after the world has loaded
+    pub welcome_delay_secs: f64,
+    /// Whether muted players are also prevented from running commands
+    pub mute_commands: bool,
+    /// Whether muted players are told when their chat message is dropped
+    pub notify_muted: bool,
+    /// How long to wait for player data to save when shutting down, in seconds, before giving
+    /// up on it
+    pub shutdown_save_timeout_secs: f64,
+    /// Whether login plugin requests from a player's first server are forwarded to the player's
+    /// client. If disabled, the proxy answers them itself as not understood
+    pub forward_login_plugin_requests: bool,
+    /// Names of commands, without the command prefix, that players' clients always send to their
+    /// active server. These take precedence over proxy commands of the same name, so a proxy
+    /// command never intercepts them
+    pub passthrough_commands: Vec<String>,
+    /// How often player data is saved, in seconds. Saving periodically is disabled if zero. Can
+    /// be changed while running with the `saveinterval` command
+    pub player_data_save_interval_secs: u64,
+    /// Messages shown above the hotbar of players moved onto a server, by server id. Servers
+    /// without a message are switched to silently
+    pub switch_messages: HashMap<u64, String>,
+    /// Commands, without the leading `/`, sent to a server by id on behalf of players when they
+    /// join onto it or are moved onto it. The server runs them as if the player had typed them,
+    /// so they only work if the server lets players run them, such as through a permission the
+    /// player already has
+    pub join_commands: HashMap<u64, Vec<String>>,
+    /// Whether to enable TCP keep-alives on connections to servers, so that the operating system
+    /// notices servers that have gone away without closing the connection
+    pub backend_tcp_keep_alive: bool,
+    /// Connections to servers that send nothing for this many seconds are closed, as if the
+    /// server had disconnected. Servers normally send keep-alives and time updates regularly, so
+    /// this should be well above their keep-alive interval. Disabled if unset
+    pub backend_silence_timeout_secs: Option<f64>,
+    /// Position in the overworld that new players join at, and that the `spawn` command sends
+    /// players to. Must be inside the world's border and build height limits
+    pub spawn_position: (f64, f64, f64),
+    /// Rules for routing players by the address they log in from, checked in order before the
+    /// zones. Players whose address matches no rule join the server for their position
+    pub ip_routes: Vec<IpRoute>,
+    /// What chat messages from players start with to run a proxy command, such as `"!"`, instead
+    /// of being sent to their server. Players can only run the commands `player_commands` allows
+    /// them. Players can't run proxy commands if unset
+    pub proxy_command_prefix: Option<String>,
+    /// Proxy commands each player may run from chat, by player name. `"*"` allows every command.
+    /// Players who aren't listed can't run any. In offline mode, players choose their own name,
+    /// so anyone can run the commands allowed here
+    pub player_commands: HashMap<String, Vec<String>>,
+    /// Longest time, in seconds, a client can take to log in and join its server before it is
+    /// disconnected. Logins are never timed out if unset
+    pub login_timeout_secs: Option<f64>,
+    /// Commands run automatically on a schedule, such as daily announcements
+    pub scheduled_commands: Vec<ScheduledCommand>,
+    /// Most proxy eids that can be mapped to server entities at once. Entities spawned beyond
+    /// this are not shown to players, so that a server spawning entities endlessly can't use up
+    /// every eid
+    pub max_eid_mappings: usize,
+    /// Most chat messages kept in the chat log. The log is disabled if zero
+    pub chat_log_max_messages: usize,
+    /// Most memory the chat log takes up, in bytes, approximately. The oldest messages are
+    /// dropped when either this or `chat_log_max_messages` is reached
+    pub chat_log_max_bytes: usize,
+    /// Whether commands are read from stdin. Disable when running without a terminal, such as
+    /// in the background as a service
+    pub console_enabled: bool,
+    /// File that command output shown in the console is also appended to, if set
+    pub console_output_log: Option<String>,
+    /// Largest size, in bytes, a packet capture started with the `capture` command may grow to
+    /// before it is stopped. Captures are written to the `captures` directory
+    pub capture_max_bytes: u64,
+    /// Signals for an external autoscaler when servers become busy or idle. Disabled if unset
+    pub autoscale: Option<AutoscaleConfig>,
 }
 impl Default for SplinterConfig {
     fn default() -> Self {
@@ -50,10 +443,119 @@ impl Default for SplinterConfig {
             compression_threshold: Some(256),
             improper_version_disconnect_message: "Your client version is not supported".into(),
             brand: "Splinter".into(),
+            brand_includes_server: false,
+            player_data_max_age_days: None,
+            block_data_path: None,
+            item_data_path: None,
+            entity_data_path: None,
+            version_data_paths: HashMap::new(),
+            zone_messages: HashMap::new(),
+            max_dummy_connections: None,
+            prewarm_adjacent: false,
+            resync_packets: vec![],
+            command_audit_log: AuditLogDestination::Disabled,
+            max_connections_per_ip: None,
+            status_requests_per_minute: None,
+            respawn_switch_suppression_secs: 5.,
+            backend_grace_period_secs: None,
+            reconnect_command_buffer_size: 16,
+            default_server: Some(0),
+            tcp_nodelay: true,
+            batch_client_packets: false,
+            latency_sensitive_packets: vec![
+                "EntityPosition".into(),
+                "EntityPositionAndRotation".into(),
+                "EntityRotation".into(),
+                "EntityHeadLook".into(),
+                "EntityTeleport".into(),
+                "EntityVelocity".into(),
+                "PlayerPositionAndLook".into(),
+                "EntityAnimation".into(),
+                "EntityStatus".into(),
+                "UpdateHealth".into(),
+                "KeepAlive".into(),
+            ],
+            listen_backlog: 128,
+            player_data_enabled: true,
+            tags_fallback_secs: Some(10.),
+            dimension_zones: HashMap::new(),
+            broadcast_format: BroadcastFormat::default(),
+            compress_player_data: false,
+            messages: HashMap::new(),
+            default_locale: "en_us".into(),
+            startup_backend_check: BackendCheckMode::default(),
+            status_build_info: false,
+            client_queue_size: 4096,
+            client_queue_wait_secs: 1.,
+            passthrough_undeserializable_packets: false,
+            chunk_compaction_interval_secs: Some(60.),
+            welcome_messages: Vec::new(),
+            welcome_delay_secs: 3.,
+            mute_commands: false,
+            notify_muted: true,
+            shutdown_save_timeout_secs: 10.,
+            forward_login_plugin_requests: false,
+            passthrough_commands: Vec::new(),
+            player_data_save_interval_secs: 30,
+            switch_messages: HashMap::new(),
+            join_commands: HashMap::new(),
+            backend_tcp_keep_alive: false,
+            backend_silence_timeout_secs: None,
+            spawn_position: DEFAULT_SPAWN_POSITION,
+            ip_routes: Vec::new(),
+            proxy_command_prefix: None,
+            player_commands: HashMap::new(),
+            login_timeout_secs: Some(30.),
+            scheduled_commands: Vec::new(),
+            max_eid_mappings: 1_000_000,
+            chat_log_max_messages: 1000,
+            chat_log_max_bytes: 1024 * 1024,
+            console_enabled: true,
+            console_output_log: None,
+            capture_max_bytes: 64 * 1024 * 1024,
+            autoscale: None,
         }
     }
 }
 
+/// Checks that a config field's number of seconds can be made into a duration, which must be
+/// more than zero for intervals
+fn check_secs(field: &str, secs: f64, is_interval: bool) -> anyhow::Result<()> {
+    if !secs.is_finite() || secs < 0. || (is_interval && secs == 0.) {
+        let expected = if is_interval {
+            "positive"
+        } else {
+            "non-negative"
+        };
+        bail!(
+            "{} must be a {} number of seconds, but is {}",
+            field,
+            expected,
+            secs
+        );
+    }
+    Ok(())
+}
+
+/// Config fields with any of these in their name are redacted when displayed
+const SECRET_FIELD_PATTERNS: &[&str] = &["password", "secret", "token", "key"];
+
+/// Config fields that can be changed without restarting the proxy, with the command that changes
+/// them. Every other field is only read on startup
+const LIVE_FIELDS: &[(&str, &str)] = &[
+    ("dimension_zones", "reloadzones"),
+    ("player_data_save_interval_secs", "saveinterval"),
+];
+
+/// A comment for a config field saying whether changing it needs a restart, or which command
+/// changes it while running
+fn change_marker(field: &str) -> String {
+    match LIVE_FIELDS.iter().find(|(name, _)| *name == field) {
+        Some((_, command)) => format!("// live, with `{}`", command),
+        None => "// restart required".into(),
+    }
+}
+
 impl SplinterConfig {
     /// Attempts to read splinter config from a string
     pub fn from_str(data: impl AsRef<str>) -> anyhow::Result<SplinterConfig> {
@@ -63,23 +565,110 @@ impl SplinterConfig {
     pub fn from_file(filepath: impl AsRef<Path>) -> anyhow::Result<SplinterConfig> {
         Self::from_str(fs::read_to_string(filepath)?)
     }
+    /// Checks that every duration in the config is one that can be waited for
+    pub fn validate_durations(&self) -> anyhow::Result<()> {
+        check_secs(
+            "respawn_switch_suppression_secs",
+            self.respawn_switch_suppression_secs,
+            false,
+        )?;
+        if let Some(secs) = self.chunk_compaction_interval_secs {
+            check_secs("chunk_compaction_interval_secs", secs, true)?;
+        }
+        check_secs("client_queue_wait_secs", self.client_queue_wait_secs, false)?;
+        check_secs("welcome_delay_secs", self.welcome_delay_secs, false)?;
+        check_secs(
+            "shutdown_save_timeout_secs",
+            self.shutdown_save_timeout_secs,
+            false,
+        )?;
+        if let Some(secs) = self.backend_grace_period_secs {
+            check_secs("backend_grace_period_secs", secs, false)?;
+        }
+        if let Some(secs) = self.tags_fallback_secs {
+            check_secs("tags_fallback_secs", secs, false)?;
+        }
+        if let Some(secs) = self.backend_silence_timeout_secs {
+            check_secs("backend_silence_timeout_secs", secs, true)?;
+        }
+        if let Some(secs) = self.login_timeout_secs {
+            check_secs("login_timeout_secs", secs, true)?;
+        }
+        Ok(())
+    }
     /// Attempts to convert this splinter config to a string
     pub fn to_string(&self) -> anyhow::Result<String> {
         ron::ser::to_string_pretty(self, PrettyConfig::default()).map_err(anyhow::Error::new)
     }
+    /// Converts this splinter config to a string for display, with the values of secret fields
+    /// replaced with `"<redacted>"`, and each field marked with whether it needs a restart to
+    /// change
+    pub fn to_redacted_string(&self) -> anyhow::Result<String> {
+        // the pretty output puts each top level field on its own line, indented once
+        const FIELD_INDENT: &str = "    ";
+        let mut lines = Vec::new();
+        let mut redacting = false;
+        for line in self.to_string()?.lines() {
+            let is_top_level = line.starts_with(FIELD_INDENT)
+                && !line[FIELD_INDENT.len()..].starts_with(char::is_whitespace);
+            if redacting {
+                // skip the rest of a multiline value up to its closing line
+                if is_top_level {
+                    redacting = false;
+                }
+                continue;
+            }
+            let field = match line
+                .split_once(':')
+                .filter(|_| is_top_level)
+                .map(|(name, _)| name.trim())
+            {
+                Some(field) => field,
+                None => {
+                    lines.push(line.to_owned());
+                    continue;
+                }
+            };
+            if SECRET_FIELD_PATTERNS.iter().any(|pat| field.contains(pat)) {
+                lines.push(format!(
+                    "{}{}: \"<redacted>\", {}",
+                    FIELD_INDENT,
+                    field,
+                    change_marker(field)
+                ));
+                redacting = !line.ends_with(',');
+            } else {
+                lines.push(format!("{} {}", line, change_marker(field)));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
     /// Attempts to write this splinter config to a file
     pub fn to_file(&self, filepath: impl AsRef<Path>) -> anyhow::Result<()> {
         File::create(filepath)?
             .write_all(self.to_string()?.as_bytes())
             .map_err(anyhow::Error::new)
     }
+    /// Gets the configured template for a message in a locale, falling back to the default locale
+    pub fn message_template(&self, locale: &str, key: &str) -> Option<&str> {
+        [locale, self.default_locale.as_str()]
+            .iter()
+            .find_map(|locale| self.messages.get(&locale.to_lowercase())?.get(key))
+            .map(String::as_str)
+    }
     /// Gets the server status given the config and the proxy
     pub fn server_status(&self, proxy: &SplinterProxy) -> StatusSpec {
         let players = smol::block_on(proxy.players.read());
         let total_players = players.len();
+        // the status format has no room for extra fields, so build info goes in the version name
+        let version_name = match (&self.display_version, self.status_build_info) {
+            (Some(name), true) => Some(format!("{} - {}", name, build_info())),
+            (None, true) => Some(build_info()),
+            (name, false) => name.clone(),
+        };
         StatusSpec {
-            version: self.display_version.as_ref().map(|name| StatusVersionSpec {
-                name: name.clone(),
+            version: version_name.map(|name| StatusVersionSpec {
+                name,
                 protocol: self.protocol,
             }),
             players: StatusPlayersSpec {
@@ -98,3 +687,37 @@ impl SplinterConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        assert!(SplinterConfig::default().validate_durations().is_ok());
+        let mut config = SplinterConfig::default();
+        config.respawn_switch_suppression_secs = -1.;
+        assert!(config.validate_durations().is_err());
+        config.respawn_switch_suppression_secs = f64::NAN;
+        assert!(config.validate_durations().is_err());
+        // suppressing for no time at all is fine, but intervals have to pass some time
+        let mut config = SplinterConfig::default();
+        config.respawn_switch_suppression_secs = 0.;
+        assert!(config.validate_durations().is_ok());
+        config.chunk_compaction_interval_secs = Some(0.);
+        assert!(config.validate_durations().is_err());
+    }
+
+    #[test]
+    fn shown_fields_are_marked_for_restart() {
+        let shown = SplinterConfig::default().to_redacted_string().unwrap();
+        let field_line = |field: &str| {
+            shown
+                .lines()
+                .find(|line| line.trim_start().starts_with(&format!("{}:", field)))
+                .unwrap()
+        };
+        assert!(field_line("dimension_zones").ends_with("// live, with `reloadzones`"));
+        assert!(field_line("proxy_address").ends_with("// restart required"));
+    }
+}
@@ -0,0 +1,33 @@
+use std::{
+    io,
+    net::SocketAddr,
+};
+
+use thiserror::Error;
+
+/// Errors returned by the proxy's public operations
+#[derive(Debug, Error)]
+pub enum SplinterError {
+    /// No player with the given name is connected
+    #[error("Failed to find player \"{0}\"")]
+    PlayerNotFound(String),
+    /// A simulation server could not be connected to
+    #[error("Failed to connect to server {id} at {address}")]
+    ServerUnreachable {
+        id: u64,
+        address: SocketAddr,
+        #[source]
+        source: io::Error,
+    },
+    /// A packet was to be sent on a server connection that has already closed
+    #[error("Connection to server {0} is closed")]
+    ConnectionClosed(u64),
+    /// The config has an invalid value
+    #[error("Invalid config: {0}")]
+    ConfigInvalid(String),
+    /// Any other error
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type SplinterResult<T> = Result<T, SplinterError>;
@@ -0,0 +1,77 @@
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use futures_lite::future;
+use smol::Timer;
+
+use crate::{
+    protocol::{
+        current::status::StatusSpec,
+        v_cur::fetch_status,
+    },
+    proxy::{
+        server::SplinterServer,
+        SplinterProxy,
+    },
+};
+
+/// How long to wait for a server's status before considering it unreachable
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait after a server is found unreachable before prewarming dummies for it again
+const PREWARM_BACKOFF: Duration = Duration::from_secs(30);
+
+/// What happened the last time a server's status was fetched
+#[derive(Clone, Copy)]
+pub struct ServerCheck {
+    pub reachable: bool,
+    /// How long the server took to answer, if it did
+    pub latency: Option<Duration>,
+    pub checked_at: Instant,
+}
+
+impl SplinterProxy {
+    /// Fetches a server's status, giving up if it takes too long, and remembers whether the
+    /// server answered and how quickly
+    pub async fn check_server(&self, server: &SplinterServer) -> anyhow::Result<StatusSpec> {
+        let started = Instant::now();
+        let status = future::or(fetch_status(server), async {
+            Timer::after(STATUS_TIMEOUT).await;
+            Err(anyhow!("Timed out fetching the status of server {}", server.id))
+        })
+        .await;
+        let check = ServerCheck {
+            reachable: status.is_ok(),
+            latency: status.as_ref().ok().map(|_| started.elapsed()),
+            checked_at: Instant::now(),
+        };
+        self.server_checks.lock().unwrap().insert(server.id, check);
+        status
+    }
+    /// Remembers whether connecting to a server worked, keeping the latency of its last status
+    /// check if it's still reachable
+    pub fn record_reachability(&self, server_id: u64, reachable: bool) {
+        let mut checks = self.server_checks.lock().unwrap();
+        let latency = checks
+            .get(&server_id)
+            .and_then(|check| check.latency)
+            .filter(|_| reachable);
+        checks.insert(
+            server_id,
+            ServerCheck {
+                reachable,
+                latency,
+                checked_at: Instant::now(),
+            },
+        );
+    }
+    /// Whether a server was found unreachable too recently to try prewarming a dummy for it again
+    pub fn prewarm_backing_off(&self, server_id: u64) -> bool {
+        match self.server_checks.lock().unwrap().get(&server_id) {
+            Some(check) => !check.reachable && check.checked_at.elapsed() < PREWARM_BACKOFF,
+            None => false,
+        }
+    }
+}
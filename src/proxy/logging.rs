@@ -3,7 +3,9 @@ use std::{
         self,
         metadata,
         File,
+        OpenOptions,
     },
+    io::Write,
     path::Path,
 };
 
@@ -69,3 +71,23 @@ pub fn init() -> anyhow::Result<()> {
     ])?;
     Ok(())
 }
+
+/// Appends a line to a file, prefixed with the current local time. Creates the file if it does
+/// not exist.
+///
+/// This does blocking IO, so use `blocking::unblock` when calling it from async code.
+pub fn append_timestamped_line(path: impl AsRef<Path>, line: impl AsRef<str>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Opening {}", path.display()))?;
+    writeln!(
+        file,
+        "[{}] {}",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        line.as_ref()
+    )
+    .with_context(|| format!("Writing to {}", path.display()))
+}
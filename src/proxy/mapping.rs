@@ -13,6 +13,8 @@ pub struct SplinterMapping {
     pub eids: BiHashMap<i32, (u64, i32)>,
     pub entity_data: HashMap<i32, EntityData>,
     pub eid_gen: IdGenerator,
+    /// Number of times a server entity was registered again while it was still mapped
+    pub eid_collisions: usize,
 }
 
 impl SplinterMapping {
@@ -21,9 +23,25 @@ impl SplinterMapping {
             eids: BiHashMap::new(),
             eid_gen: IdGenerator::new(),
             entity_data: HashMap::new(),
+            eid_collisions: 0,
         }
     }
+    /// Registers a new proxy eid for a server's entity
+    ///
+    /// If the entity is already mapped, such as when a server reuses an eid without the proxy
+    /// seeing the old entity removed, the collision is logged and counted, and the existing proxy
+    /// eid is returned instead of replacing it, which would break whatever the client has under
+    /// the existing eid.
     pub fn register_eid_mapping(&mut self, server_id: u64, server_eid: i32) -> i32 {
+        if let Some(existing_eid) = self.eids.get_by_right(&(server_id, server_eid)) {
+            warn!(
+                "Server {} eid {} was registered again while already mapped to proxy eid {}, \
+                 keeping the existing mapping",
+                server_id, server_eid, existing_eid
+            );
+            self.eid_collisions += 1;
+            return *existing_eid;
+        }
         let new_eid = self.eid_gen.take_id() as i32;
         self.eids.insert(new_eid, (server_id, server_eid));
         debug!(
@@ -32,6 +50,89 @@ impl SplinterMapping {
         );
         new_eid
     }
+    /// Gets the proxy eid of a server's entity, registering a mapping for it if it doesn't have
+    /// one yet
+    ///
+    /// Packets can reference an entity before the entity is spawned, such as a leash attached to
+    /// a holder that spawns afterwards, so the mapping is made early and then used by the spawn.
+    pub fn get_or_register_eid_mapping(&mut self, server_id: u64, server_eid: i32) -> i32 {
+        match self.eids.get_by_right(&(server_id, server_eid)) {
+            Some(proxy_eid) => *proxy_eid,
+            None => self.register_eid_mapping(server_id, server_eid),
+        }
+    }
+    /// Points a proxy eid at a server's entity, replacing whatever it was mapped to before
+    ///
+    /// Used for the client's own player, whose proxy eid stays the same while the server entity
+    /// it stands for changes with the active server.
+    pub fn remap_eid(&mut self, proxy_eid: i32, server_id: u64, server_eid: i32) {
+        self.eids.insert(proxy_eid, (server_id, server_eid));
+    }
+    /// Number of proxy eids currently mapped to server entities
+    pub fn outstanding_eids(&self) -> usize {
+        self.eids.len()
+    }
+    /// Whether a server's entity can be given a proxy eid without going over the most
+    /// outstanding mappings allowed. Entities that are already mapped always can
+    pub fn has_room_for_eid(&self, server_id: u64, server_eid: i32, max_mappings: usize) -> bool {
+        self.eids.contains_right(&(server_id, server_eid)) || self.outstanding_eids() < max_mappings
+    }
+    /// Cross-checks the eid mappings, entity data, and eid generator for entries that are
+    /// missing from one another
+    pub fn verify(&self) -> MappingReport {
+        let orphaned_entity_data = self
+            .entity_data
+            .keys()
+            .filter(|eid| !self.eids.contains_left(eid))
+            .copied()
+            .collect();
+        let unreserved_eids = self
+            .eids
+            .left_values()
+            .filter(|eid| self.eid_gen.is_available(**eid as u64))
+            .copied()
+            .collect();
+        let leaked_ids = self
+            .eid_gen
+            .outstanding_ids()
+            .into_iter()
+            .filter(|id| !self.eids.contains_left(&(*id as i32)))
+            .collect();
+        MappingReport {
+            orphaned_entity_data,
+            unreserved_eids,
+            leaked_ids,
+        }
+    }
+    /// Fixes the inconsistencies found by `verify`
+    pub fn fix(&mut self, report: &MappingReport) {
+        for eid in report.orphaned_entity_data.iter() {
+            self.entity_data.remove(eid);
+        }
+        for eid in report.unreserved_eids.iter() {
+            self.eid_gen.reserve_id(*eid as u64);
+        }
+        for id in report.leaked_ids.iter() {
+            self.eid_gen.return_id(*id);
+        }
+    }
+}
+
+/// Inconsistencies between the parts of a `SplinterMapping`
+pub struct MappingReport {
+    /// Proxy eids with entity data but no eid mapping
+    pub orphaned_entity_data: Vec<i32>,
+    /// Mapped proxy eids that the eid generator could hand out again
+    pub unreserved_eids: Vec<i32>,
+    /// Ids taken from the eid generator that aren't mapped and so will never be returned
+    pub leaked_ids: Vec<u64>,
+}
+impl MappingReport {
+    pub fn is_consistent(&self) -> bool {
+        self.orphaned_entity_data.is_empty()
+            && self.unreserved_eids.is_empty()
+            && self.leaked_ids.is_empty()
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -65,20 +166,56 @@ pub struct IdGenerator {
     available_ids_set: HashSet<u64>,
 }
 impl IdGenerator {
+    const INITIAL_ID: u64 = 1;
     pub fn new() -> Self {
-        const INITIAL_ID: u64 = 1;
         Self {
-            available_ids: vec![INITIAL_ID],
-            available_ids_set: HashSet::from_iter([INITIAL_ID]),
+            available_ids: vec![Self::INITIAL_ID],
+            available_ids_set: HashSet::from_iter([Self::INITIAL_ID]),
         }
     }
     pub fn take_id(&mut self) -> u64 {
-        if self.available_ids.len() > 1 {
+        let id = if self.available_ids.len() > 1 {
             self.available_ids.remove(self.available_ids.len() - 2) // remove second to last
         } else {
             let id = self.available_ids.remove(0);
             self.available_ids.push(id + 1);
+            self.available_ids_set.insert(id + 1);
             id
+        };
+        self.available_ids_set.remove(&id);
+        id
+    }
+    /// The lowest id that has never been taken
+    fn next_id(&self) -> u64 {
+        *self.available_ids.last().unwrap()
+    }
+    /// Whether the id would be handed out by a future `take_id`
+    pub fn is_available(&self, id: u64) -> bool {
+        id >= self.next_id() || self.available_ids_set.contains(&id)
+    }
+    /// Gets all ids that have been taken and not returned
+    pub fn outstanding_ids(&self) -> Vec<u64> {
+        (Self::INITIAL_ID..self.next_id())
+            .filter(|id| !self.available_ids_set.contains(id))
+            .collect()
+    }
+    /// Marks an id as taken without handing it out
+    pub fn reserve_id(&mut self, id: u64) {
+        let next_id = self.next_id();
+        let last_index = self.available_ids.len() - 1;
+        if id >= next_id {
+            // the ids skipped over are still available
+            self.available_ids.splice(last_index..last_index, next_id..id);
+            self.available_ids_set.remove(&next_id);
+            self.available_ids_set.extend(next_id..id);
+            self.available_ids_set.insert(id + 1);
+            self.available_ids[last_index + (id - next_id) as usize] = id + 1;
+        } else if let Some(index) = self.available_ids[..last_index]
+            .iter()
+            .position(|available| *available == id)
+        {
+            self.available_ids.remove(index);
+            self.available_ids_set.remove(&id);
         }
     }
     pub fn return_id(&mut self, id: u64) {
@@ -87,3 +224,25 @@ impl IdGenerator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicting_registration_keeps_existing_mapping() {
+        let mut map = SplinterMapping::new();
+        let proxy_eid = map.register_eid_mapping(1, 10);
+        assert_eq!(map.eid_collisions, 0);
+
+        // the server reusing eid 10 without the proxy seeing it removed
+        assert_eq!(map.register_eid_mapping(1, 10), proxy_eid);
+        assert_eq!(map.eid_collisions, 1);
+        assert_eq!(map.outstanding_eids(), 1);
+        assert!(map.verify().is_consistent());
+
+        // the same eid on another server is a different entity
+        assert_ne!(map.register_eid_mapping(2, 10), proxy_eid);
+        assert_eq!(map.eid_collisions, 1);
+    }
+}
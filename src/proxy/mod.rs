@@ -1,131 +1,516 @@
 use std::{
     collections::HashMap,
-    net::{SocketAddr, TcpListener},
+    net::{IpAddr, SocketAddr, TcpListener},
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex as SyncMutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use anyhow::Context;
+use arc_swap::ArcSwap;
+use blocking::unblock;
+use futures_lite::future;
 use smol::{
     lock::{Mutex, RwLock},
     Async, Timer,
 };
+use socket2::{Domain, Protocol, Socket, Type};
 
+pub mod capture;
 pub mod chat;
+pub mod chatlog;
 pub mod client;
 pub mod config;
+pub mod error;
+pub mod health;
 pub mod logging;
 pub mod mapping;
 pub mod server;
+#[cfg(test)]
+pub mod testing;
 
+use chatlog::ChatLog;
 use client::SplinterClient;
-use config::SplinterConfig;
+use config::{IpRouteAction, SplinterConfig};
+use error::{SplinterError, SplinterResult};
+use health::ServerCheck;
 use mapping::SplinterMapping;
-use server::SplinterServer;
+use server::{ProtocolSource, SplinterServer};
 
 use crate::{
-    protocol::Tags,
+    protocol::{
+        current::{types::Chat, uuid::UUID4, PacketLatestKind},
+        describe_version,
+        v_cur::{parse_latency_sensitive_kinds, parse_resync_kinds, IdNameMaps, ProxyBossBar},
+        Tags,
+    },
+    proxy::chat::ToChat,
     systems::{
+        commands::CommandSender,
         playersave::{
-            load_player_data, save_player_data, PlInfo, PlInfoPlayer, PLAYER_DATA_FILENAME,
+            clamp_save_interval, load_saved_player_data, player_data_filename, prune_player_data,
+            save_player_data, validate_position, PlInfo, PlInfoPlayer,
+        },
+        routing::parse_network,
+        zoning::{
+            update_client_servers, validate_zone_servers, world_to_chunk_position, Zone, Zoner,
         },
-        zoning::{Zone, Zoner},
     },
 };
 
+/// Describes this build of the proxy, with its version and the commit it was built from
+pub fn build_info() -> String {
+    format!(
+        "Splinter Proxy {} ({})",
+        env!("CARGO_PKG_VERSION"),
+        option_env!("SPLINTER_GIT_COMMIT").unwrap_or("unknown commit")
+    )
+}
+
 pub struct SplinterProxy {
     pub alive: AtomicBool,
     pub config: SplinterConfig,
     pub players: RwLock<HashMap<String, Arc<SplinterClient>>>,
     pub servers: RwLock<HashMap<u64, Arc<SplinterServer>>>,
     pub mapping: Mutex<SplinterMapping>,
-    pub tags: Mutex<Option<Tags>>,
+    /// Tags sent by servers, by the protocol version of the servers that sent them
+    pub tags: Mutex<HashMap<i32, Tags>>,
+    /// Id to name maps for converting tags, by protocol version. Loaded when first needed
+    pub id_maps: Mutex<HashMap<i32, Arc<IdNameMaps>>>,
 
     pub player_data: Mutex<PlInfo>,
-    pub zoner: Zoner,
+    /// Zones, swappable so that they can be reloaded while running
+    pub zoner: ArcSwap<Zoner>,
+    /// Number of open connections from each IP address
+    pub connections_per_ip: SyncMutex<HashMap<IpAddr, usize>>,
+    /// Status requests each IP address may still make right now, and when that was last updated
+    pub status_allowance: SyncMutex<HashMap<IpAddr, (f64, Instant)>>,
+    /// The last status check of each server that has been checked, by server id
+    pub server_checks: SyncMutex<HashMap<u64, ServerCheck>>,
+    /// Display names servers have given players in the player list, by player uuid, kept until the
+    /// player leaves
+    pub display_names: Mutex<HashMap<UUID4, Option<Chat>>>,
+    /// How often player data is saved, in seconds, or zero if it isn't saved periodically
+    pub save_interval_secs: AtomicU64,
+    /// Boss bars the proxy is currently showing to every player
+    pub boss_bars: Mutex<Vec<ProxyBossBar>>,
+    /// The most recent chat messages from players
+    pub chat_log: Mutex<ChatLog>,
+    /// Kinds of packets resent from a server's last one when switching onto it
+    pub resync_kinds: Vec<PacketLatestKind>,
+    /// Kinds of packets written to clients right away when batching
+    pub latency_sensitive_kinds: Vec<PacketLatestKind>,
 }
 
 impl SplinterProxy {
-    pub fn new(config: SplinterConfig) -> anyhow::Result<Self> {
+    pub fn new(config: SplinterConfig) -> SplinterResult<Self> {
         let servers = {
             let mut map = HashMap::new();
-            for (id, addr_str) in config.simulation_servers.iter() {
+            for (id, entry) in config.simulation_servers.iter() {
                 map.insert(
                     *id,
                     Arc::new(SplinterServer {
                         id: *id,
-                        address: SocketAddr::from_str(addr_str)?,
+                        address: SocketAddr::from_str(&entry.address).map_err(|e| {
+                            SplinterError::ConfigInvalid(format!(
+                                "address \"{}\" for server {}: {}",
+                                &entry.address, id, e
+                            ))
+                        })?,
+                        protocol: entry.protocol.unwrap_or(config.protocol),
+                        protocol_source: if entry.protocol.is_some() {
+                            ProtocolSource::Configured
+                        } else {
+                            ProtocolSource::Assumed
+                        },
+                        weight: entry.weight,
+                        compression_threshold: entry.compression_threshold,
+                        tcp_nodelay: config.tcp_nodelay,
+                        tcp_keep_alive: config.backend_tcp_keep_alive,
                     }),
                 );
             }
+            validate_zone_servers(&config.dimension_zones, &map)
+                .map_err(|e| SplinterError::ConfigInvalid(format!("{:#}", e)))?;
             RwLock::new(map)
         };
+        config
+            .validate_durations()
+            .map_err(|e| SplinterError::ConfigInvalid(format!("{:#}", e)))?;
+        for route in config.ip_routes.iter() {
+            parse_network(&route.network).map_err(|e| {
+                SplinterError::ConfigInvalid(format!("IP route network: {:#}", e))
+            })?;
+            if let IpRouteAction::Server(server_id) = route.action {
+                if !config
+                    .simulation_servers
+                    .iter()
+                    .any(|(id, _)| *id == server_id)
+                {
+                    return Err(SplinterError::ConfigInvalid(format!(
+                        "IP route for \"{}\" uses server {}, which doesn't exist",
+                        &route.network, server_id
+                    )));
+                }
+            }
+        }
+        validate_position(config.spawn_position)
+            .map_err(|e| {
+                SplinterError::ConfigInvalid(format!("invalid spawn position: {:#}", e))
+            })?;
+        if matches!(&config.proxy_command_prefix, Some(prefix) if prefix.is_empty()) {
+            return Err(SplinterError::ConfigInvalid(
+                "proxy command prefix can't be empty".into(),
+            ));
+        }
+        let (default_protocol, id_maps) = IdNameMaps::load_default(&config)
+            .map_err(|e| SplinterError::ConfigInvalid(format!("{:#}", e)))?;
+        // with player data disabled, the player data stays empty so everyone joins at the
+        // default spawn position
+        let mut player_data = if config.player_data_enabled {
+            load_saved_player_data(config.compress_player_data).unwrap_or_default()
+        } else {
+            PlInfo::default()
+        };
+        if let Some(max_age_days) = config.player_data_max_age_days {
+            prune_player_data(&mut player_data, max_age_days);
+        }
+        let save_interval_secs = clamp_save_interval(config.player_data_save_interval_secs);
+        let chat_log = ChatLog::new(config.chat_log_max_messages, config.chat_log_max_bytes);
+        let resync_kinds = parse_resync_kinds(&config.resync_packets)
+            .map_err(|e| SplinterError::ConfigInvalid(format!("{:#}", e)))?;
+        let latency_sensitive_kinds =
+            parse_latency_sensitive_kinds(&config.latency_sensitive_packets)
+                .map_err(|e| SplinterError::ConfigInvalid(format!("{:#}", e)))?;
+        let zoner = Zoner {
+            dimension_zones: config.dimension_zones.clone(),
+            zones: vec![
+                (
+                    0,
+                    Zone::Rectangle {
+                        x1: -4,
+                        z1: -4,
+                        x2: 4,
+                        z2: 4,
+                    },
+                ),
+                (
+                    1,
+                    Zone::InvertedRectangle {
+                        x1: -3,
+                        z1: -3,
+                        x2: 3,
+                        z2: 3,
+                    },
+                ),
+            ],
+        };
         Ok(Self {
             alive: AtomicBool::new(true),
             config,
             players: RwLock::new(HashMap::new()),
             servers,
             mapping: Mutex::new(SplinterMapping::new()),
-            tags: Mutex::new(None),
-            zoner: Zoner {
-                zones: vec![
-                    (
-                        0,
-                        Zone::Rectangle {
-                            x1: -4,
-                            z1: -4,
-                            x2: 4,
-                            z2: 4,
-                        },
-                    ),
-                    (
-                        1,
-                        Zone::InvertedRectangle {
-                            x1: -3,
-                            z1: -3,
-                            x2: 3,
-                            z2: 3,
-                        },
-                    ),
-                ],
-            },
-            player_data: Mutex::new(
-                load_player_data(PLAYER_DATA_FILENAME).unwrap_or(PlInfo::default()),
+            tags: Mutex::new(HashMap::new()),
+            id_maps: Mutex::new(
+                vec![(default_protocol, Arc::new(id_maps))]
+                    .into_iter()
+                    .collect(),
             ),
+            zoner: ArcSwap::new(Arc::new(zoner)),
+            player_data: Mutex::new(player_data),
+            connections_per_ip: SyncMutex::new(HashMap::new()),
+            status_allowance: SyncMutex::new(HashMap::new()),
+            server_checks: SyncMutex::new(HashMap::new()),
+            display_names: Mutex::new(HashMap::new()),
+            save_interval_secs: AtomicU64::new(save_interval_secs),
+            boss_bars: Mutex::new(Vec::new()),
+            chat_log: Mutex::new(chat_log),
+            resync_kinds,
+            latency_sensitive_kinds,
         })
     }
     pub fn is_alive(&self) -> bool {
         self.alive.load(Ordering::Relaxed)
     }
+    /// Gets the id to name maps for a protocol version, loading them if they haven't been yet
+    pub async fn id_maps_for(&self, protocol: i32) -> anyhow::Result<Arc<IdNameMaps>> {
+        let mut id_maps = self.id_maps.lock().await;
+        if let Some(maps) = id_maps.get(&protocol) {
+            return Ok(Arc::clone(maps));
+        }
+        let maps = Arc::new(IdNameMaps::load(&self.config, protocol).with_context(|| {
+            format!("Failed to load data files for {}", describe_version(protocol))
+        })?);
+        id_maps.insert(protocol, Arc::clone(&maps));
+        Ok(maps)
+    }
+    /// Removes a server while the proxy is running
+    ///
+    /// Its zones are dropped first, so that nothing picks it again, and players on it are drained
+    /// onto other servers before it's removed. Every player's dummy connection to it is then
+    /// disconnected. If a player can't be drained, the server and its zones are kept.
+    pub async fn remove_server(self: &Arc<Self>, server_id: u64) -> anyhow::Result<()> {
+        if !self.servers.read().await.contains_key(&server_id) {
+            bail!("No server with id {}", server_id);
+        }
+        if self.config.default_server == Some(server_id) {
+            bail!("Server {} is the default server, so it can't be removed", server_id);
+        }
+        let without_server = |zones: &Vec<(u64, Zone)>| {
+            zones
+                .iter()
+                .filter(|(id, _)| *id != server_id)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let previous_zoner = self.zoner.rcu(|zoner| Zoner {
+            zones: without_server(&zoner.zones),
+            dimension_zones: zoner
+                .dimension_zones
+                .iter()
+                .map(|(dimension, zones)| (dimension.clone(), without_server(zones)))
+                .collect(),
+        });
+        let clients = self.players.read().await.values().map(Arc::clone).collect::<Vec<_>>();
+        for client in clients.iter() {
+            if client.server_id() != server_id {
+                continue;
+            }
+            if let Err(e) = client.drain_server(server_id).await {
+                // the server's zones are put back on top of whatever the zones are now, as they
+                // may have been reloaded since
+                let with_server = |zones: &Vec<(u64, Zone)>, previous: Option<&Vec<(u64, Zone)>>| {
+                    let mut zones = zones.clone();
+                    if !zones.iter().any(|(id, _)| *id == server_id) {
+                        zones.extend(
+                            previous
+                                .into_iter()
+                                .flatten()
+                                .filter(|(id, _)| *id == server_id)
+                                .cloned(),
+                        );
+                    }
+                    zones
+                };
+                self.zoner.rcu(|zoner| Zoner {
+                    zones: with_server(&zoner.zones, Some(&previous_zoner.zones)),
+                    dimension_zones: zoner
+                        .dimension_zones
+                        .iter()
+                        .map(|(dimension, zones)| {
+                            let previous = previous_zoner.dimension_zones.get(dimension);
+                            (dimension.clone(), with_server(zones, previous))
+                        })
+                        .collect(),
+                });
+                return Err(e.context(format!(
+                    "Failed to move \"{}\" off of server {}, so it wasn't removed",
+                    &client.name, server_id
+                )));
+            }
+        }
+        // removed before disconnecting dummies, so that new connections to it fail
+        self.servers.write().await.remove(&server_id);
+        self.server_checks.lock().unwrap().remove(&server_id);
+        for client in clients.iter() {
+            if client.dummy_servers.load().iter().any(|(id, _)| *id == server_id) {
+                if let Err(e) = client.disconnect_dummy(server_id).await {
+                    debug!("Failed to disconnect {}-{}: {:?}", &client.name, server_id, e);
+                }
+            }
+            // zone indices may have shifted
+            update_client_servers(self, client).await;
+        }
+        info!("Removed server {}", server_id);
+        Ok(())
+    }
     pub async fn kick_client(
         &self,
         client_name: impl AsRef<str>,
         reason: ClientKickReason,
-    ) -> anyhow::Result<()> {
+    ) -> SplinterResult<()> {
         let name_string = client_name.as_ref().to_owned();
         let cl_opt = self.players.read().await.get(&name_string).map(Arc::clone);
         if let Some(client) = cl_opt {
-            client.send_kick(reason).await?;
+            // the player is removed even if the kick can't be sent, like when the client's queue
+            // overflowed, so that it doesn't stay in the player list
+            let kicked = client.send_kick(reason).await;
             client.set_alive(false).await;
             self.players.write().await.remove(&name_string);
-            let pos = &**client.position.load();
-            self.player_data.lock().await.players.insert(
-                client.uuid,
-                PlInfoPlayer {
-                    x: pos.x,
-                    y: pos.y,
-                    z: pos.z,
-                    name: client.name.clone(),
-                },
+            self.display_names.lock().await.remove(&client.uuid);
+            self.remember_player(&client).await;
+            kicked?;
+        } else {
+            return Err(SplinterError::PlayerNotFound(name_string));
+        }
+        Ok(())
+    }
+    /// Records a player's state in the player data, if player data is enabled
+    pub async fn remember_player(&self, client: &SplinterClient) {
+        if self.config.player_data_enabled {
+            self.player_data
+                .lock()
+                .await
+                .players
+                .insert(client.uuid, PlInfoPlayer::from_client(client));
+        }
+    }
+    /// Sends a chat message to the player with the given name
+    pub async fn message_player(
+        &self,
+        client_name: impl AsRef<str>,
+        msg: impl ToChat,
+        sender: &CommandSender,
+    ) -> SplinterResult<()> {
+        let client = self
+            .players
+            .read()
+            .await
+            .get(client_name.as_ref())
+            .map(Arc::clone)
+            .ok_or_else(|| SplinterError::PlayerNotFound(client_name.as_ref().to_owned()))?;
+        client.send_message(msg, sender).await?;
+        Ok(())
+    }
+    /// Gets the id of the server that owns a world position in a dimension, falling back to the
+    /// configured default server if no zone contains it
+    pub fn server_for_position(&self, dimension: &str, x: f64, z: f64) -> Option<u64> {
+        self.zoner
+            .load()
+            .zones_in_point(dimension, world_to_chunk_position((x, z)))
+            .get(0)
+            .copied()
+            .or(self.config.default_server)
+    }
+    /// Gets the id of the server a player joining at a world position should be put on. Like
+    /// `server_for_position`, except that the least loaded server is chosen when zones overlap
+    pub async fn server_for_join(&self, dimension: &str, x: f64, z: f64) -> Option<u64> {
+        let servers = self
+            .zoner
+            .load()
+            .zones_in_point(dimension, world_to_chunk_position((x, z)));
+        self.least_loaded_server(&servers).await.or(self.config.default_server)
+    }
+    /// Chooses which of the given servers a player should be put on, preferring servers with the
+    /// fewest players relative to their weight. Earlier servers win ties
+    pub async fn least_loaded_server(&self, candidates: &[u64]) -> Option<u64> {
+        let mut loads = HashMap::<u64, usize>::new();
+        for client in self.players.read().await.values() {
+            *loads.entry(client.server_id()).or_insert(0) += 1;
+        }
+        let servers = self.servers.read().await;
+        let score = |server_id: &u64| {
+            let weight = servers.get(server_id).map(|server| server.weight).unwrap_or(1);
+            if weight == 0 {
+                return f64::INFINITY;
+            }
+            // the player being placed is counted so that weight matters between empty servers
+            (loads.get(server_id).copied().unwrap_or(0) + 1) as f64 / weight as f64
+        };
+        let mut best: Option<(u64, f64)> = None;
+        for server_id in candidates {
+            let server_score = score(server_id);
+            if best.map_or(true, |(_, best_score)| server_score < best_score) {
+                best = Some((*server_id, server_score));
+            }
+        }
+        best.map(|(server_id, _)| server_id)
+    }
+    /// Asks a server which protocol version it speaks and caches it on the server, warning if a
+    /// previously detected version changed. Servers with a protocol set in the config are left
+    /// alone
+    ///
+    /// Connections that are already open keep the version they were opened with.
+    pub async fn detect_server_protocol(&self, server_id: u64) -> anyhow::Result<()> {
+        let server = self
+            .servers
+            .read()
+            .await
+            .get(&server_id)
+            .map(Arc::clone)
+            .ok_or_else(|| anyhow!("No server with id {}", server_id))?;
+        if server.protocol_source == ProtocolSource::Configured {
+            return Ok(());
+        }
+        let status = self.check_server(&server).await?;
+        let protocol = status
+            .version
+            .map(|version| version.protocol)
+            .ok_or_else(|| anyhow!("Server {} did not report its version", server_id))?;
+        if protocol == server.protocol {
+            if server.protocol_source == ProtocolSource::Detected {
+                return Ok(());
+            }
+        } else if server.protocol_source == ProtocolSource::Detected {
+            warn!(
+                "Server {} changed from protocol {} to {}",
+                server_id, server.protocol, protocol
             );
         } else {
-            bail!("Failed to find client by the name \"{}\"", name_string);
+            info!("Detected protocol {} for server {}", protocol, server_id);
+        }
+        let mut servers = self.servers.write().await;
+        // the server could have been replaced while its status was being fetched
+        if let Some(current) = servers.get_mut(&server_id) {
+            let mut updated = (**current).clone();
+            updated.protocol = protocol;
+            updated.protocol_source = ProtocolSource::Detected;
+            *current = Arc::new(updated);
         }
         Ok(())
     }
+    /// Counts a new connection from an IP address, returning a guard that uncounts it when
+    /// dropped. Returns `None` if the IP address already has the maximum number of connections.
+    pub fn track_connection(self: &Arc<Self>, ip: IpAddr) -> Option<ConnectionGuard> {
+        let mut connections = self.connections_per_ip.lock().unwrap();
+        if let Some(max) = self.config.max_connections_per_ip {
+            if connections.get(&ip).copied().unwrap_or(0) >= max {
+                return None;
+            }
+        }
+        *connections.entry(ip).or_insert(0) += 1;
+        Some(ConnectionGuard {
+            proxy: Arc::clone(self),
+            ip,
+        })
+    }
+    /// Counts a status request from an IP address against the configured rate, returning whether
+    /// it should be answered
+    ///
+    /// Each IP address may make up to a minute's worth of requests at once, regaining them
+    /// steadily over the minute, so occasional bursts like refreshing the server list a few
+    /// times in a row are still answered.
+    pub fn allow_status_request(&self, ip: IpAddr) -> bool {
+        let per_minute = match self.config.status_requests_per_minute {
+            Some(per_minute) => per_minute as f64,
+            None => return true,
+        };
+        let now = Instant::now();
+        let regained = |(allowance, updated): (f64, Instant)| {
+            let elapsed = now.duration_since(updated).as_secs_f64();
+            (allowance + elapsed / 60. * per_minute).min(per_minute)
+        };
+        let mut allowances = match self.status_allowance.lock() {
+            Ok(allowances) => allowances,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if !allowances.contains_key(&ip) {
+            // addresses back at the full allowance are the same as ones never seen
+            allowances.retain(|_, entry| regained(*entry) < per_minute);
+        }
+        let allowance = allowances
+            .get(&ip)
+            .map(|entry| regained(*entry))
+            .unwrap_or(per_minute);
+        let allowed = allowance >= 1.;
+        let allowance = if allowed { allowance - 1. } else { allowance };
+        allowances.insert(ip, (allowance, now));
+        allowed
+    }
     pub async fn shutdown(&self) {
         let names = self
             .players
@@ -143,14 +528,52 @@ impl SplinterProxy {
             }
         }
 
-        if let Err(e) = save_player_data(&*self.player_data.lock().await, PLAYER_DATA_FILENAME) {
-            error!("Error saving player data: {:?}", e);
+        if self.config.player_data_enabled {
+            // saving is done on another thread so that a hung disk can't hang the shutdown
+            let player_data = self.player_data.lock().await.clone();
+            let filename = player_data_filename(self.config.compress_player_data);
+            let timeout = Duration::from_secs_f64(self.config.shutdown_save_timeout_secs);
+            let save = unblock(move || save_player_data(&player_data, filename));
+            match future::or(async { Some(save.await) }, async {
+                Timer::after(timeout).await;
+                None
+            })
+            .await
+            {
+                Some(Ok(())) => {}
+                Some(Err(e)) => error!("Error saving player data: {:?}", e),
+                None => warn!(
+                    "Saving player data took longer than {:?}, shutting down without waiting for \
+                     it. Player data may not have been saved",
+                    timeout
+                ),
+            }
         }
         info!("Shutting down");
         self.alive.store(false, Ordering::Relaxed);
     }
 }
 
+/// Keeps a connection counted towards its IP address's connection limit until dropped
+pub struct ConnectionGuard {
+    proxy: Arc<SplinterProxy>,
+    ip: IpAddr,
+}
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut connections = match self.proxy.connections_per_ip.lock() {
+            Ok(connections) => connections,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
+
 /// A reason for a client to get kicked
 #[derive(Clone)]
 pub enum ClientKickReason {
@@ -160,6 +583,8 @@ pub enum ClientKickReason {
     Kicked(String, Option<String>),
     /// Server shut down
     Shutdown,
+    /// Client's server disconnected and did not come back in time
+    ServerLost,
 }
 
 impl ClientKickReason {
@@ -176,13 +601,15 @@ impl ClientKickReason {
                 }
             ),
             ClientKickReason::Shutdown => "Server shut down".into(),
+            ClientKickReason::ServerLost => "Lost connection to the server".into(),
         }
     }
 }
 
 pub async fn run(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
     let address = SocketAddr::from_str(proxy.config.proxy_address.as_str())?;
-    let listener = Async::<TcpListener>::bind(address)?;
+    let listener = bind_listener(address, proxy.config.listen_backlog)
+        .with_context(|| format!("Failed to listen on {}", address))?;
     {
         let proxy = Arc::clone(&proxy);
         smol::spawn(async move {
@@ -195,7 +622,22 @@ pub async fn run(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
                         continue;
                     }
                 };
-                if let Err(e) = client::handle(stream, addr, Arc::clone(&proxy)) {
+                if proxy.config.tcp_nodelay {
+                    if let Err(e) = stream.get_ref().set_nodelay(true) {
+                        warn!("Failed to set TCP_NODELAY for connection from {}: {}", addr, e);
+                    }
+                }
+                let guard = match proxy.track_connection(addr.ip()) {
+                    Some(guard) => guard,
+                    None => {
+                        warn!(
+                            "Refusing connection from {}: too many connections from this address",
+                            addr
+                        );
+                        continue;
+                    }
+                };
+                if let Err(e) = client::handle(stream, addr, Arc::clone(&proxy), guard) {
                     error!("Failed to handle connection from {}: {}", addr, e);
                 }
             }
@@ -210,3 +652,46 @@ pub async fn run(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+/// Binds a TCP listener with the given maximum number of pending connections
+fn bind_listener(address: SocketAddr, backlog: i32) -> anyhow::Result<Async<TcpListener>> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    // match the standard library's TcpListener::bind
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(backlog)?;
+    Ok(Async::new(TcpListener::from(socket))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::client_with_dummies;
+
+    #[test]
+    fn kick_removes_client_that_cant_be_written_to() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1]).await;
+            let proxy = Arc::clone(&client.proxy);
+            proxy
+                .players
+                .write()
+                .await
+                .insert(client.name.clone(), Arc::clone(&client));
+            // like after the client's queue overflowed
+            client.outgoing.close();
+            assert!(proxy
+                .kick_client(&client.name, ClientKickReason::TimedOut)
+                .await
+                .is_err());
+            assert!(proxy.players.read().await.is_empty());
+            assert!(!client.alive.load(Ordering::Relaxed));
+        });
+    }
+}
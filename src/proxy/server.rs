@@ -1,5 +1,8 @@
 use std::{
-    collections::HashSet,
+    collections::{
+        HashMap,
+        HashSet,
+    },
     net::{
         SocketAddr,
         TcpStream,
@@ -15,22 +18,97 @@ use smol::{
     lock::Mutex,
     Async,
 };
+use socket2::SockRef;
 
-use crate::protocol::{
-    current::uuid::UUID4,
-    AsyncCraftConnection,
-    AsyncCraftReader,
-    AsyncCraftWriter,
+use crate::{
+    protocol::{
+        current::{
+            uuid::UUID4,
+            PacketLatest,
+            PacketLatestKind,
+        },
+        AsyncCraftConnection,
+        AsyncCraftReader,
+        AsyncCraftWriter,
+    },
+    proxy::error::SplinterError,
 };
 
+/// Where the protocol version of a server came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolSource {
+    /// Set for the server in the config, so it is never detected
+    Configured,
+    /// Assumed to be the proxy's protocol until it is detected
+    Assumed,
+    /// Learned from the server's status
+    Detected,
+}
+
 #[derive(Clone)]
 pub struct SplinterServer {
     pub id: u64,
     pub address: SocketAddr,
+    /// Protocol version the server speaks
+    pub protocol: i32,
+    pub protocol_source: ProtocolSource,
+    /// Whether to disable Nagle's algorithm on connections to the server
+    pub tcp_nodelay: bool,
+    /// Whether to enable TCP keep-alives on connections to the server
+    pub tcp_keep_alive: bool,
+    /// How much load the server takes relative to others
+    pub weight: u32,
+    /// Compression threshold for packets sent to the server once it enables compression. Uses
+    /// the server's own threshold if unset
+    pub compression_threshold: Option<i32>,
 }
 impl SplinterServer {
-    pub async fn connect(&self) -> anyhow::Result<AsyncCraftConnection> {
-        let arc_stream = AsyncArc::new(Async::<TcpStream>::connect(self.address).await?);
+    /// Gets the compression thresholds for reading from and writing to a connection to this
+    /// server, after the server enabled compression with the given threshold
+    ///
+    /// Packets from the server are read with the server's threshold, while packets to the server
+    /// are written with the threshold configured for it, or the server's if there isn't one.
+    /// Fails if the configured threshold is below the server's, as the server would reject the
+    /// smaller packets compressed with it.
+    pub fn compression_thresholds(
+        &self,
+        server_threshold: i32,
+    ) -> anyhow::Result<(Option<i32>, Option<i32>)> {
+        if server_threshold <= 0 {
+            return Ok((None, None));
+        }
+        let write_threshold = match self.compression_threshold {
+            Some(threshold) if threshold < server_threshold => bail!(
+                "Compression threshold {} configured for server {} is below its own threshold {}",
+                threshold,
+                self.id,
+                server_threshold
+            ),
+            Some(threshold) => threshold,
+            None => server_threshold,
+        };
+        Ok((Some(server_threshold), Some(write_threshold)))
+    }
+    pub async fn connect(&self) -> Result<AsyncCraftConnection, SplinterError> {
+        let unreachable = |source| SplinterError::ServerUnreachable {
+            id: self.id,
+            address: self.address,
+            source,
+        };
+        let stream = Async::<TcpStream>::connect(self.address)
+            .await
+            .map_err(unreachable)?;
+        if self.tcp_nodelay {
+            stream.get_ref().set_nodelay(true).map_err(unreachable)?;
+        }
+        // these are at the TCP level since servers kick players for Minecraft keep-alives they
+        // didn't ask for
+        if self.tcp_keep_alive {
+            SockRef::from(stream.get_ref())
+                .set_keepalive(true)
+                .map_err(unreachable)?;
+        }
+        let arc_stream = AsyncArc::new(stream);
         let (reader, writer) = (
             AsyncArc::clone(&arc_stream).compat(),
             AsyncArc::clone(&arc_stream).compat(),
@@ -45,8 +123,33 @@ pub struct SplinterServerConnection {
     pub reader: Mutex<AsyncCraftReader>,
     pub server: SplinterServer,
     pub alive: AtomicBool,
+    /// Whether the player on this connection died and has not respawned yet
+    pub awaiting_respawn: AtomicBool,
 
     pub eid: i32,
     pub uuid: UUID4,
     pub known_chunks: Mutex<HashSet<(i32, i32)>>,
+    /// Objectives the server has displayed on the client, by display position
+    pub displayed_objectives: Mutex<HashMap<i8, String>>,
+    /// The last packets the server sent of each kind that is resent when switching onto it
+    pub resync_packets: Mutex<HashMap<PacketLatestKind, Vec<PacketLatest>>>,
+}
+impl SplinterServerConnection {
+    /// Wraps a new connection to a server. The player's eid and uuid on the server aren't known
+    /// until it logs in
+    pub fn new(server: SplinterServer, conn: AsyncCraftConnection) -> Self {
+        let (reader, writer) = conn.into_split();
+        Self {
+            writer: Mutex::new(writer),
+            reader: Mutex::new(reader),
+            server,
+            alive: AtomicBool::new(true),
+            awaiting_respawn: AtomicBool::new(false),
+            eid: -1,
+            uuid: UUID4::from(0u128),
+            known_chunks: Mutex::new(HashSet::new()),
+            displayed_objectives: Mutex::new(HashMap::new()),
+            resync_packets: Mutex::new(HashMap::new()),
+        }
+    }
 }
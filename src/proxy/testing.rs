@@ -0,0 +1,97 @@
+//! Helpers for tests that need a proxy with connected clients
+//!
+//! Servers and clients are stood in for by a listener that accepts connections and never
+//! answers, so whatever the proxy writes to them is buffered and ignored.
+
+use std::{
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+};
+
+use async_compat::CompatExt;
+use async_dup::Arc as AsyncArc;
+use craftio_rs::{CraftConnection, CraftIo};
+use smol::Async;
+
+use crate::{
+    protocol::current::{
+        protocol::{PacketDirection, State},
+        types::Vec3,
+    },
+    proxy::{
+        client::SplinterClient, config::SplinterConfig, server::SplinterServerConnection,
+        SplinterProxy,
+    },
+};
+
+/// Opens a connection to the listener
+pub async fn silent_stream(listener: &TcpListener) -> AsyncArc<Async<TcpStream>> {
+    AsyncArc::new(
+        Async::<TcpStream>::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap(),
+    )
+}
+
+/// Makes a proxy whose servers with the given ids are all the listener
+pub fn silent_proxy(listener: &TcpListener, server_ids: &[u64]) -> Arc<SplinterProxy> {
+    let address = listener.local_addr().unwrap().to_string();
+    let mut config = SplinterConfig::default();
+    config.player_data_enabled = false;
+    config.simulation_servers = server_ids
+        .iter()
+        .map(|id| (*id, address.as_str().into()))
+        .collect();
+    Arc::new(SplinterProxy::new(config).unwrap())
+}
+
+/// Makes a client on the first of the servers, with dummy connections to the rest. The player's
+/// eid on each server is ten times the server's id
+pub async fn client_with_dummies(
+    listener: &TcpListener,
+    server_ids: &[u64],
+) -> Arc<SplinterClient> {
+    let proxy = silent_proxy(listener, server_ids);
+    let mut conns = vec![];
+    for id in server_ids {
+        let server = proxy.servers.read().await.get(id).map(Arc::clone).unwrap();
+        let mut conn =
+            SplinterServerConnection::new((*server).clone(), server.connect().await.unwrap());
+        conn.eid = *id as i32 * 10;
+        conns.push(Arc::new(conn));
+    }
+    let stream = silent_stream(listener).await;
+    let (_, mut writer) = CraftConnection::from_async(
+        (
+            AsyncArc::clone(&stream).compat(),
+            AsyncArc::clone(&stream).compat(),
+        ),
+        PacketDirection::ServerBound,
+    )
+    .into_split();
+    writer.set_state(State::Play);
+    let active = conns.remove(0);
+    let proxy_eid = proxy
+        .mapping
+        .lock()
+        .await
+        .register_eid_mapping(active.server.id, active.eid);
+    let client = Arc::new(SplinterClient::new(
+        Arc::clone(&proxy),
+        "tester".into(),
+        writer,
+        stream,
+        active,
+        Vec3 {
+            x: 0.,
+            y: 64.,
+            z: 0.,
+        },
+        proxy.config.protocol,
+        proxy_eid,
+    ));
+    for conn in conns {
+        assert!(client.add_connected_dummy(conn).await);
+    }
+    client
+}
@@ -0,0 +1,252 @@
+use std::{
+    collections::HashMap,
+    fs,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use blocking::unblock;
+use futures_lite::{
+    future,
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+};
+use json::{
+    object,
+    JsonValue,
+};
+use smol::{
+    net::TcpStream,
+    Timer,
+};
+
+use crate::{
+    proxy::{
+        config::{
+            AutoscaleConfig,
+            AutoscaleOutput,
+        },
+        SplinterProxy,
+    },
+    systems::SplinterSystem,
+};
+
+/// How long to wait for a webhook to respond
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+inventory::submit! {
+    SplinterSystem {
+        name: "Autoscale Signals",
+        init: Box::new(|proxy| {
+            Box::pin(init(proxy))
+        }),
+    }
+}
+
+/// How busy a server is, as told to the autoscaler
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ServerLoad {
+    Idle,
+    Normal,
+    Busy,
+}
+impl ServerLoad {
+    fn name(&self) -> &'static str {
+        match self {
+            ServerLoad::Idle => "idle",
+            ServerLoad::Normal => "normal",
+            ServerLoad::Busy => "busy",
+        }
+    }
+    /// The load of a server with a player count, given its previous load. A server only stops
+    /// being busy or idle once its count is past the threshold by more than the hysteresis
+    fn next(self, players: usize, config: &AutoscaleConfig) -> ServerLoad {
+        if players >= config.busy_players {
+            return ServerLoad::Busy;
+        }
+        if players <= config.idle_players {
+            return ServerLoad::Idle;
+        }
+        match self {
+            ServerLoad::Busy if players + config.hysteresis >= config.busy_players => {
+                ServerLoad::Busy
+            }
+            ServerLoad::Idle if players <= config.idle_players + config.hysteresis => {
+                ServerLoad::Idle
+            }
+            _ => ServerLoad::Normal,
+        }
+    }
+}
+
+/// A plain HTTP URL split into where to connect and what to request
+struct HttpUrl {
+    /// Host and port, as connected to
+    address: String,
+    host: String,
+    path: String,
+}
+impl HttpUrl {
+    fn parse(url: &str) -> anyhow::Result<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| anyhow!("Webhook URL \"{}\" is not a plain http:// URL", url))?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            bail!("Webhook URL \"{}\" has no host", url);
+        }
+        let address = if host.contains(':') {
+            host.to_owned()
+        } else {
+            format!("{}:80", host)
+        };
+        Ok(Self {
+            address,
+            host: host.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+    /// Posts a JSON body, failing unless the response has a success status
+    async fn post_json(&self, body: &JsonValue) -> anyhow::Result<()> {
+        let body = body.dump();
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        );
+        let mut stream = TcpStream::connect(&self.address)
+            .await
+            .with_context(|| format!("Connecting to {}", &self.address))?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await?;
+        let response = String::from_utf8_lossy(&response);
+        let status_line = response.lines().next().unwrap_or("");
+        match status_line.split_whitespace().nth(1) {
+            Some(status) if status.starts_with('2') => Ok(()),
+            _ => bail!("Webhook responded with \"{}\"", status_line),
+        }
+    }
+}
+
+async fn init(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+    let config = match proxy.config.autoscale.as_ref() {
+        Some(config) => config.clone(),
+        None => return Ok(()),
+    };
+    if config.idle_players >= config.busy_players {
+        bail!("Autoscale idle player count must be below the busy player count");
+    }
+    if !(config.interval_secs > 0.) {
+        bail!("Autoscale interval must be positive");
+    }
+    let webhook = match &config.output {
+        AutoscaleOutput::File(_) => None,
+        AutoscaleOutput::Webhook(url) => Some(HttpUrl::parse(url)?),
+    };
+    smol::spawn(autoscale_loop(proxy, config, webhook)).detach();
+    Ok(())
+}
+
+/// Counts the players on every server, signalling the autoscaler about how busy the servers are
+async fn autoscale_loop(
+    proxy: Arc<SplinterProxy>,
+    config: AutoscaleConfig,
+    webhook: Option<HttpUrl>,
+) {
+    let mut loads = HashMap::<u64, ServerLoad>::new();
+    loop {
+        Timer::after(Duration::from_secs_f64(config.interval_secs)).await;
+        if !proxy.is_alive() {
+            break;
+        }
+        let mut counts = proxy
+            .servers
+            .read()
+            .await
+            .keys()
+            .map(|id| (*id, 0usize))
+            .collect::<HashMap<_, _>>();
+        for client in proxy.players.read().await.values() {
+            *counts.entry(client.server_id()).or_insert(0) += 1;
+        }
+        let mut server_ids = counts.keys().copied().collect::<Vec<_>>();
+        server_ids.sort_unstable();
+        let mut changes = vec![];
+        for server_id in server_ids.iter() {
+            let players = counts[server_id];
+            let previous = *loads.get(server_id).unwrap_or(&ServerLoad::Normal);
+            let load = previous.next(players, &config);
+            loads.insert(*server_id, load);
+            if load != previous {
+                info!(
+                    "Server {} is now {} with {} players",
+                    server_id,
+                    load.name(),
+                    players
+                );
+                changes.push(object! {
+                    server: *server_id,
+                    players: players,
+                    load: load.name(),
+                });
+            }
+        }
+        match (&config.output, &webhook) {
+            (AutoscaleOutput::File(path), _) => {
+                let servers = server_ids
+                    .iter()
+                    .map(|server_id| {
+                        object! {
+                            server: *server_id,
+                            players: counts[server_id],
+                            load: loads[server_id].name(),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let data = json::stringify_pretty(object! { servers: servers }, 4);
+                let path = path.clone();
+                // written aside and renamed, so the autoscaler never reads a partial file
+                if let Err(e) = unblock(move || -> anyhow::Result<()> {
+                    let temp_path = format!("{}.tmp", &path);
+                    fs::write(&temp_path, data)
+                        .with_context(|| format!("Writing {}", &temp_path))?;
+                    fs::rename(&temp_path, &path)
+                        .with_context(|| format!("Renaming {} to {}", &temp_path, &path))
+                })
+                .await
+                {
+                    error!("Failed to write autoscale signals: {:?}", e);
+                }
+            }
+            (AutoscaleOutput::Webhook(url), Some(webhook)) => {
+                for change in changes {
+                    let post = future::or(
+                        async { Some(webhook.post_json(&change).await) },
+                        async {
+                            Timer::after(WEBHOOK_TIMEOUT).await;
+                            None
+                        },
+                    );
+                    match post.await {
+                        Some(Ok(())) => {}
+                        Some(Err(e)) => {
+                            error!("Failed to post autoscale signal to {}: {:?}", url, e)
+                        }
+                        None => error!("Timed out posting autoscale signal to {}", url),
+                    }
+                }
+            }
+            (AutoscaleOutput::Webhook(_), None) => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::HashSet,
+    sync::Arc,
+};
+
+use crate::{
+    proxy::{
+        config::BackendCheckMode,
+        SplinterProxy,
+    },
+    systems::SplinterSystem,
+};
+
+inventory::submit! {
+    SplinterSystem {
+        name: "Backend Check",
+        init: Box::new(|proxy| {
+            Box::pin(check_backends(proxy))
+        }),
+    }
+}
+
+/// Pings every configured server, logging which are reachable. Fails if any server is
+/// unreachable and the check is configured to abort startup
+async fn check_backends(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+    let mode = &proxy.config.startup_backend_check;
+    if matches!(mode, BackendCheckMode::Disabled) {
+        return Ok(());
+    }
+    let zoner = proxy.zoner.load();
+    let zoned_servers = zoner
+        .zones
+        .iter()
+        .chain(zoner.dimension_zones.values().flatten())
+        .map(|(server_id, _)| *server_id)
+        .collect::<HashSet<u64>>();
+    let mut servers = proxy
+        .servers
+        .read()
+        .await
+        .values()
+        .map(Arc::clone)
+        .collect::<Vec<_>>();
+    servers.sort_by_key(|server| server.id);
+    let mut unreachable = Vec::new();
+    for server in servers {
+        match proxy.check_server(&server).await {
+            Ok(status) => info!(
+                "Server {} at {} is reachable ({}/{} players)",
+                server.id, server.address, status.players.online, status.players.max
+            ),
+            Err(e) => {
+                warn_unreachable(server.id, &zoned_servers, format!("{:#}", e));
+                unreachable.push(server.id);
+            }
+        }
+    }
+    if !unreachable.is_empty() && matches!(mode, BackendCheckMode::Abort) {
+        bail!("Servers {:?} are unreachable", unreachable);
+    }
+    Ok(())
+}
+
+fn warn_unreachable(server_id: u64, zoned_servers: &HashSet<u64>, reason: String) {
+    if zoned_servers.contains(&server_id) {
+        warn!(
+            "Server {} is unreachable and owns a zone, so players there will fail to join: {}",
+            server_id, reason
+        );
+    } else {
+        warn!("Server {} is unreachable: {}", server_id, reason);
+    }
+}
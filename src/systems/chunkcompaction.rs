@@ -0,0 +1,62 @@
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use smol::Timer;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::SplinterSystem,
+};
+
+inventory::submit! {
+    SplinterSystem {
+        name: "Chunk Compaction",
+        init: Box::new(|proxy| {
+            Box::pin(async move {
+                if let Some(interval_secs) = proxy.config.chunk_compaction_interval_secs {
+                    let interval = Duration::from_secs_f64(interval_secs);
+                    smol::spawn(chunk_compaction_loop(proxy, interval)).detach();
+                }
+                Ok(())
+            })
+        }),
+    }
+}
+
+/// Periodically removes stale entries from every client's known chunks, logging how many were
+/// removed along with how many look to have leaked
+async fn chunk_compaction_loop(proxy: Arc<SplinterProxy>, interval: Duration) {
+    loop {
+        Timer::after(interval).await;
+        if !proxy.is_alive() {
+            break;
+        }
+        let clients = proxy
+            .players
+            .read()
+            .await
+            .values()
+            .map(Arc::clone)
+            .collect::<Vec<_>>();
+        let mut total_removed = 0;
+        for client in clients {
+            let removed = client.compact_known_chunks().await;
+            if removed > 0 {
+                debug!("removed {} stale known chunks for {}", removed, &client.name);
+                total_removed += removed;
+            }
+            let untracked = client.untracked_known_chunks().await;
+            if untracked > 0 {
+                debug!(
+                    "{} has {} known chunks that none of its connections know about",
+                    &client.name, untracked
+                );
+            }
+        }
+        if total_removed > 0 {
+            info!("Removed {} stale known chunk entries", total_removed);
+        }
+    }
+}
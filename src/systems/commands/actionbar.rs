@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::{
+        chat::Localized,
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "actionbar",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() < 2 {
+                bail!("Expected a player name and a message");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            let message = args[1..].join(" ");
+            smol::block_on(client.send_action_bar(message.as_str()))?;
+            sender.respond_sync(Localized {
+                key: "actionbar_sent",
+                fallback: "Sent action bar message to {player}",
+                args: &[("player", &client.name)],
+            })?;
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    protocol::{
+        current::proto::BossBarColor,
+        v_cur::parse_boss_bar_color,
+    },
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+/// How long a boss bar is shown for if the command doesn't say, in seconds
+const DEFAULT_BOSS_BAR_SECS: f64 = 10.;
+
+inventory::submit! {
+    SplinterCommand {
+        name: "bossbar",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let mut args = args.to_vec();
+            // the optional arguments are taken off the end, so the text can have spaces
+            let secs = match args.last().and_then(|arg| arg.parse::<f64>().ok()) {
+                Some(secs) if args.len() > 1 => {
+                    args.pop();
+                    secs
+                }
+                _ => DEFAULT_BOSS_BAR_SECS,
+            };
+            let color = match args.last().and_then(|arg| parse_boss_bar_color(arg)) {
+                Some(color) if args.len() > 1 => {
+                    args.pop();
+                    color
+                }
+                _ => BossBarColor::White,
+            };
+            if args.is_empty() {
+                bail!("Usage: bossbar <text> [color] [duration in seconds]");
+            }
+            if !secs.is_finite() || secs <= 0. {
+                bail!("Duration must be a positive number of seconds");
+            }
+            let title = args.join(" ");
+            smol::block_on(proxy.show_boss_bar(title.clone(), color, Duration::from_secs_f64(secs)));
+            sender.respond_sync(format!("Showing boss bar \"{}\" for {} seconds", title, secs))?;
+            Ok(())
+        }),
+    }
+}
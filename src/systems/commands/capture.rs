@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::{
+        client::SplinterClient,
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "capture",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let find_client = |name: &str| {
+                smol::block_on(proxy.players.read())
+                    .get(name)
+                    .map(Arc::clone)
+                    .ok_or_else(|| anyhow!("Failed to find player"))
+            };
+            match args {
+                ["off"] => {
+                    let clients = smol::block_on(proxy.players.read()).values().map(Arc::clone).collect::<Vec<_>>();
+                    stop_captures(sender, &clients)
+                }
+                ["off", name] => stop_captures(sender, &[find_client(name)?]),
+                [name, path] => {
+                    let client = find_client(name)?;
+                    let path = smol::block_on(client.start_capture(path))?;
+                    sender.respond_sync(format!("Capturing {}'s packets to \"{}\"", &client.name, path))
+                }
+                _ => bail!("Usage: capture <player> <file> | capture off [player]"),
+            }
+        }),
+    }
+}
+
+/// Stops capturing the packets of the clients, responding with where each capture went
+fn stop_captures(sender: &CommandSender, clients: &[Arc<SplinterClient>]) -> anyhow::Result<()> {
+    let mut stopped = false;
+    for client in clients.iter() {
+        if let Some((path, written)) = smol::block_on(client.stop_capture()) {
+            sender.respond_sync(format!(
+                "Stopped capturing {}'s packets, wrote {} bytes to \"{}\"",
+                &client.name, written, path
+            ))?;
+            stopped = true;
+        }
+    }
+    if !stopped {
+        sender.respond_sync("No packets were being captured")?;
+    }
+    Ok(())
+}
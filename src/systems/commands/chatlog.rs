@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+/// How many messages are shown if the command doesn't say
+const DEFAULT_MESSAGE_COUNT: usize = 10;
+
+inventory::submit! {
+    SplinterCommand {
+        name: "chatlog",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let count = match args {
+                [] => DEFAULT_MESSAGE_COUNT,
+                [count] => count.parse::<usize>().map_err(|_| anyhow!("Invalid message count \"{}\"", count))?,
+                _ => bail!("Usage: chatlog [count]"),
+            };
+            let lines = {
+                let chat_log = smol::block_on(proxy.chat_log.lock());
+                let mut lines = vec![format!(
+                    "Chat log has {} messages taking about {} bytes",
+                    chat_log.message_count(),
+                    chat_log.size()
+                )];
+                lines.extend(chat_log.recent(count).map(|entry| {
+                    format!("[{}] {}: {}", entry.time.format("%H:%M:%S"), &entry.sender, &entry.message)
+                }));
+                lines
+            };
+            for line in lines {
+                sender.respond_sync(line)?;
+            }
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "clientbrand",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let player_map = smol::block_on(proxy.players.read());
+            let client = player_map.get(args[0]).ok_or_else(|| anyhow!("Failed to find player"))?;
+            sender.respond_sync(format!("{} is using client brand \"{}\"", &client.name, &**client.brand.load()))?;
+            Ok(())
+        }),
+    }
+}
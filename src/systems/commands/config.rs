@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "config",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            match args.get(0) {
+                Some(&"show") => {
+                    // live changes aren't written back to the loaded config, so this shows the
+                    // values from startup
+                    sender.respond_sync(format!(
+                        "Config loaded on startup (live fields can be changed with the command \
+                         given, without a restart):\n{}",
+                        proxy.config.to_redacted_string()?
+                    ))?;
+                }
+                Some(_) => bail!("Unknown subcommand"),
+                None => bail!("Expected a subcommand"),
+            }
+            Ok(())
+        }),
+    }
+}
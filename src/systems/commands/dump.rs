@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::Ordering,
+        Arc,
+    },
+};
+
+use anyhow::Context;
+use blocking::unblock;
+use chrono::Local;
+use json::{
+    object,
+    JsonValue,
+};
+
+use crate::{
+    proxy::{
+        client::SplinterClient,
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+const DUMP_DIRECTORY: &str = "dumps";
+
+inventory::submit! {
+    SplinterCommand {
+        name: "dump",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            let state = smol::block_on(dump_client_state(&client));
+            // names from offline mode clients can hold anything, including path separators
+            let file_name = client
+                .name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+                .collect::<String>();
+            let filename = format!(
+                "./{}/{}_{}.json",
+                DUMP_DIRECTORY,
+                file_name,
+                Local::now().format("%Y%m%d_%H%M%S")
+            );
+            let data = json::stringify_pretty(state, 4);
+            let path = filename.clone();
+            smol::block_on(unblock(move || {
+                if !Path::new(DUMP_DIRECTORY).is_dir() {
+                    fs::create_dir(DUMP_DIRECTORY).with_context(|| "Creating dumps directory")?;
+                }
+                fs::write(&path, data).with_context(|| format!("Writing dump to {}", &path))
+            }))?;
+            sender.respond_sync(format!("Dumped {}'s state to {}", &client.name, filename))?;
+            Ok(())
+        }),
+    }
+}
+
+/// Takes a snapshot of the state of a client that is useful for reproducing issues
+async fn dump_client_state(client: &SplinterClient) -> JsonValue {
+    let settings = client.settings.load();
+    let position = client.position.load();
+    let dummy_servers = client
+        .dummy_servers
+        .load()
+        .iter()
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    let skin_parts = settings
+        .skin_parts
+        .iter()
+        .map(|part| format!("{:?}", part))
+        .collect::<Vec<_>>();
+    let current_zone = match *client.current_zone.lock().await {
+        Some(zone) => JsonValue::from(zone),
+        None => JsonValue::Null,
+    };
+    object! {
+        name: client.name.as_str(),
+        uuid: client.uuid.to_string(),
+        protocol: client.protocol,
+        brand: client.brand.load().as_str(),
+        active_server: client.server_id(),
+        dummy_servers: dummy_servers,
+        position: object! {
+            x: position.x,
+            y: position.y,
+            z: position.z,
+        },
+        dimension: client.dimension.load().as_str(),
+        current_zone: current_zone,
+        settings: object! {
+            locale: settings.locale.as_str(),
+            view_distance: settings.view_distance,
+            chat_mode: format!("{:?}", settings.chat_mode),
+            chat_colors: settings.chat_colors,
+            skin_parts: skin_parts,
+            main_hand: format!("{:?}", settings.main_hand),
+            text_filtering_enabled: settings.text_filtering_enabled,
+        },
+        alive: client.alive.load(Ordering::Relaxed),
+        dead: client.is_dead(),
+        reconnecting: client.is_reconnecting(),
+        known_chunks: client.known_chunks.lock().await.len(),
+        known_eids: client.known_eids.lock().await.len(),
+        last_keep_alive: *client.last_keep_alive.lock().await as u64,
+    }
+}
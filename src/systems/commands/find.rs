@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+/// Most players listed by the find command
+const MAX_FIND_RESULTS: usize = 20;
+
+inventory::submit! {
+    SplinterCommand {
+        name: "find",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected part of a player name");
+            }
+            let partial = args[0].to_lowercase();
+            let mut matches = smol::block_on(proxy.players.read())
+                .iter()
+                .filter(|(name, _)| name.to_lowercase().contains(&partial))
+                .map(|(name, client)| (name.to_owned(), client.server_id()))
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                sender.respond_sync(format!("No players matching \"{}\"", args[0]))?;
+                return Ok(());
+            }
+            matches.sort_by(|(a, _), (b, _)| a.to_lowercase().cmp(&b.to_lowercase()));
+            let total = matches.len();
+            let mut msg = format!(
+                "{} player{} matching \"{}\": {}",
+                total,
+                if total == 1 { "" } else { "s" },
+                args[0],
+                matches
+                    .iter()
+                    .take(MAX_FIND_RESULTS)
+                    .map(|(name, server_id)| format!("{} (server {})", name, server_id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if total > MAX_FIND_RESULTS {
+                msg.push_str(&format!(" and {} more", total - MAX_FIND_RESULTS));
+            }
+            sender.respond_sync(msg)?;
+            Ok(())
+        }),
+    }
+}
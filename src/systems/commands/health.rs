@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use crate::{
+    protocol::describe_version,
+    proxy::{
+        server::{
+            ProtocolSource,
+            SplinterServer,
+        },
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+const HEADERS: [&str; 6] = ["Server", "Reachable", "Latency", "Players", "Version", "Dummies"];
+
+inventory::submit! {
+    SplinterCommand {
+        name: "health",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let mut servers = smol::block_on(proxy.servers.read()).values().map(Arc::clone).collect::<Vec<_>>();
+            servers.sort_by_key(|server| server.id);
+            match args {
+                [] => sender.respond_sync(smol::block_on(health_table(proxy, &servers))),
+                ["check"] => {
+                    sender.respond_sync(format!("Checking {} servers", servers.len()))?;
+                    // a server can take seconds to time out, so the checks run in the background
+                    // and report once they're all done
+                    let proxy = Arc::clone(proxy);
+                    let sender = sender.clone();
+                    smol::spawn(async move {
+                        let checks = servers
+                            .iter()
+                            .map(|server| {
+                                let proxy = Arc::clone(&proxy);
+                                let server = Arc::clone(server);
+                                // failures are recorded as unreachable
+                                smol::spawn(async move {
+                                    let _ = proxy.check_server(&server).await;
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        for check in checks {
+                            check.await;
+                        }
+                        if let Err(e) = sender.respond(health_table(&proxy, &servers).await).await {
+                            error!("Failed to send server health to {}: {}", sender.name(), e);
+                        }
+                    })
+                    .detach();
+                    Ok(())
+                }
+                _ => bail!("Usage: health [check]"),
+            }
+        }),
+    }
+}
+
+/// Formats what's known about the health of each server as an aligned table
+async fn health_table(proxy: &SplinterProxy, servers: &[Arc<SplinterServer>]) -> String {
+    let mut players = HashMap::<u64, usize>::new();
+    let mut dummies = HashMap::<u64, usize>::new();
+    for client in proxy.players.read().await.values() {
+        *players.entry(client.server_id()).or_insert(0) += 1;
+        for (server_id, _) in client.dummy_servers.load().iter() {
+            *dummies.entry(*server_id).or_insert(0) += 1;
+        }
+    }
+    let checks = proxy.server_checks.lock().unwrap().clone();
+    let rows = servers
+        .iter()
+        .map(|server| {
+            let check = checks.get(&server.id);
+            [
+                server.id.to_string(),
+                match check {
+                    Some(check) if check.reachable => "yes".into(),
+                    Some(_) => "no".into(),
+                    None => "unknown".into(),
+                },
+                match check.and_then(|check| check.latency) {
+                    Some(latency) => format!("{}ms", latency.as_millis()),
+                    None => "unknown".into(),
+                },
+                players.get(&server.id).copied().unwrap_or(0).to_string(),
+                match server.protocol_source {
+                    ProtocolSource::Assumed => "unknown".into(),
+                    _ => describe_version(server.protocol),
+                },
+                dummies.get(&server.id).copied().unwrap_or(0).to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut widths = HEADERS.map(str::len);
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: &[String]| {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
+    };
+    let mut lines = vec![format_row(&HEADERS.map(String::from))];
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines.join("\n")
+}
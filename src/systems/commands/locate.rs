@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::{
+        commands::{
+            CommandSender,
+            SplinterCommand,
+        },
+        zoning::{
+            world_to_chunk_position,
+            DEFAULT_DIMENSION,
+        },
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "locate",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 2 && args.len() != 3 {
+                bail!("Expected an x and z coordinate, and optionally a dimension");
+            }
+            let x = args[0].parse::<f64>().map_err(|e| anyhow!("Invalid x coordinate: {}", e))?;
+            let z = args[1].parse::<f64>().map_err(|e| anyhow!("Invalid z coordinate: {}", e))?;
+            let dimension = args.get(2).copied().unwrap_or(DEFAULT_DIMENSION);
+            let chunk_pos = world_to_chunk_position((x, z));
+            let zones = proxy.zoner.load().matching_zones(dimension, chunk_pos);
+            sender.respond_sync(format!(
+                "({}, {}) in {} is in chunk ({}, {}), matching {}",
+                x,
+                z,
+                dimension,
+                chunk_pos.0,
+                chunk_pos.1,
+                if zones.is_empty() {
+                    "no zones".into()
+                } else {
+                    zones
+                        .iter()
+                        .map(|(index, server_id)| format!("zone {} (server {})", index, server_id))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }
+            ))?;
+            sender.respond_sync(match (proxy.server_for_position(dimension, x, z), zones.is_empty()) {
+                (Some(server_id), false) => format!("Resolves to server {}", server_id),
+                (Some(server_id), true) => format!("Resolves to the default server {}", server_id),
+                (None, _) => "Does not resolve to any server".into(),
+            })?;
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "mapping",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            match args {
+                ["verify"] | ["verify", "--fix"] => {
+                    let fix = args.len() == 2;
+                    let map = &mut *smol::block_on(proxy.mapping.lock());
+                    let report = map.verify();
+                    if map.eid_collisions > 0 {
+                        sender.respond_sync(format!(
+                            "{} eid registrations collided with an existing mapping",
+                            map.eid_collisions
+                        ))?;
+                    }
+                    if report.is_consistent() {
+                        sender.respond_sync("Mapping is consistent")?;
+                        return Ok(());
+                    }
+                    sender.respond_sync(format!(
+                        "{} entity data entries without an eid mapping, {} mapped eids not \
+                         reserved in the id generator, {} ids taken from the id generator \
+                         without an eid mapping",
+                        report.orphaned_entity_data.len(),
+                        report.unreserved_eids.len(),
+                        report.leaked_ids.len(),
+                    ))?;
+                    if fix {
+                        map.fix(&report);
+                        sender.respond_sync("Fixed mapping inconsistencies")?;
+                    } else {
+                        sender.respond_sync("Run \"mapping verify --fix\" to fix them")?;
+                    }
+                    Ok(())
+                }
+                ["count"] => {
+                    let outstanding = smol::block_on(proxy.mapping.lock()).outstanding_eids();
+                    sender.respond_sync(format!(
+                        "{} of at most {} eids are mapped",
+                        outstanding, proxy.config.max_eid_mappings
+                    ))?;
+                    Ok(())
+                }
+                _ => bail!("Usage: mapping <verify [--fix]|count>"),
+            }
+        }),
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     sync::Arc,
 };
 
+use arc_swap::ArcSwapOption;
 use blocking::{
     unblock,
     Unblock,
@@ -11,17 +12,60 @@ use mcproto_rs::uuid::UUID4;
 
 use crate::{
     proxy::{
-        chat::ToChat,
+        chat::ToResponse,
         client::SplinterClient,
+        config::AuditLogDestination,
+        logging::append_timestamped_line,
         SplinterProxy,
     },
     systems::SplinterSystem,
 };
 
+mod actionbar;
+mod bossbar;
+mod capture;
+mod chatlog;
+mod clientbrand;
+mod config;
+mod dump;
+mod find;
+mod health;
 mod kick;
 mod list;
+mod locate;
+mod mapping;
+mod mute;
+mod ping;
+mod reconnect;
+mod reload;
+mod reloadzones;
+mod removeserver;
+mod resourcepack;
+mod resyncchunks;
+mod saveinterval;
+mod say;
+mod spawn;
 mod stop;
 mod switch;
+mod tpcoords;
+mod version;
+mod zone;
+
+lazy_static! {
+    /// Where output for the console is also written, if anywhere. Set from the config when the
+    /// console starts, as senders don't have access to the proxy
+    static ref CONSOLE_OUTPUT_LOG: ArcSwapOption<String> = ArcSwapOption::empty();
+}
+
+/// Logs output for the console, also appending it to the console output log if there is one
+async fn console_output(text: String) {
+    info!("{}", &text);
+    if let Some(path) = CONSOLE_OUTPUT_LOG.load_full() {
+        if let Err(e) = unblock(move || append_timestamped_line(&*path, text)).await {
+            error!("Failed to write to console output log: {:?}", e);
+        }
+    }
+}
 
 pub enum CommandSender {
     Player(Arc<SplinterClient>),
@@ -29,16 +73,19 @@ pub enum CommandSender {
 }
 
 impl CommandSender {
-    pub async fn respond(&self, msg: impl ToChat + ToString) -> anyhow::Result<()> {
+    /// Responds with a message, which players get in their locale if it's `Localized`
+    pub async fn respond(&self, msg: impl ToResponse) -> anyhow::Result<()> {
         match self {
-            CommandSender::Player(client) => client.send_message(msg, self).await,
+            CommandSender::Player(client) => {
+                client.send_message(msg.to_player_chat(client), self).await
+            }
             CommandSender::Console => {
-                info!("{}", msg.to_string());
+                console_output(msg.to_console_text()).await;
                 Ok(())
             }
         }
     }
-    pub fn respond_sync(&self, msg: impl ToChat + ToString) -> anyhow::Result<()> {
+    pub fn respond_sync(&self, msg: impl ToResponse) -> anyhow::Result<()> {
         smol::block_on(self.respond(msg))
     }
     pub fn name(&self) -> String {
@@ -47,6 +94,19 @@ impl CommandSender {
             CommandSender::Console => "console".into(),
         }
     }
+    /// Whether the sender may run a command. The console can run every command, while players
+    /// can only run the ones the config allows them
+    pub fn may_run(&self, proxy: &SplinterProxy, cmd: &str) -> bool {
+        match self {
+            CommandSender::Console => true,
+            CommandSender::Player(client) => proxy
+                .config
+                .player_commands
+                .get(&client.name)
+                .map(|allowed| allowed.iter().any(|name| name == "*" || name == cmd))
+                .unwrap_or(false),
+        }
+    }
     pub fn uuid(&self) -> UUID4 {
         match self {
             CommandSender::Player(client) => client.uuid,
@@ -64,6 +124,7 @@ impl Clone for CommandSender {
     }
 }
 
+/// A command's action. Actions are run on a blocking thread, so they can use `smol::block_on`
 pub type CommandFn = Box<
     dyn Send + Sync + Fn(&Arc<SplinterProxy>, &str, &[&str], &CommandSender) -> anyhow::Result<()>,
 >;
@@ -80,15 +141,65 @@ pub async fn process_command(
     args: &[&str],
     sender: &CommandSender,
 ) -> anyhow::Result<()> {
-    if let Some(cmd_data) = inventory::iter::<SplinterCommand>
+    let res = if !sender.may_run(proxy, cmd) {
+        Err(anyhow!("You don't have permission to run \"{}\"", cmd))
+    } else if let Some(cmd_data) = inventory::iter::<SplinterCommand>
         .into_iter()
         .find(|cmd_data| cmd_data.name.eq(cmd))
     {
-        (cmd_data.action)(proxy, cmd, args, sender)?;
+        // actions block on async work, like waiting for a player to answer a ping, which needs
+        // the executor to be free to relay packets in the meantime, so they get a thread of
+        // their own
+        let proxy = Arc::clone(proxy);
+        let cmd = cmd.to_owned();
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let sender = sender.clone();
+        unblock(move || {
+            let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+            (cmd_data.action)(&proxy, &cmd, args.as_slice(), &sender)
+        })
+        .await
     } else {
-        bail!("Unknown command \"{}\"", cmd);
+        Err(anyhow!("Unknown command \"{}\"", cmd))
+    };
+    audit_command(proxy, cmd, args, sender, &res).await;
+    res
+}
+
+/// Records an executed command and its outcome to the configured audit log
+async fn audit_command(
+    proxy: &Arc<SplinterProxy>,
+    cmd: &str,
+    args: &[&str],
+    sender: &CommandSender,
+    res: &anyhow::Result<()>,
+) {
+    let destination = &proxy.config.command_audit_log;
+    if let AuditLogDestination::Disabled = destination {
+        return;
+    }
+    let outcome = match res {
+        Ok(()) => "succeeded".into(),
+        Err(e) => format!("failed: {:?}", e),
+    };
+    let entry = format!(
+        "{} ({}) ran command \"{}\" with args {:?}, which {}",
+        sender.name(),
+        sender.uuid(),
+        cmd,
+        args,
+        outcome
+    );
+    match destination {
+        AuditLogDestination::Disabled => {}
+        AuditLogDestination::MainLog => info!("[audit] {}", entry),
+        AuditLogDestination::File(path) => {
+            let path = path.clone();
+            if let Err(e) = unblock(move || append_timestamped_line(path, entry)).await {
+                error!("Failed to write to command audit log: {:?}", e);
+            }
+        }
     }
-    Ok(())
 }
 
 inventory::submit! {
@@ -101,6 +212,12 @@ inventory::submit! {
 }
 
 async fn init(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+    // scheduled commands respond to the console too, so this applies even without stdin
+    CONSOLE_OUTPUT_LOG.store(proxy.config.console_output_log.clone().map(Arc::new));
+    if !proxy.config.console_enabled {
+        info!("Console is disabled, not listening for commands on stdin");
+        return Ok(());
+    }
     let mut stdin = Unblock::new(unblock(io::stdin).await);
     smol::spawn(async move {
         loop {
@@ -108,13 +225,20 @@ async fn init(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
                 .with_mut(|stdin| {
                     let mut line = String::new();
                     match stdin.read_line(&mut line) {
-                        Ok(_) => Ok(line),
+                        // nothing read means the end of stdin, where every read returns right
+                        // away, so keeping on reading would spin
+                        Ok(0) => Ok(None),
+                        Ok(_) => Ok(Some(line)),
                         Err(e) => Err(e),
                     }
                 })
                 .await
             {
-                Ok(line) => line,
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    info!("Reached the end of stdin, no longer listening for console commands");
+                    break;
+                }
                 Err(e) => {
                     error!("Failed to read line from stdin: {}", e);
                     break;
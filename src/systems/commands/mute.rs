@@ -0,0 +1,87 @@
+use std::{
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::{
+    proxy::{
+        client::Mute,
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "mute",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.is_empty() || args.len() > 2 {
+                bail!("Expected a player name and optionally a duration (ex. 30s, 10m, 2h, 1d)");
+            }
+            let duration = args.get(1).map(|arg| parse_duration(arg)).transpose()?;
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            let expires = duration
+                .map(|duration| {
+                    Instant::now()
+                        .checked_add(duration)
+                        .ok_or_else(|| anyhow!("Duration {:?} is too long", duration))
+                })
+                .transpose()?;
+            *smol::block_on(client.mute.lock()) = Some(Mute { expires });
+            sender.respond_sync(match duration {
+                Some(duration) => format!("Muted {} for {:?}", &client.name, duration),
+                None => format!("Muted {}", &client.name),
+            })?;
+            Ok(())
+        }),
+    }
+}
+
+inventory::submit! {
+    SplinterCommand {
+        name: "unmute",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            if smol::block_on(client.mute.lock()).take().is_some() {
+                sender.respond_sync(format!("Unmuted {}", &client.name))?;
+            } else {
+                sender.respond_sync(format!("{} is not muted", &client.name))?;
+            }
+            Ok(())
+        }),
+    }
+}
+
+/// Parses a duration written as a number followed by a unit of s, m, h, or d. A number alone is
+/// taken as seconds
+fn parse_duration(text: &str) -> anyhow::Result<Duration> {
+    let (number, unit_secs) = match text.char_indices().last() {
+        Some((index, 's')) => (&text[..index], 1),
+        Some((index, 'm')) => (&text[..index], 60),
+        Some((index, 'h')) => (&text[..index], 60 * 60),
+        Some((index, 'd')) => (&text[..index], 60 * 60 * 24),
+        _ => (text, 1),
+    };
+    let number = number
+        .parse::<u64>()
+        .map_err(|e| anyhow!("Invalid duration \"{}\": {}", text, e))?;
+    let secs = number
+        .checked_mul(unit_secs)
+        .ok_or_else(|| anyhow!("Duration \"{}\" is too long", text))?;
+    Ok(Duration::from_secs(secs))
+}
@@ -0,0 +1,33 @@
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+/// How long to wait for a player to answer a ping
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+inventory::submit! {
+    SplinterCommand {
+        name: "ping",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            let latency = smol::block_on(client.ping(PING_TIMEOUT))?;
+            sender.respond_sync(format!("{}'s latency is {}ms", &client.name, latency.as_millis()))?;
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "reconnect",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            if client.is_reconnecting() {
+                bail!("{} is already reconnecting", &client.name);
+            }
+            smol::block_on(client.soft_reconnect())?;
+            sender.respond_sync(format!(
+                "Reconnected {} to server {}",
+                &client.name,
+                client.server_id()
+            ))?;
+            Ok(())
+        }),
+    }
+}
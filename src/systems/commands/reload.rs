@@ -0,0 +1,44 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use anyhow::Context;
+
+use crate::{
+    protocol::{
+        describe_version,
+        v_cur::IdNameMaps,
+    },
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "reload",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            match args.get(0) {
+                Some(&"data") => {
+                    let id_maps = &mut *smol::block_on(proxy.id_maps.lock());
+                    // every version loaded so far is reloaded, so that none are left with stale data
+                    let mut reloaded = HashMap::new();
+                    for protocol in id_maps.keys() {
+                        let maps = IdNameMaps::load(&proxy.config, *protocol).with_context(|| {
+                            format!("Failed to reload data files for {}", describe_version(*protocol))
+                        })?;
+                        reloaded.insert(*protocol, Arc::new(maps));
+                    }
+                    *id_maps = reloaded;
+                    sender.respond_sync("Reloaded block, item, and entity data")?;
+                },
+                Some(_) => bail!("Unknown subcommand"),
+                None => bail!("Expected a subcommand"),
+            }
+            Ok(())
+        }),
+    }
+}
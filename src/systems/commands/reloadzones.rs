@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+
+use crate::{
+    proxy::{
+        config::SplinterConfig,
+        SplinterProxy,
+    },
+    systems::{
+        commands::{
+            CommandSender,
+            SplinterCommand,
+        },
+        zoning::{
+            update_client_servers,
+            validate_zone_servers,
+            Zoner,
+        },
+    },
+    CONFIG_FILENAME,
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "reloadzones",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if !args.is_empty() {
+                bail!("Usage: reloadzones");
+            }
+            // only the zones are taken from the file, the rest of the config stays as it was loaded
+            let config = SplinterConfig::from_file(CONFIG_FILENAME)
+                .with_context(|| format!("Failed to read \"{}\"", CONFIG_FILENAME))?;
+            validate_zone_servers(&config.dimension_zones, &smol::block_on(proxy.servers.read()))
+                .context("Not reloading zones")?;
+            let dimension_count = config.dimension_zones.len();
+            proxy.zoner.rcu(|zoner| Zoner {
+                zones: zoner.zones.clone(),
+                dimension_zones: config.dimension_zones.clone(),
+            });
+            sender.respond_sync(format!("Reloaded zones for {} dimensions", dimension_count))?;
+            // move players off of servers that no longer own where they are right away, rather
+            // than waiting for the zoner to come around. Switching waits on new connections, so
+            // it happens in the background
+            let proxy = Arc::clone(proxy);
+            smol::spawn(async move {
+                let clients = proxy.players.read().await.values().map(Arc::clone).collect::<Vec<_>>();
+                for client in clients.iter() {
+                    update_client_servers(&proxy, client).await;
+                }
+            })
+            .detach();
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "removeserver",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let server_id = match args {
+                [server_id] => server_id
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Invalid server id \"{}\"", server_id))?,
+                _ => bail!("Usage: removeserver <server id>"),
+            };
+            sender.respond_sync(format!("Removing server {}", server_id))?;
+            // draining players off of the server waits on their new connections
+            let proxy = Arc::clone(proxy);
+            let sender = sender.clone();
+            smol::spawn(async move {
+                let res = match proxy.remove_server(server_id).await {
+                    Ok(()) => sender.respond(format!("Removed server {} and its zones", server_id)).await,
+                    Err(e) => sender.respond(format!("Failed to remove server {}: {:?}", server_id, e)).await,
+                };
+                if let Err(e) = res {
+                    error!("Failed to send server removal result to {}: {}", sender.name(), e);
+                }
+            })
+            .detach();
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "resourcepack",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let player_map = smol::block_on(proxy.players.read());
+            let client = player_map.get(args[0]).ok_or_else(|| anyhow!("Failed to find player"))?;
+            let status = *smol::block_on(client.resource_pack_status.lock());
+            sender.respond_sync(match status {
+                Some(status) => format!("{}'s last resource pack status is {:?}", &client.name, status),
+                None => format!("{} has not responded to a resource pack", &client.name),
+            })?;
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::{
+        chat::Localized,
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "resyncchunks",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            // resyncing waits on the servers to unload chunks, so it happens in the background
+            let sender = sender.clone();
+            smol::spawn(async move {
+                let res = match client.resync_chunks().await {
+                    Ok(chunk_count) => {
+                        sender
+                            .respond(Localized {
+                                key: "resyncchunks_done",
+                                fallback: "Cleared {count} known chunks for {player} and asked their servers to resend them",
+                                args: &[("count", &chunk_count.to_string()), ("player", &client.name)],
+                            })
+                            .await
+                    }
+                    Err(e) => sender.respond(format!("Failed to resync chunks for {}: {:?}", &client.name, e)).await,
+                };
+                if let Err(e) = res {
+                    error!("Failed to send chunk resync result to {}: {}", sender.name(), e);
+                }
+            })
+            .detach();
+            Ok(())
+        }),
+    }
+}
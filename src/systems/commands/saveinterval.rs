@@ -0,0 +1,54 @@
+use std::sync::{
+    atomic::Ordering,
+    Arc,
+};
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::{
+        commands::{
+            CommandSender,
+            SplinterCommand,
+        },
+        playersave::{
+            clamp_save_interval,
+            MIN_SAVE_INTERVAL_SECS,
+        },
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "saveinterval",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let current = proxy.save_interval_secs.load(Ordering::Relaxed);
+            let secs = match args {
+                [] => {
+                    sender.respond_sync(if current == 0 {
+                        "Player data is not being saved periodically".into()
+                    } else {
+                        format!("Player data is saved every {} seconds", current)
+                    })?;
+                    return Ok(());
+                }
+                [secs] => secs
+                    .parse::<u64>()
+                    .map_err(|e| anyhow!("Invalid number of seconds: {}", e))?,
+                _ => bail!("Expected the number of seconds between saves, or 0 to disable saving"),
+            };
+            let clamped = clamp_save_interval(secs);
+            proxy.save_interval_secs.store(clamped, Ordering::Relaxed);
+            sender.respond_sync(if clamped == 0 {
+                "Disabled saving player data periodically".into()
+            } else if clamped != secs {
+                format!(
+                    "Player data will be saved every {} seconds, the minimum interval",
+                    MIN_SAVE_INTERVAL_SECS
+                )
+            } else {
+                format!("Player data will be saved every {} seconds", clamped)
+            })?;
+            Ok(())
+        }),
+    }
+}
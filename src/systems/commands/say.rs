@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::{
+        chat::{
+            broadcast_message,
+            format_broadcast,
+        },
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "say",
+        action: Box::new(say),
+    }
+}
+
+inventory::submit! {
+    SplinterCommand {
+        name: "broadcast",
+        action: Box::new(say),
+    }
+}
+
+fn say(
+    proxy: &Arc<SplinterProxy>,
+    _cmd: &str,
+    args: &[&str],
+    sender: &CommandSender,
+) -> anyhow::Result<()> {
+    if args.is_empty() {
+        bail!("Expected a message");
+    }
+    let message = format_broadcast(&proxy.config.broadcast_format, sender, &args.join(" "));
+    info!("[{}] {}", sender.name(), args.join(" "));
+    smol::block_on(broadcast_message(proxy, sender, message));
+    Ok(())
+}
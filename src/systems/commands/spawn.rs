@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use crate::{
+    protocol::{
+        current::types::Vec3,
+        v_cur::send_position_set,
+    },
+    proxy::SplinterProxy,
+    systems::{
+        commands::{
+            CommandSender,
+            SplinterCommand,
+        },
+        zoning::DEFAULT_DIMENSION,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "spawn",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            if args.len() != 1 {
+                bail!("Expected a player name");
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(args[0])
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            let dimension = client.dimension.load();
+            // the dimension is unknown until the first respawn or join game is seen
+            if !dimension.is_empty() && **dimension != DEFAULT_DIMENSION {
+                bail!("{} is not in the overworld, where the spawn is", &client.name);
+            }
+            let (x, y, z) = proxy.config.spawn_position;
+            let target_id = proxy
+                .server_for_position(DEFAULT_DIMENSION, x, z)
+                .ok_or_else(|| anyhow!("No server owns the spawn position"))?;
+            smol::block_on(async {
+                // set first so that the zoner doesn't switch the player back, and so that the new
+                // server's player isn't moved back to the old position
+                client.update_position(Vec3 { x, y, z });
+                if target_id != client.server_id() {
+                    // another server owns the spawn, so the player has to be moved onto it rather
+                    // than teleported into its region on this one
+                    if !client.dummy_servers.load().iter().any(|(id, _)| *id == target_id) {
+                        client.connect_dummy(target_id).await?;
+                    }
+                    client.swap_dummy(target_id).await?;
+                }
+                send_position_set(&mut *client.active_server.load().writer.lock().await, x, y, z)
+                    .await
+            })?;
+            sender.respond_sync(format!(
+                "Sent {} to spawn on server {}",
+                &client.name, target_id
+            ))?;
+            Ok(())
+        }),
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use crate::{
+    protocol::{
+        current::types::Vec3,
+        v_cur::send_position_set,
+    },
+    proxy::SplinterProxy,
+    systems::{
+        commands::{
+            CommandSender,
+            SplinterCommand,
+        },
+        playersave::validate_position,
+        zoning::world_to_chunk_position,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "tpcoords",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let (name, target_id, x, y, z) = match args {
+                [name, target_id, x, y, z] => (
+                    *name,
+                    target_id.parse::<u64>().map_err(|_| anyhow!("Invalid server id \"{}\"", target_id))?,
+                    x.parse::<f64>().map_err(|_| anyhow!("Invalid x coordinate \"{}\"", x))?,
+                    y.parse::<f64>().map_err(|_| anyhow!("Invalid y coordinate \"{}\"", y))?,
+                    z.parse::<f64>().map_err(|_| anyhow!("Invalid z coordinate \"{}\"", z))?,
+                ),
+                _ => bail!("Usage: tpcoords <player> <server id> <x> <y> <z>"),
+            };
+            validate_position((x, y, z))?;
+            if !smol::block_on(proxy.servers.read()).contains_key(&target_id) {
+                bail!("No server with id {}", target_id);
+            }
+            let client = smol::block_on(proxy.players.read())
+                .get(name)
+                .map(Arc::clone)
+                .ok_or_else(|| anyhow!("Failed to find player"))?;
+            let dimension = client.dimension.load();
+            let owners = proxy
+                .zoner
+                .load()
+                .zones_in_point(&dimension, world_to_chunk_position((x, z)));
+            if !owners.contains(&target_id) {
+                // the zoner will likely move them onto a server that owns the position
+                sender.respond_sync(format!(
+                    "Warning: server {} doesn't own ({}, {}, {}), so {} may be switched off of it",
+                    target_id, x, y, z, &client.name
+                ))?;
+            }
+            smol::block_on(async {
+                // set first so that the new server's player isn't moved back to the old position
+                client.update_position(Vec3 { x, y, z });
+                if target_id != client.server_id() {
+                    if !client.dummy_servers.load().iter().any(|(id, _)| *id == target_id) {
+                        client.connect_dummy(target_id).await?;
+                    }
+                    client.swap_dummy(target_id).await?;
+                }
+                // the swap has finished, so this goes to the target server
+                send_position_set(&mut *client.active_server.load().writer.lock().await, x, y, z)
+                    .await
+            })?;
+            sender.respond_sync(format!(
+                "Sent {} to ({}, {}, {}) on server {}",
+                &client.name, x, y, z, target_id
+            ))?;
+            Ok(())
+        }),
+    }
+}
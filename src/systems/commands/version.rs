@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::{
+        build_info,
+        SplinterProxy,
+    },
+    systems::commands::{
+        CommandSender,
+        SplinterCommand,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "version",
+        action: Box::new(|_proxy: &Arc<SplinterProxy>, _cmd: &str, _args: &[&str], sender: &CommandSender| {
+            sender.respond_sync(build_info())
+        }),
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::{
+        commands::{
+            CommandSender,
+            SplinterCommand,
+        },
+        zoning::Zone,
+    },
+};
+
+inventory::submit! {
+    SplinterCommand {
+        name: "zone",
+        action: Box::new(|proxy: &Arc<SplinterProxy>, _cmd: &str, args: &[&str], sender: &CommandSender| {
+            let zoner = proxy.zoner.load();
+            match args {
+                ["list"] => {
+                    respond_zones(sender, "default", &zoner.zones)?;
+                    let mut dimensions = zoner.dimension_zones.iter().collect::<Vec<_>>();
+                    dimensions.sort_by_key(|(dimension, _)| *dimension);
+                    for (dimension, zones) in dimensions {
+                        respond_zones(sender, dimension, zones)?;
+                    }
+                    Ok(())
+                }
+                ["list", dimension] => {
+                    respond_zones(sender, dimension, zoner.profile(dimension))
+                }
+                _ => bail!("Usage: zone list [dimension]"),
+            }
+        }),
+    }
+}
+
+/// Responds with each zone of a profile on its own line, in priority order
+fn respond_zones(sender: &CommandSender, profile: &str, zones: &[(u64, Zone)]) -> anyhow::Result<()> {
+    sender.respond_sync(format!(
+        "{} zone{} in the {} profile (coordinates in chunks, upper bounds exclusive):",
+        zones.len(),
+        if zones.len() == 1 { "" } else { "s" },
+        profile
+    ))?;
+    for (index, (server_id, zone)) in zones.iter().enumerate() {
+        sender.respond_sync(format!("{}: {} -> server {}", index, zone, server_id))?;
+    }
+    Ok(())
+}
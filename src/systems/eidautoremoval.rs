@@ -26,6 +26,9 @@ async fn eid_auto_removal_loop(proxy: Arc<SplinterProxy>) -> anyhow::Result<()>
             Timer::after(Duration::from_secs(15)).await;
             let mut total_used_eids = HashSet::<i32>::new();
             for (_, cl) in proxy.players.read().await.iter() {
+                // a client's own player is never spawned on it, so it may not be in its known
+                // eids, but the mapping is needed for as long as the client is around
+                total_used_eids.insert(cl.proxy_eid);
                 let cl_known_eids = cl.known_eids.lock().await;
                 for eid in cl_known_eids.iter() {
                     total_used_eids.insert(*eid);
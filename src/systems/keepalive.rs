@@ -3,9 +3,12 @@ use std::{
     sync::{atomic::Ordering, Arc},
     time::{Duration, SystemTime},
 };
+#[cfg(test)]
+use std::sync::atomic::AtomicU64;
 
 use anyhow::Context;
 use craftio_rs::{CraftAsyncReader, CraftAsyncWriter};
+use futures_lite::future::{self, Future};
 use mcproto_rs::protocol::PacketDirection;
 use smol::Timer;
 
@@ -17,7 +20,9 @@ use crate::{
             PacketLatest, PacketLatestKind, RawPacketLatest,
         },
         events::LazyDeserializedPacket,
-        v_cur::{has_eids, map_eid, send_packet, send_position_set},
+        v_cur::{
+            has_eids, is_mount_packet, map_eid, send_packet, send_position_set,
+        },
         PacketDestination,
     },
     proxy::{
@@ -30,12 +35,59 @@ inventory::submit! {
     SplinterSystem {
         name: "Keep Alive",
         init: Box::new(|proxy| {
-            Box::pin(keep_alive_loop(proxy))
+            Box::pin(keep_alive_loop(proxy, SystemClock))
         })
     }
 }
 
-async fn keep_alive_loop(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+/// How long a client has to respond to keep alives before it is kicked
+pub const KEEP_ALIVE_TIMEOUT_MILLIS: u128 = 30 * 1000;
+
+/// Source of the current time for keep alive and timeout logic
+pub trait Clock {
+    /// Gets the current unix time in milliseconds
+    fn unix_time_millis(&self) -> u128;
+}
+
+/// Clock backed by the system time
+#[derive(Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    #[inline]
+    fn unix_time_millis(&self) -> u128 {
+        unix_time_millis()
+    }
+}
+
+/// Clock that only moves when told to, for testing timeouts
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockClock {
+    millis: AtomicU64,
+}
+#[cfg(test)]
+impl MockClock {
+    pub fn new(millis: u64) -> Self {
+        Self {
+            millis: AtomicU64::new(millis),
+        }
+    }
+    pub fn advance(&self, duration: Duration) {
+        self.millis
+            .fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+#[cfg(test)]
+impl Clock for MockClock {
+    fn unix_time_millis(&self) -> u128 {
+        self.millis.load(Ordering::Relaxed) as u128
+    }
+}
+
+async fn keep_alive_loop(
+    proxy: Arc<SplinterProxy>,
+    clock: impl Clock + Send + 'static,
+) -> anyhow::Result<()> {
     smol::spawn(async move {
         loop {
             Timer::after(Duration::from_secs(15)).await;
@@ -46,21 +98,8 @@ async fn keep_alive_loop(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
                 .iter()
                 .map(|(_, client)| Arc::clone(client))
                 .collect::<Vec<_>>();
-            let keep_alive_millis = unix_time_millis();
-            for client in players.iter() {
-                if keep_alive_millis - *client.last_keep_alive.lock().await > 30 * 1000 {
-                    // client connection time out
-                    if let Err(e) = proxy
-                        .kick_client(&client.name, ClientKickReason::TimedOut)
-                        .await
-                    {
-                        error!(
-                            "Error while kicking timed out client \"{}\": {}",
-                            &client.name, e
-                        );
-                    }
-                }
-            }
+            kick_timed_out_clients(&proxy, &players, &clock).await;
+            let keep_alive_millis = clock.unix_time_millis();
             let send_futs = players
                 .iter()
                 .map(|client| {
@@ -84,6 +123,49 @@ async fn keep_alive_loop(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Kicks the clients that have not responded to a keep alive within the timeout
+pub async fn kick_timed_out_clients(
+    proxy: &Arc<SplinterProxy>,
+    players: &[Arc<SplinterClient>],
+    clock: &impl Clock,
+) {
+    for client in players.iter() {
+        if client
+            .keep_alive_timed_out(clock, KEEP_ALIVE_TIMEOUT_MILLIS)
+            .await
+        {
+            // client connection time out
+            if let Err(e) = proxy
+                .kick_client(&client.name, ClientKickReason::TimedOut)
+                .await
+            {
+                error!(
+                    "Error while kicking timed out client \"{}\": {}",
+                    &client.name, e
+                );
+            }
+        }
+    }
+}
+
+/// Waits for a read from a server connection, giving up once the server has been silent for
+/// longer than the configured timeout. Returns `None` if it gave up
+pub async fn read_before_silence_timeout<T>(
+    proxy: &SplinterProxy,
+    read: impl Future<Output = T>,
+) -> Option<T> {
+    match proxy.config.backend_silence_timeout_secs {
+        Some(timeout_secs) => {
+            future::or(async { Some(read.await) }, async {
+                Timer::after(Duration::from_secs_f64(timeout_secs)).await;
+                None
+            })
+            .await
+        }
+        None => Some(read.await),
+    }
+}
+
 /// Gets the current unix time in milliseconds
 pub fn unix_time_millis() -> u128 {
     match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
@@ -109,7 +191,15 @@ pub async fn watch_dummy(client: Arc<SplinterClient>, dummy_conn: Arc<SplinterSe
                 break debug!("dummy conn {} no longer alive", dummy_conn.server.id);
             }
             let mut lock = dummy_conn.reader.lock().await;
-            let raw_packet = match lock.read_raw_packet_async::<RawPacketLatest>().await {
+            let read = lock.read_raw_packet_async::<RawPacketLatest>();
+            let read = match read_before_silence_timeout(&client.proxy, read).await {
+                Some(read) => read,
+                None => {
+                    dummy_conn.alive.store(false, Ordering::Relaxed);
+                    break warn!("Server {} went silent on dummy connection for \"{}\", closing it", dummy_conn.server.id, &client.name);
+                }
+            };
+            let raw_packet = match read {
                 Ok(Some(packet)) => packet,
                 Ok(None) => {
                     dummy_conn.alive.store(false, Ordering::Relaxed);
@@ -120,16 +210,20 @@ pub async fn watch_dummy(client: Arc<SplinterClient>, dummy_conn: Arc<SplinterSe
                     continue;
                 },
             };
+            client.capture_packet(&PacketDirection::ClientBound, dummy_conn.server.id, &raw_packet).await;
             let mut lazy_packet = LazyDeserializedPacket::from_raw_packet(raw_packet);
             let packet_kind = lazy_packet.kind();
             let mut pass_through = false;
+            dummy_conn.track_resync_lazy(&client.proxy.resync_kinds, &mut lazy_packet).await;
             if matches!(packet_kind,
                 PacketLatestKind::PlayServerKeepAlive
                 | PacketLatestKind::PlayChunkData
                 | PacketLatestKind::PlayUpdateLight
                 | PacketLatestKind::PlayUnloadChunk
                 | PacketLatestKind::PlayServerPlayerPositionAndLook
-                | PacketLatestKind::PlayServerPluginMessage) {
+                | PacketLatestKind::PlayServerPluginMessage
+                | PacketLatestKind::PlayUpdateHealth
+                | PacketLatestKind::PlayRespawn) {
                 match lazy_packet.packet() {
                     Ok(packet) => match packet {
                         PacketLatest::PlayServerKeepAlive(body) => {
@@ -179,6 +273,14 @@ pub async fn watch_dummy(client: Arc<SplinterClient>, dummy_conn: Arc<SplinterSe
                                 }
                             }
                         },
+                        PacketLatest::PlayUpdateHealth(body) => {
+                            if body.health <= 0. {
+                                dummy_conn.awaiting_respawn.store(true, Ordering::Relaxed);
+                            }
+                        },
+                        PacketLatest::PlayRespawn(_body) => {
+                            dummy_conn.awaiting_respawn.store(false, Ordering::Relaxed);
+                        },
                         PacketLatest::PlayServerPluginMessage(_body) => {
                             // if body.channel == "splinter:splinter" {
                             //     match body.data.data[0] {
@@ -207,10 +309,18 @@ pub async fn watch_dummy(client: Arc<SplinterClient>, dummy_conn: Arc<SplinterSe
                     }
                 }
             }
+            // what the player rides on the dummy's server is needed to put it back on when
+            // swapping to it
+            if is_mount_packet(lazy_packet.kind()) {
+                if let Ok(packet) = lazy_packet.packet() {
+                    client.track_mount(&dummy_conn, packet).await;
+                }
+            }
             if has_eids(lazy_packet.kind()) {
                 if let Ok(packet) = lazy_packet.packet() {
                     let map = &mut *client.proxy.mapping.lock().await;
-                    pass_through = pass_through || SplinterMappingResult::Client == map_eid(&*client, map, packet, &PacketDirection::ClientBound, &dummy_conn.server);
+                    let known_eids = &mut *client.known_eids.lock().await;
+                    pass_through = pass_through || SplinterMappingResult::Client == map_eid(known_eids, map, packet, &PacketDirection::ClientBound, dummy_conn.server.id);
                 }
             }
             if pass_through {
@@ -231,3 +341,44 @@ pub async fn watch_dummy(client: Arc<SplinterClient>, dummy_conn: Arc<SplinterSe
     })
     .detach()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::proxy::testing::client_with_dummies;
+
+    #[test]
+    fn unresponsive_client_is_kicked_after_timeout() {
+        smol::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = client_with_dummies(&listener, &[1]).await;
+            let proxy = Arc::clone(&client.proxy);
+            proxy
+                .players
+                .write()
+                .await
+                .insert(client.name.clone(), Arc::clone(&client));
+            let players = vec![Arc::clone(&client)];
+            let clock = MockClock::new(1_000_000);
+            client.record_keep_alive(&clock).await;
+
+            clock.advance(Duration::from_millis(KEEP_ALIVE_TIMEOUT_MILLIS as u64));
+            kick_timed_out_clients(&proxy, &players, &clock).await;
+            assert!(proxy.players.read().await.contains_key(&client.name));
+            assert!(client.alive.load(Ordering::Relaxed));
+
+            // answering resets the timeout
+            client.record_keep_alive(&clock).await;
+            clock.advance(Duration::from_millis(KEEP_ALIVE_TIMEOUT_MILLIS as u64));
+            kick_timed_out_clients(&proxy, &players, &clock).await;
+            assert!(proxy.players.read().await.contains_key(&client.name));
+
+            clock.advance(Duration::from_millis(1));
+            kick_timed_out_clients(&proxy, &players, &clock).await;
+            assert!(!proxy.players.read().await.contains_key(&client.name));
+            assert!(!client.alive.load(Ordering::Relaxed));
+        });
+    }
+}
@@ -7,10 +7,16 @@ use smol::prelude::Future;
 
 use crate::proxy::SplinterProxy;
 
+pub mod autoscale;
+pub mod backendcheck;
+pub mod chunkcompaction;
 pub mod commands;
 pub mod eidautoremoval;
 pub mod keepalive;
 pub mod playersave;
+pub mod protocoldetect;
+pub mod routing;
+pub mod scheduler;
 pub mod zoning;
 
 pub type SystemInitFn = Box<
@@ -1,16 +1,22 @@
 use std::{
     collections::HashMap,
     fs::{self, File},
-    io::Write,
+    io::{Read, Write},
+    path::Path,
     sync::{atomic::Ordering, Arc},
     time::Duration,
 };
 
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 use smol::Timer;
 
-use crate::{protocol::current::uuid::UUID4, proxy::SplinterProxy, systems::SplinterSystem};
+use crate::{
+    protocol::current::uuid::UUID4,
+    proxy::{client::SplinterClient, SplinterProxy},
+    systems::{keepalive::unix_time_millis, SplinterSystem},
+};
 
 inventory::submit! {
     SplinterSystem {
@@ -29,19 +35,69 @@ inventory::submit! {
 }
 
 pub const PLAYER_DATA_FILENAME: &str = "./playerdata.ron";
+pub const COMPRESSED_PLAYER_DATA_FILENAME: &str = "./playerdata.ron.gz";
+/// Magic bytes every gzip file starts with
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Shortest time between saves of player data, in seconds, so that the disk isn't saved to
+/// constantly
+pub const MIN_SAVE_INTERVAL_SECS: u64 = 10;
 pub const DEFAULT_SPAWN_POSITION: (f64, f64, f64) = (0., 8., 0.);
+/// Farthest a player can be from the center of the world on the x and z axes
+const MAX_HORIZONTAL_COORDINATE: f64 = 30_000_000.;
+/// Lowest and highest y coordinates any dimension can be built in
+const MIN_Y_COORDINATE: f64 = -2032.;
+const MAX_Y_COORDINATE: f64 = 2031.;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PlInfoPlayer {
     pub x: f64,
     pub y: f64,
     pub z: f64,
     pub name: String,
+    /// Unix time in seconds of when the player was last online. Zero if unknown
+    #[serde(default)]
+    pub last_seen: u64,
+    /// Dimension the player was last in. Unknown for players saved before it was tracked
+    #[serde(default)]
+    pub dimension: Option<String>,
+}
+impl PlInfoPlayer {
+    /// Creates a record of a client's current state, marking them as seen now
+    pub fn from_client(client: &SplinterClient) -> PlInfoPlayer {
+        let pos = &**client.position.load();
+        PlInfoPlayer {
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            name: client.name.clone(),
+            last_seen: unix_time_secs(),
+            dimension: Some(String::clone(&client.dimension.load())).filter(|d| !d.is_empty()),
+        }
+    }
 }
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PlInfo {
     pub players: HashMap<UUID4, PlInfoPlayer>,
 }
+impl PlInfo {
+    /// Removes players who have not been seen within the given number of days. Returns the number
+    /// of players removed
+    ///
+    /// Players without a last seen time (saved before it was tracked) are marked as seen now
+    /// instead of being removed.
+    pub fn prune(&mut self, max_age_days: u64) -> usize {
+        let now = unix_time_secs();
+        let max_age_secs = max_age_days.saturating_mul(24 * 60 * 60);
+        let count_before = self.players.len();
+        self.players.retain(|_, player| {
+            if player.last_seen == 0 {
+                player.last_seen = now;
+            }
+            now.saturating_sub(player.last_seen) <= max_age_secs
+        });
+        count_before - self.players.len()
+    }
+}
 impl Default for PlInfo {
     fn default() -> PlInfo {
         PlInfo {
@@ -51,25 +107,127 @@ impl Default for PlInfo {
 }
 
 pub async fn player_save_loop(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+    if !proxy.config.player_data_enabled {
+        info!("Player data is disabled; not saving player data");
+        return Ok(());
+    }
     loop {
         if !proxy.alive.load(Ordering::Relaxed) {
             break;
         }
-        Timer::after(Duration::from_secs(30)).await;
-        if let Err(e) = save_player_data(&*proxy.player_data.lock().await, PLAYER_DATA_FILENAME) {
+        // read every time so that changes to the interval apply after the current wait
+        let interval = proxy.save_interval_secs.load(Ordering::Relaxed);
+        if interval == 0 {
+            // check again later in case saving is turned back on
+            Timer::after(Duration::from_secs(1)).await;
+            continue;
+        }
+        Timer::after(Duration::from_secs(interval)).await;
+        if proxy.save_interval_secs.load(Ordering::Relaxed) == 0 {
+            continue;
+        }
+        if let Some(max_age_days) = proxy.config.player_data_max_age_days {
+            prune_player_data(&mut *proxy.player_data.lock().await, max_age_days);
+        }
+        if let Err(e) = save_player_data(
+            &*proxy.player_data.lock().await,
+            player_data_filename(proxy.config.compress_player_data),
+        ) {
             error!("Player Saver error when reading file: {:?}", e);
         }
     }
     Ok(())
 }
+/// Raises a save interval to the minimum if it is too short. Zero is left as is, since it disables
+/// saving
+pub fn clamp_save_interval(secs: u64) -> u64 {
+    if secs == 0 {
+        0
+    } else {
+        secs.max(MIN_SAVE_INTERVAL_SECS)
+    }
+}
+
+/// Checks that a position is a place players can actually be in the world
+pub fn validate_position(position: (f64, f64, f64)) -> anyhow::Result<()> {
+    let (x, y, z) = position;
+    if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+        bail!("position {:?} is not a finite position", position);
+    }
+    if x.abs() > MAX_HORIZONTAL_COORDINATE || z.abs() > MAX_HORIZONTAL_COORDINATE {
+        bail!(
+            "position {:?} is more than {} blocks from the center of the world",
+            position,
+            MAX_HORIZONTAL_COORDINATE
+        );
+    }
+    if !(MIN_Y_COORDINATE..=MAX_Y_COORDINATE).contains(&y) {
+        bail!(
+            "position {:?} is outside of the y coordinates {} to {}",
+            position,
+            MIN_Y_COORDINATE,
+            MAX_Y_COORDINATE
+        );
+    }
+    Ok(())
+}
+/// Gets the current unix time in seconds
+pub fn unix_time_secs() -> u64 {
+    (unix_time_millis() / 1000) as u64
+}
+/// Prunes players not seen within the given number of days, logging how many were removed
+pub fn prune_player_data(info: &mut PlInfo, max_age_days: u64) {
+    let pruned = info.prune(max_age_days);
+    if pruned > 0 {
+        info!(
+            "Pruned {} player data entries not seen in the last {} days",
+            pruned, max_age_days
+        );
+    }
+}
+/// Gets the file player data is saved to
+pub fn player_data_filename(compress: bool) -> &'static str {
+    if compress {
+        COMPRESSED_PLAYER_DATA_FILENAME
+    } else {
+        PLAYER_DATA_FILENAME
+    }
+}
+/// Loads player data from the file it is saved to. If that file doesn't exist, the file used when
+/// `compress` is the other way is loaded instead, so existing player data is kept when the option
+/// is changed
+pub fn load_saved_player_data(compress: bool) -> anyhow::Result<PlInfo> {
+    let filename = player_data_filename(compress);
+    if Path::new(filename).exists() {
+        load_player_data(filename)
+    } else {
+        load_player_data(player_data_filename(!compress))
+    }
+}
+/// Loads player data from a file, which may be plain or gzipped RON
 pub fn load_player_data(filename: impl AsRef<str>) -> anyhow::Result<PlInfo> {
-    let existing_file = fs::read_to_string(filename.as_ref())?;
+    let bytes = fs::read(filename.as_ref())?;
+    let existing_file = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut decompressed)?;
+        decompressed
+    } else {
+        String::from_utf8(bytes)?
+    };
     let existing_plinfo: PlInfo = ron::de::from_str(&existing_file)?;
     Ok(existing_plinfo)
 }
+/// Saves player data to a file, gzipped if the file name ends in ".gz"
 pub fn save_player_data(info: &PlInfo, filename: impl AsRef<str>) -> anyhow::Result<()> {
     debug!("saving player data...");
-    File::create(filename.as_ref())?
-        .write_all(ron::ser::to_string_pretty(info, PrettyConfig::default())?.as_bytes())
-        .map_err(anyhow::Error::new)
+    let data = ron::ser::to_string_pretty(info, PrettyConfig::default())?;
+    let mut file = File::create(filename.as_ref())?;
+    if filename.as_ref().ends_with(".gz") {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(data.as_bytes())?;
+        encoder.finish()?;
+        Ok(())
+    } else {
+        file.write_all(data.as_bytes()).map_err(anyhow::Error::new)
+    }
 }
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::{
+    proxy::SplinterProxy,
+    systems::SplinterSystem,
+};
+
+inventory::submit! {
+    SplinterSystem {
+        name: "Protocol Detection",
+        init: Box::new(|proxy| {
+            Box::pin(detect_protocols(proxy))
+        }),
+    }
+}
+
+/// Learns the protocol version of every server that doesn't have one set in the config. Servers
+/// that can't be reached keep assuming the proxy's protocol until they are reconnected to
+async fn detect_protocols(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+    let mut server_ids = proxy.servers.read().await.keys().copied().collect::<Vec<_>>();
+    server_ids.sort_unstable();
+    for server_id in server_ids {
+        if let Err(e) = proxy.detect_server_protocol(server_id).await {
+            warn!(
+                "Failed to detect the protocol of server {}: {:#}",
+                server_id, e
+            );
+        }
+    }
+    Ok(())
+}
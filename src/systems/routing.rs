@@ -0,0 +1,141 @@
+use std::{
+    net::{
+        IpAddr,
+        SocketAddr,
+    },
+    sync::Arc,
+};
+
+use anyhow::Context;
+
+use crate::proxy::{
+    config::IpRouteAction,
+    SplinterProxy,
+};
+
+/// What a routing provider decided for a player logging in
+pub enum Route {
+    /// Leave the decision to other providers, and then to the zones
+    Continue,
+    /// Put the player on the server with this id
+    Server(u64),
+    /// Refuse the login, telling the player why
+    Deny(String),
+}
+
+pub type RouteFn = Box<dyn Send + Sync + Fn(&Arc<SplinterProxy>, &SocketAddr, &str) -> Route>;
+/// A hook that decides where a player logging in is put, given the address they connect from and
+/// their name, such as to refuse some address ranges or to send players to a server near them
+///
+/// Providers are consulted in no particular order before the zones are, and the first one that
+/// doesn't return `Route::Continue` decides. If none decide, the player joins the server for their
+/// position. A player put on a server that doesn't own their position is moved to the one that
+/// does by the zoner once they are in.
+pub struct RoutingProvider {
+    pub name: &'static str,
+    pub route: RouteFn,
+}
+
+inventory::collect!(RoutingProvider);
+
+/// Asks the routing providers where a player logging in should go
+pub fn route_login(proxy: &Arc<SplinterProxy>, addr: &SocketAddr, name: &str) -> Route {
+    for provider in inventory::iter::<RoutingProvider> {
+        match (provider.route)(proxy, addr, name) {
+            Route::Continue => {}
+            route => {
+                debug!("routing provider \"{}\" decided the route for {}", provider.name, name);
+                return route;
+            }
+        }
+    }
+    Route::Continue
+}
+
+inventory::submit! {
+    RoutingProvider {
+        name: "IP Routes",
+        route: Box::new(|proxy, addr, _name| {
+            let ip = addr.ip();
+            for route in proxy.config.ip_routes.iter() {
+                // the networks were validated when the config was loaded
+                let matches = parse_network(&route.network)
+                    .map(|network| in_network(network, ip))
+                    .unwrap_or(false);
+                if matches {
+                    return match &route.action {
+                        IpRouteAction::Server(server_id) => Route::Server(*server_id),
+                        IpRouteAction::Deny(reason) => Route::Deny(reason.clone()),
+                    };
+                }
+            }
+            Route::Continue
+        }),
+    }
+}
+
+/// Parses a network in CIDR notation, or a single address, into its address and prefix length
+pub fn parse_network(network: &str) -> anyhow::Result<(IpAddr, u8)> {
+    let (addr, prefix_len) = match network.split_once('/') {
+        Some((addr, prefix_len)) => (
+            addr,
+            Some(
+                prefix_len
+                    .parse::<u8>()
+                    .with_context(|| format!("Invalid prefix length in \"{}\"", network))?,
+            ),
+        ),
+        None => (network, None),
+    };
+    let addr = addr
+        .parse::<IpAddr>()
+        .with_context(|| format!("Invalid address in \"{}\"", network))?;
+    let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix_len = prefix_len.unwrap_or(max_prefix_len);
+    if prefix_len > max_prefix_len {
+        bail!("Prefix length of \"{}\" is longer than the address", network);
+    }
+    Ok((addr, prefix_len))
+}
+
+/// Whether an address is in a network. Addresses of the other IP version never are, except for
+/// IPv4 addresses mapped into IPv6
+pub fn in_network((network, prefix_len): (IpAddr, u8), addr: IpAddr) -> bool {
+    let (network, addr, prefix_len) = match (network, addr) {
+        (IpAddr::V4(network), IpAddr::V4(addr)) => (
+            u32::from(network) as u128,
+            u32::from(addr) as u128,
+            prefix_len + 96,
+        ),
+        (IpAddr::V4(network), IpAddr::V6(addr)) => match addr.to_ipv4_mapped() {
+            Some(addr) => (
+                u32::from(network) as u128,
+                u32::from(addr) as u128,
+                prefix_len + 96,
+            ),
+            None => return false,
+        },
+        (IpAddr::V6(network), IpAddr::V6(addr)) => {
+            (u128::from(network), u128::from(addr), prefix_len)
+        }
+        (IpAddr::V6(_), IpAddr::V4(_)) => return false,
+    };
+    // the addresses are compared as if they were both 128 bits
+    let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+    network & mask == addr & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_mapped_ipv6_addresses_match_ipv4_networks() {
+        let network = parse_network("0.0.0.0/0").unwrap();
+        assert!(in_network(network, "127.0.0.1".parse().unwrap()));
+        assert!(in_network(network, "::ffff:127.0.0.1".parse().unwrap()));
+        // the loopback and other IPv4 compatible addresses aren't IPv4 addresses
+        assert!(!in_network(network, "::1".parse().unwrap()));
+        assert!(!in_network(network, "::7f00:1".parse().unwrap()));
+    }
+}
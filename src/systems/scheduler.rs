@@ -0,0 +1,102 @@
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{
+    Local,
+    NaiveTime,
+};
+use smol::Timer;
+
+use crate::{
+    proxy::{
+        config::CommandSchedule,
+        SplinterProxy,
+    },
+    systems::{
+        commands::{
+            process_command,
+            CommandSender,
+        },
+        SplinterSystem,
+    },
+};
+
+inventory::submit! {
+    SplinterSystem {
+        name: "Command Scheduler",
+        init: Box::new(|proxy| {
+            Box::pin(init(proxy))
+        }),
+    }
+}
+
+/// When a scheduled command runs, parsed from the config
+enum Schedule {
+    Interval(Duration),
+    Daily(NaiveTime),
+}
+
+impl Schedule {
+    fn parse(schedule: &CommandSchedule) -> anyhow::Result<Self> {
+        Ok(match schedule {
+            CommandSchedule::Interval(0) => bail!("Schedule interval can't be zero"),
+            CommandSchedule::Interval(secs) => Schedule::Interval(Duration::from_secs(*secs)),
+            CommandSchedule::Daily(time) => Schedule::Daily(
+                NaiveTime::parse_from_str(time, "%H:%M")
+                    .map_err(|e| anyhow!("Invalid daily schedule time \"{}\": {}", time, e))?,
+            ),
+        })
+    }
+    /// How long to wait from now until the next time the command runs
+    fn until_next(&self) -> Duration {
+        match self {
+            Schedule::Interval(interval) => *interval,
+            Schedule::Daily(time) => {
+                let until = *time - Local::now().time();
+                let until = if until <= chrono::Duration::zero() {
+                    until + chrono::Duration::days(1)
+                } else {
+                    until
+                };
+                // always positive at this point
+                until.to_std().unwrap()
+            }
+        }
+    }
+}
+
+async fn init(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
+    // parse everything first, so that a mistake in any schedule stops the proxy from starting
+    let mut schedules = vec![];
+    for scheduled in proxy.config.scheduled_commands.iter() {
+        if scheduled.command.trim().is_empty() {
+            bail!("Scheduled command can't be empty");
+        }
+        schedules.push((Schedule::parse(&scheduled.schedule)?, scheduled.command.clone()));
+    }
+    for (schedule, command) in schedules {
+        smol::spawn(run_scheduled_command(Arc::clone(&proxy), schedule, command)).detach();
+    }
+    Ok(())
+}
+
+/// Runs a command as the console every time its schedule comes up
+async fn run_scheduled_command(proxy: Arc<SplinterProxy>, schedule: Schedule, command: String) {
+    let mut split = command.split_whitespace();
+    // commands were checked to not be empty
+    let cmd = split.next().unwrap();
+    let args = split.collect::<Vec<&str>>();
+    loop {
+        Timer::after(schedule.until_next()).await;
+        if !proxy.is_alive() {
+            break;
+        }
+        info!("Running scheduled command: {}", &command);
+        let sender = CommandSender::Console;
+        if let Err(e) = process_command(&proxy, cmd, args.as_slice(), &sender).await {
+            error!("Scheduled command \"{}\" failed: {:?}", &command, e);
+        }
+    }
+}
@@ -1,15 +1,48 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::{BTreeSet, HashMap}, fmt, sync::Arc, time::Duration};
 
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use smol::Timer;
 
-use crate::{proxy::SplinterProxy, systems::SplinterSystem};
+use crate::{
+    proxy::{client::SplinterClient, server::SplinterServer, SplinterProxy},
+    systems::{commands::CommandSender, SplinterSystem},
+};
 
+/// Dimension assumed for players whose dimension isn't known yet
+pub const DEFAULT_DIMENSION: &str = "minecraft:overworld";
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Zone {
     Rectangle { x1: i32, z1: i32, x2: i32, z2: i32 },
     InvertedRectangle { x1: i32, z1: i32, x2: i32, z2: i32 },
 }
 
+/// Describes the zone in chunk coordinates, along with the block coordinates they cover
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, x1, z1, x2, z2) = match self {
+            Self::Rectangle { x1, z1, x2, z2 } => ("rectangle", x1, z1, x2, z2),
+            Self::InvertedRectangle { x1, z1, x2, z2 } => {
+                ("everywhere outside rectangle", x1, z1, x2, z2)
+            }
+        };
+        write!(
+            f,
+            "{} chunks ({}, {}) to ({}, {}) (blocks ({}, {}) to ({}, {}))",
+            name,
+            x1,
+            z1,
+            x2,
+            z2,
+            x1 * 16,
+            z1 * 16,
+            x2 * 16,
+            z2 * 16
+        )
+    }
+}
+
 pub fn world_to_chunk_position((x, z): (f64, f64)) -> (i32, i32) {
     return ((x as i32) >> 4, (z as i32) >> 4);
 }
@@ -23,22 +56,132 @@ impl Zone {
             }
         }
     }
+    /// Whether the zones overlap or share an edge, so that a player can walk from one into the
+    /// other
+    pub fn touches(&self, other: &Zone) -> bool {
+        match (self, other) {
+            (
+                Self::Rectangle { x1, z1, x2, z2 },
+                Self::Rectangle {
+                    x1: ox1,
+                    z1: oz1,
+                    x2: ox2,
+                    z2: oz2,
+                },
+            ) => x1 <= ox2 && ox1 <= x2 && z1 <= oz2 && oz1 <= z2,
+            // a rectangle only misses the outside of another if it's inside without touching the
+            // edges
+            (
+                Self::Rectangle { x1, z1, x2, z2 },
+                Self::InvertedRectangle {
+                    x1: ox1,
+                    z1: oz1,
+                    x2: ox2,
+                    z2: oz2,
+                },
+            )
+            | (
+                Self::InvertedRectangle {
+                    x1: ox1,
+                    z1: oz1,
+                    x2: ox2,
+                    z2: oz2,
+                },
+                Self::Rectangle { x1, z1, x2, z2 },
+            ) => !(x1 > ox1 && x2 < ox2 && z1 > oz1 && z2 < oz2),
+            // both go on forever, so they always overlap
+            (Self::InvertedRectangle { .. }, Self::InvertedRectangle { .. }) => true,
+        }
+    }
 }
 
 pub struct Zoner {
+    /// Zones used in dimensions that don't have their own profile
     pub zones: Vec<(u64, Zone)>,
+    /// Zones for specific dimensions, by dimension name
+    pub dimension_zones: HashMap<String, Vec<(u64, Zone)>>,
 }
 
 impl Zoner {
-    pub fn zones_in_point(&self, (x, z): (i32, i32)) -> SmallVec<[u64; 2]> {
+    /// Gets the zones used in a dimension
+    pub fn profile(&self, dimension: &str) -> &[(u64, Zone)] {
+        self.dimension_zones
+            .get(dimension)
+            .map(Vec::as_slice)
+            .unwrap_or(&self.zones)
+    }
+    pub fn zones_in_point(&self, dimension: &str, (x, z): (i32, i32)) -> SmallVec<[u64; 2]> {
         let mut ids = SmallVec::new();
-        for (server_id, zone) in self.zones.iter() {
+        for (server_id, zone) in self.profile(dimension).iter() {
             if zone.point_in_zone(x, z) {
                 ids.push(*server_id);
             }
         }
         return ids;
     }
+    /// Gets the servers of zones adjacent to the zones containing the point, which the player is
+    /// likely to move onto next. Servers with a zone containing the point aren't included
+    pub fn adjacent_servers(&self, dimension: &str, (x, z): (i32, i32)) -> Vec<u64> {
+        let profile = self.profile(dimension);
+        let (containing, others): (Vec<_>, Vec<_>) = profile
+            .iter()
+            .partition(|(_, zone)| zone.point_in_zone(x, z));
+        let mut ids = Vec::new();
+        for (server_id, zone) in others {
+            if ids.contains(server_id) || containing.iter().any(|(id, _)| id == server_id) {
+                continue;
+            }
+            if containing.iter().any(|(_, current)| current.touches(zone)) {
+                ids.push(*server_id);
+            }
+        }
+        ids
+    }
+    /// Gets the indices and server ids of all zones containing the point, in priority order
+    pub fn matching_zones(&self, dimension: &str, (x, z): (i32, i32)) -> Vec<(usize, u64)> {
+        self.profile(dimension)
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, zone))| zone.point_in_zone(x, z))
+            .map(|(index, (server_id, _))| (index, *server_id))
+            .collect()
+    }
+    /// Gets the index of the first zone containing the point that belongs to the given server
+    pub fn zone_for_server(
+        &self,
+        dimension: &str,
+        (x, z): (i32, i32),
+        server_id: u64,
+    ) -> Option<usize> {
+        self.profile(dimension)
+            .iter()
+            .position(|(id, zone)| *id == server_id && zone.point_in_zone(x, z))
+    }
+}
+
+/// Checks that every zone belongs to a server that exists. Used both when the proxy starts and
+/// when zones are reloaded
+pub fn validate_zone_servers(
+    dimension_zones: &HashMap<String, Vec<(u64, Zone)>>,
+    servers: &HashMap<u64, Arc<SplinterServer>>,
+) -> anyhow::Result<()> {
+    let unknown_servers = dimension_zones
+        .values()
+        .flatten()
+        .map(|(server_id, _)| *server_id)
+        .filter(|server_id| !servers.contains_key(server_id))
+        .collect::<BTreeSet<_>>();
+    if !unknown_servers.is_empty() {
+        bail!(
+            "Zones use servers that don't exist: {}",
+            unknown_servers
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(())
 }
 
 inventory::submit! {
@@ -60,21 +203,85 @@ inventory::submit! {
 pub async fn zoner_loop(proxy: Arc<SplinterProxy>) -> anyhow::Result<()> {
     loop {
         Timer::after(Duration::from_secs(1)).await;
-        for (_, cl) in proxy.players.read().await.iter() {
-            let pl_pos = &**cl.position.load();
-            if let Err(e) = cl
-                .update_touching_servers(
-                    proxy
-                        .zoner
-                        .zones_in_point(world_to_chunk_position((pl_pos.x, pl_pos.z))),
-                )
-                .await
-            {
-                error!(
-                    "Error updating touching servers for player {}: {:?}",
-                    &cl.name, e
-                );
-            }
+        // the players lock isn't held while updating, as switching servers takes it again, which
+        // would wait forever behind a login or logout waiting to write
+        let clients = proxy
+            .players
+            .read()
+            .await
+            .values()
+            .map(Arc::clone)
+            .collect::<Vec<_>>();
+        for cl in clients.iter() {
+            update_client_servers(&proxy, cl).await;
+        }
+    }
+}
+
+/// Connects the client to the servers of the zones it is in, switching servers if its active
+/// server's zones no longer contain it, unless switching is suppressed for it right now
+pub async fn update_client_servers(proxy: &Arc<SplinterProxy>, cl: &Arc<SplinterClient>) {
+    let suppression_window = Duration::from_secs_f64(proxy.config.respawn_switch_suppression_secs);
+    if cl.zoning_suppressed(suppression_window).await {
+        return;
+    }
+    let pl_pos = &**cl.position.load();
+    let chunk_pos = world_to_chunk_position((pl_pos.x, pl_pos.z));
+    let dimension = cl.dimension.load_full();
+    let zoner = proxy.zoner.load_full();
+    let adjacent = if proxy.config.prewarm_adjacent {
+        zoner.adjacent_servers(&dimension, chunk_pos)
+    } else {
+        Vec::new()
+    };
+    if let Err(e) = cl
+        .update_touching_servers(zoner.zones_in_point(&dimension, chunk_pos), adjacent)
+        .await
+    {
+        error!(
+            "Error updating touching servers for player {}: {:?}",
+            &cl.name, e
+        );
+        return;
+    }
+    update_current_zone(proxy, cl, &dimension, chunk_pos).await;
+}
+
+/// Updates which zone the client is in, sending the configured leave and enter messages if it
+/// changed
+///
+/// The current zone is the zone of the client's active server, so this only changes when the
+/// client switches servers, and is debounced by the overlap between zones in the same way. Zone
+/// indices are within the profile of the client's dimension.
+async fn update_current_zone(
+    proxy: &Arc<SplinterProxy>,
+    client: &Arc<SplinterClient>,
+    dimension: &str,
+    chunk_pos: (i32, i32),
+) {
+    let new_zone = proxy
+        .zoner
+        .load()
+        .zone_for_server(dimension, chunk_pos, client.server_id());
+    let old_zone = {
+        let mut current_zone = client.current_zone.lock().await;
+        if *current_zone == new_zone {
+            return;
+        }
+        std::mem::replace(&mut *current_zone, new_zone)
+    };
+    let leave_msg = old_zone
+        .and_then(|index| proxy.config.zone_messages.get(&index))
+        .and_then(|msgs| msgs.leave.as_ref());
+    let enter_msg = new_zone
+        .and_then(|index| proxy.config.zone_messages.get(&index))
+        .and_then(|msgs| msgs.enter.as_ref());
+    for msg in leave_msg.into_iter().chain(enter_msg) {
+        if let Err(e) = client
+            .send_message(msg.as_str(), &CommandSender::Console)
+            .await
+        {
+            error!("Failed to send zone message to {}: {:?}", &client.name, e);
         }
     }
 }